@@ -0,0 +1,54 @@
+// Copyright 2024 Barbagus
+//
+// This file is part of slmlib.
+//
+// slmlib is free software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// slmlib is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without even
+// the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU General
+// Public License for more details.
+// You should have received a copy of the GNU General Public License along with slmlib. If not, see
+// <https://www.gnu.org/licenses/>.
+
+//! A tool to print the per segment breakdown of the Burdell score of a Score My Line (SML) file as
+//! CSV, e.g. to plot where the points were lost.
+
+use slmlib::{burdell, files};
+use std::{env, fs, path::PathBuf};
+
+fn main() {
+    let mut args = env::args().skip(1);
+    let input_path: PathBuf = args.next().expect("no input file specified").into();
+    let settings = match args.next().as_deref() {
+        None | Some("pro") => burdell::LVL_PRO,
+        Some("amateur") => burdell::LVL_AMATEUR,
+        Some("newbie") => burdell::LVL_NEWBIE,
+        Some(level) => panic!("unknown level: {}", level),
+    };
+
+    let buf = fs::read(input_path).expect("read input file");
+    let attempt = files::sml::load(&buf).expect("load SML file");
+    let (start, end) = attempt.route();
+    let mission = slmlib::analyze(start, end, attempt.track());
+
+    let details = burdell::compute_score_detailed(settings, &mission);
+
+    println!(
+        "# score: {:.2}, exponent: {:.6}",
+        details.score, details.exponent
+    );
+    println!("Index,Start,End,MaxDeviation,Fill,Penalty");
+    for segment in details.segments {
+        println!(
+            "{},{:.3},{:.3},{:.3},{:?},{:.6}",
+            segment.index,
+            segment.start,
+            segment.end,
+            segment.max_deviation,
+            segment.fill,
+            segment.penalty
+        );
+    }
+}
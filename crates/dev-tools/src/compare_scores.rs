@@ -17,9 +17,15 @@
 //!
 //! The expected results have been manually collected from the site and organized in so called
 //! "fix" files.
+//!
+//! With `--all`, a table is displayed for every combination of Burdell segmentation strategy and
+//! gap-fill policy, followed by a summary of the mean absolute errors.
+
+mod fixtures;
 
-use slmlib::{self, burdell, files};
-use std::fs;
+use fixtures::{load_fixtures, Fixture};
+use slmlib::burdell::{self, BurdellSettings, GapFill, Segmentation};
+use std::env;
 
 fn fmt_err(err: f64) -> String {
     let s = format!("{:.2}", err);
@@ -37,22 +43,21 @@ fn fmt_err(err: f64) -> String {
         None => String::from("-"),
         Some(index) => match index {
             0 => panic!(),
-            1 => format!("{}", s),
+            1 => s,
             2 => format!("*{}*", s),
             _ => format!("**{}**", s),
         },
     }
 }
 
-fn main() {
-    let mut paths = fs::read_dir("../../fixtures")
-        .unwrap()
-        .map(|e| e.unwrap())
-        .filter(|e| e.metadata().unwrap().is_file())
-        .map(|e| e.path())
-        .filter(|p| p.extension().is_some_and(|e| e == "sml"))
-        .collect::<Vec<_>>();
-    paths.sort();
+/// Print the table of scores and errors of the three levels, with a given segmentation strategy and
+/// gap-fill policy, returning the mean absolute errors
+fn print_table(fixtures: &[Fixture], segmentation: Segmentation, gap_fill: GapFill) -> [f64; 3] {
+    let levels = [burdell::LVL_PRO, burdell::LVL_AMATEUR, burdell::LVL_NEWBIE].map(|level| {
+        level
+            .with_segmentation(segmentation)
+            .with_gap_fill(gap_fill)
+    });
 
     println!(
         "| {} |",
@@ -81,58 +86,79 @@ fn main() {
         .join("|")
     );
 
-    for sml_path in paths {
-        let name = sml_path
-            .file_stem()
-            .unwrap()
-            .to_os_string()
-            .into_string()
-            .unwrap();
-
-        let fix_path = sml_path.clone().with_extension("json");
-
-        let mission = {
-            let attempt = {
-                let buf = fs::read(sml_path).expect("read SML file");
-                files::sml::load(&buf).expect("load SML file")
-            };
-            let (start, end) = attempt.route();
-            let track = attempt.track().collect::<Vec<_>>();
-            slmlib::analyze(start, end, track)
-        };
-
-        let fix = {
-            let buf = fs::read(fix_path).expect("read FIX file");
-            files::fix::load(&buf).expect("parse FIX file")
-        };
-
-        for score in fix.scores.into_iter().filter(|s| s.ignore.is_none()) {
-            let pro = {
-                let burdell_score = burdell::compute_score(burdell::LVL_PRO, &mission);
-                (burdell_score, burdell_score - score.scores.pro)
-            };
-            let amateur = {
-                let burdell_score = burdell::compute_score(burdell::LVL_AMATEUR, &mission);
-                (burdell_score, burdell_score - score.scores.amateur)
-            };
-            let newbie = {
-                let burdell_score = burdell::compute_score(burdell::LVL_NEWBIE, &mission);
-                (burdell_score, burdell_score - score.scores.newbie)
-            };
-
-            println!(
-                "| {} |",
-                [
-                    format!("{:18}", name),
-                    format!("{:12.2}", pro.0),
-                    format!("{:>12}", fmt_err(pro.1)),
-                    format!("{:12.2}", amateur.0),
-                    format!("{:>12}", fmt_err(amateur.1)),
-                    format!("{:12.2}", newbie.0),
-                    format!("{:>12}", fmt_err(newbie.1)),
-                ]
-                .join(" | ")
-            );
+    let mut errors = [0.0; 3];
+    let mut count = 0;
+
+    for fixture in fixtures {
+        for expected in &fixture.expected {
+            let scores = levels
+                .map(|level: BurdellSettings| burdell::compute_score(level, &fixture.mission));
+            let expected = [expected.pro, expected.amateur, expected.newbie];
+
+            let mut cells = vec![format!("{:18}", fixture.name)];
+            for (i, (score, expected)) in scores.into_iter().zip(expected).enumerate() {
+                cells.push(format!("{:12.2}", score));
+                cells.push(format!("{:>12}", fmt_err(score - expected)));
+                errors[i] += (score - expected).abs();
+            }
+            count += 1;
+
+            println!("| {} |", cells.join(" | "));
+        }
+    }
+
+    errors.map(|error| error / count as f64)
+}
+
+fn main() {
+    let fixtures = load_fixtures();
+
+    if !env::args().skip(1).any(|arg| arg == "--all") {
+        print_table(&fixtures, Segmentation::default(), GapFill::default());
+        return;
+    }
+
+    let mut summary = Vec::new();
+    for segmentation in Segmentation::ALL {
+        for gap_fill in GapFill::ALL {
+            println!("### {} / {}\n", segmentation.to_str(), gap_fill.to_str());
+            let errors = print_table(&fixtures, segmentation, gap_fill);
+            println!();
+            summary.push((segmentation, gap_fill, errors));
         }
     }
+
+    println!("### Mean absolute errors\n");
+    println!(
+        "| {} |",
+        [
+            "Segmentation ",
+            "Gap fill          ",
+            "Pro    ",
+            "Amateur",
+            "Newbie ",
+        ]
+        .join(" | ")
+    );
+    println!(
+        "|{}|",
+        [
+            ":--------------",
+            ":-------------------",
+            "--------:",
+            "--------:",
+            "--------:",
+        ]
+        .join("|")
+    );
+    for (segmentation, gap_fill, errors) in summary {
+        println!(
+            "| {:13} | {:18} | {:7.3} | {:7.3} | {:7.3} |",
+            segmentation.to_str(),
+            gap_fill.to_str(),
+            errors[0],
+            errors[1],
+            errors[2]
+        );
+    }
 }
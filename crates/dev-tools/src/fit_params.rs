@@ -0,0 +1,330 @@
+// Copyright 2024 Barbagus
+//
+// This file is part of slmlib.
+//
+// slmlib is free software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// slmlib is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without even
+// the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU General
+// Public License for more details.
+// You should have received a copy of the GNU General Public License along with slmlib. If not, see
+// <https://www.gnu.org/licenses/>.
+
+//! A tool to search the Burdell parameters (step, coefficient, exponent base and segmentation
+//! strategy) that best reproduce the scores published by [scoremyline.com](https://scoremyline.com)
+//! for the fixtures, level by level.
+//!
+//! The step is searched over a grid around the level's current value, then refined around the best
+//! grid value; for every step and segmentation strategy, the coefficient and the exponent base are
+//! fitted with a Nelder-Mead simplex, minimizing the sum of squared errors. The search is
+//! deterministic. Being rather heavy on the Pro level, it is best run in release mode:
+//! ```text
+//! $ cargo run --release --bin fit-params [pro|amateur|newbie]...
+//! ```
+
+mod fixtures;
+
+use fixtures::{load_fixtures, Fixture};
+use slmlib::{
+    burdell::{self, BurdellSettings, Segmentation},
+    files::fix::BurdellLevel,
+};
+use std::{env, f64::consts::LN_10};
+
+/// Factors of the level's current step, the step grid is made of
+const STEP_FACTORS: [f64; 11] = [0.5, 0.6, 0.7, 0.8, 0.9, 1.0, 1.1, 1.25, 1.5, 1.75, 2.0];
+
+/// Number of subdivisions of the grid intervals around the best step, for refinement
+const STEP_REFINEMENT: usize = 8;
+
+/// A level, as published by scoremyline
+struct Level {
+    name: &'static str,
+    settings: BurdellSettings,
+    expected: fn(&BurdellLevel) -> f64,
+}
+
+const LEVELS: [Level; 3] = [
+    Level {
+        name: "pro",
+        settings: burdell::LVL_PRO,
+        expected: |scores| scores.pro,
+    },
+    Level {
+        name: "amateur",
+        settings: burdell::LVL_AMATEUR,
+        expected: |scores| scores.amateur,
+    },
+    Level {
+        name: "newbie",
+        settings: burdell::LVL_NEWBIE,
+        expected: |scores| scores.newbie,
+    },
+];
+
+/// A mission's expected score, along with what its score depends on beside the coefficient and the
+/// exponent base (given a step and a segmentation strategy)
+struct Sample<'a> {
+    name: &'a str,
+    expected: f64,
+    log_length: f64,
+    /// Logarithms of the (non zero) segment deviations
+    log_deviations: Vec<f64>,
+}
+
+impl Sample<'_> {
+    /// Burdell score, as `100 - sum(100 * (deviation / coefficient) ^ log_base(route_length))`
+    fn score(&self, log_coefficient: f64, log_base: f64) -> f64 {
+        let exponent = self.log_length / log_base;
+        let penalties: f64 = self
+            .log_deviations
+            .iter()
+            .map(|log_deviation| (exponent * (log_deviation - log_coefficient)).exp())
+            .sum();
+        f64::max(100.0 - 100.0 * penalties, 0.0)
+    }
+}
+
+fn samples<'a>(
+    fixtures: &'a [Fixture],
+    level: &Level,
+    step: f64,
+    segmentation: Segmentation,
+) -> Vec<Sample<'a>> {
+    let settings = BurdellSettings::new(step, level.settings.coefficient())
+        .expect("valid step")
+        .with_segmentation(segmentation);
+
+    fixtures
+        .iter()
+        .flat_map(|fixture| {
+            let details = burdell::compute_score_detailed(settings, &fixture.mission);
+            let log_deviations: Vec<f64> = details
+                .segments
+                .iter()
+                .filter(|segment| segment.max_deviation > 0.0)
+                .map(|segment| segment.max_deviation.ln())
+                .collect();
+            fixture.expected.iter().map(move |expected| Sample {
+                name: &fixture.name,
+                expected: (level.expected)(expected),
+                log_length: fixture.mission.route_length.ln(),
+                log_deviations: log_deviations.clone(),
+            })
+        })
+        .collect()
+}
+
+/// Sum of squared errors
+fn sse(samples: &[Sample], log_coefficient: f64, log_base: f64) -> f64 {
+    samples
+        .iter()
+        .map(|sample| (sample.score(log_coefficient, log_base) - sample.expected).powi(2))
+        .sum()
+}
+
+/// Minimize a function of two variables with the Nelder-Mead simplex method, returning the minimum
+/// and its value
+fn nelder_mead(f: impl Fn([f64; 2]) -> f64, start: [f64; 2], scale: f64) -> ([f64; 2], f64) {
+    const MAX_ITERATIONS: usize = 500;
+    const TOLERANCE: f64 = 1e-10;
+
+    let at = |x: [f64; 2]| (x, f(x));
+    let towards =
+        |a: [f64; 2], b: [f64; 2], t: f64| [a[0] + t * (b[0] - a[0]), a[1] + t * (b[1] - a[1])];
+
+    let mut simplex = [
+        at(start),
+        at([start[0] + scale, start[1]]),
+        at([start[0], start[1] + scale]),
+    ];
+
+    for _ in 0..MAX_ITERATIONS {
+        simplex.sort_by(|a, b| a.1.total_cmp(&b.1));
+        let [best, second, worst] = simplex;
+        if worst.1 - best.1 <= TOLERANCE * (1.0 + best.1.abs())
+            && (worst.0[0] - best.0[0]).abs() + (worst.0[1] - best.0[1]).abs() <= TOLERANCE
+        {
+            break;
+        }
+
+        let centroid = towards(best.0, second.0, 0.5);
+        let reflected = at(towards(centroid, worst.0, -1.0));
+
+        simplex[2] = if reflected.1 < best.1 {
+            let expanded = at(towards(centroid, worst.0, -2.0));
+            if expanded.1 < reflected.1 {
+                expanded
+            } else {
+                reflected
+            }
+        } else if reflected.1 < second.1 {
+            reflected
+        } else {
+            let contracted = if reflected.1 < worst.1 {
+                at(towards(centroid, reflected.0, 0.5))
+            } else {
+                at(towards(centroid, worst.0, 0.5))
+            };
+            if contracted.1 < f64::min(reflected.1, worst.1) {
+                contracted
+            } else {
+                // shrink towards the best point
+                simplex[1] = at(towards(best.0, second.0, 0.5));
+                at(towards(best.0, worst.0, 0.5))
+            }
+        };
+    }
+
+    simplex.sort_by(|a, b| a.1.total_cmp(&b.1));
+    simplex[0]
+}
+
+/// A set of parameters, along with its sum of squared errors
+#[derive(Debug, Clone, Copy)]
+struct Fit {
+    segmentation: Segmentation,
+    step: f64,
+    coefficient: f64,
+    base: f64,
+    sse: f64,
+}
+
+/// Fit the coefficient and the exponent base, for a given step and segmentation strategy
+fn fit(fixtures: &[Fixture], level: &Level, step: f64, segmentation: Segmentation) -> Fit {
+    let samples = samples(fixtures, level, step, segmentation);
+
+    // the base is searched as `exp(exp(x))`, so that it stays above 1
+    let ([log_coefficient, log_log_base], sse) = nelder_mead(
+        |[log_coefficient, log_log_base]| sse(&samples, log_coefficient, log_log_base.exp()),
+        [level.settings.coefficient().ln(), LN_10.ln()],
+        0.1,
+    );
+
+    Fit {
+        segmentation,
+        step,
+        coefficient: log_coefficient.exp(),
+        base: log_log_base.exp().exp(),
+        sse,
+    }
+}
+
+/// Fit all the parameters of a level
+fn fit_level(fixtures: &[Fixture], level: &Level) -> Fit {
+    let steps = STEP_FACTORS.map(|factor| factor * level.settings.step());
+
+    let mut best: Option<Fit> = None;
+    for segmentation in Segmentation::ALL {
+        let grid = steps.map(|step| fit(fixtures, level, step, segmentation));
+        let (i, _) = grid
+            .iter()
+            .enumerate()
+            .min_by(|(_, a), (_, b)| a.sse.total_cmp(&b.sse))
+            .unwrap();
+
+        let low = steps[i.saturating_sub(1)];
+        let high = steps[usize::min(i + 1, steps.len() - 1)];
+        let refined = (1..STEP_REFINEMENT * 2)
+            .map(|k| low + (high - low) * k as f64 / (STEP_REFINEMENT * 2) as f64)
+            .map(|step| fit(fixtures, level, step, segmentation));
+
+        for candidate in grid.into_iter().chain(refined) {
+            if best.is_none_or(|best| candidate.sse < best.sse) {
+                best.replace(candidate);
+            }
+        }
+    }
+    best.unwrap()
+}
+
+fn report(fixtures: &[Fixture], level: &Level, fit: Fit) {
+    let current = samples(
+        fixtures,
+        level,
+        level.settings.step(),
+        level.settings.segmentation(),
+    );
+    let fitted = samples(fixtures, level, fit.step, fit.segmentation);
+    let rms = |samples: &[Sample], coefficient: f64, base: f64| {
+        (sse(samples, coefficient.ln(), base.ln()) / samples.len() as f64).sqrt()
+    };
+
+    println!("## {}\n", level.name);
+    println!(
+        "Best fit: {} segmentation, step {:.3} m, coefficient {:.3}, exponent base {:.4}",
+        fit.segmentation.to_str(),
+        fit.step,
+        fit.coefficient,
+        fit.base
+    );
+    println!(
+        "RMS error: {:.4} (current parameters: {:.4})\n",
+        rms(&fitted, fit.coefficient, fit.base),
+        rms(&current, level.settings.coefficient(), 10.0)
+    );
+
+    println!(
+        "| {} |",
+        [
+            "Mission           ",
+            "Expected",
+            "Current ",
+            "Residual",
+            "Fitted  ",
+            "Residual",
+        ]
+        .join(" | ")
+    );
+    println!(
+        "|{}|",
+        [
+            ":-------------------",
+            "---------:",
+            "---------:",
+            "---------:",
+            "---------:",
+            "---------:",
+        ]
+        .join("|")
+    );
+    for (current, fitted) in current.iter().zip(&fitted) {
+        let current_score = current.score(level.settings.coefficient().ln(), LN_10);
+        let fitted_score = fitted.score(fit.coefficient.ln(), fit.base.ln());
+        println!(
+            "| {:18} | {:8.2} | {:8.2} | {:8.2} | {:8.2} | {:8.2} |",
+            fitted.name,
+            fitted.expected,
+            current_score,
+            current_score - current.expected,
+            fitted_score,
+            fitted_score - fitted.expected,
+        );
+    }
+    println!();
+}
+
+fn main() {
+    let names: Vec<String> = env::args().skip(1).collect();
+    let levels: Vec<&Level> = if names.is_empty() {
+        LEVELS.iter().collect()
+    } else {
+        names
+            .iter()
+            .map(|name| {
+                LEVELS
+                    .iter()
+                    .find(|level| level.name == name)
+                    .unwrap_or_else(|| panic!("unknown level: {}", name))
+            })
+            .collect()
+    };
+
+    let fixtures = load_fixtures();
+    for level in levels {
+        let fit = fit_level(&fixtures, level);
+        report(&fixtures, level, fit);
+    }
+}
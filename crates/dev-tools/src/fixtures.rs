@@ -0,0 +1,83 @@
+// Copyright 2024 Barbagus
+//
+// This file is part of slmlib.
+//
+// slmlib is free software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// slmlib is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without even
+// the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU General
+// Public License for more details.
+// You should have received a copy of the GNU General Public License along with slmlib. If not, see
+// <https://www.gnu.org/licenses/>.
+
+//! The missions of the fixtures directory, along with the scores published for them by
+//! [scoremyline.com](https://scoremyline.com).
+
+use slmlib::{
+    self,
+    files::{self, fix::BurdellLevel},
+    Slm,
+};
+use std::fs;
+
+/// A mission, with the scores expected for it
+pub struct Fixture {
+    pub name: String,
+    pub mission: Slm,
+    /// Expected scores, for every non lenient result
+    pub expected: Vec<BurdellLevel>,
+}
+
+/// Load the SML files of the fixtures directory, along with their fix files
+pub fn load_fixtures() -> Vec<Fixture> {
+    let mut paths = fs::read_dir("../../fixtures")
+        .unwrap()
+        .map(|e| e.unwrap())
+        .filter(|e| e.metadata().unwrap().is_file())
+        .map(|e| e.path())
+        .filter(|p| p.extension().is_some_and(|e| e == "sml"))
+        .collect::<Vec<_>>();
+    paths.sort();
+
+    paths
+        .into_iter()
+        .map(|sml_path| {
+            let name = sml_path
+                .file_stem()
+                .unwrap()
+                .to_os_string()
+                .into_string()
+                .unwrap();
+
+            let fix_path = sml_path.clone().with_extension("json");
+
+            let mission = {
+                let attempt = {
+                    let buf = fs::read(sml_path).expect("read SML file");
+                    files::sml::load(&buf).expect("load SML file")
+                };
+                let (start, end) = attempt.route();
+                let track = attempt.track().collect::<Vec<_>>();
+                slmlib::analyze(start, end, track)
+            };
+
+            let fix = {
+                let buf = fs::read(fix_path).expect("read FIX file");
+                files::fix::load(&buf).expect("parse FIX file")
+            };
+
+            Fixture {
+                name,
+                mission,
+                expected: fix
+                    .scores
+                    .into_iter()
+                    .filter(|s| s.ignore.is_none())
+                    .map(|s| s.scores)
+                    .collect(),
+            }
+        })
+        .collect()
+}
@@ -14,35 +14,64 @@
 
 use anyhow::{anyhow, bail, Result};
 use color_print::cstr;
-use slmlib::{self, burdell, files, geowizard, Coordinates};
-use std::{env, fs, path::PathBuf};
+use slmlib::{
+    self, burdell,
+    files::{
+        self,
+        mission::{Mission, ScoringPreset},
+        Format,
+    },
+    geowizard::{self, RankScheme},
+    scoring::Scorer,
+    Coordinates, Progress, SlmTracker, TrackPoint, TrackerUpdate,
+};
+use std::{
+    env, fs,
+    io::{self, BufRead},
+    path::PathBuf,
+};
 
 const USAGE: &str = cstr!(
     "<bold,underline>Usage:</> slm-cli[.exe] [OPTIONS] FILE
+       slm-cli[.exe] --live (--start POINT --end POINT | --mission FILE)
 
 <bold,underline>Arguments:</>
-  FILE  Input file.
+  FILE  Input file, possibly gzipped (e.g. 'attempt.gpx.gz') or in a zip archive (first track
+        file).
 
 <bold,underline>Options:</>
   -s, --start POINT    Route start point.
   -e, --end POINT      Route end point.
-  -f, --format FORMAT  Input file format (default: input file extension).
+  -m, --mission FILE   Mission definition (JSON): target line, scoring presets, leniency,
+                       exclusion zones, etc.
+  -f, --format FORMAT  Input file format (default: detected from the content, or
+                       input file extension).
+  -d, --delimiter CHAR CSV field delimiter (default: ',').
+  --decimal-comma      CSV numbers with a decimal comma (e.g. '52,606').
+  -t, --track TRACK    GPX track to analyze (default: first).
+  --line-from-gpx      Use the GPX route (two points) or 'start'/'end' waypoints as the target
+                       line.
+  -l, --live           Read NMEA sentences from the standard input (e.g. a GPS receiver) and show
+                       the mission progress at each fix.
   -h, --help           Show this message.
 
 <bold,underline>Values:</>
   POINT   Comma separated coordinates (latitude, longitude) as decimal degrees; north and east as
           positive values, south and west as negative values. Ex: '52.606,-1.91787'
-  FORMAT  <bold>csv</>: one POINT per line (optional header).
-          <bold>gpx</>: first track.
+  TRACK   Track number (starting from 1) or name.
+  FORMAT  <bold>csv</>: one POINT per line, or columns named after lat/lon/time/ele (optional
+          header).
+          <bold>fit</>: record messages (Garmin activity).
+          <bold>geojson</>: LineString, MultiLineString or Points (optional start/end properties).
+          <bold>gpx</>: first track (or TRACK), all segments.
+          <bold>kml</>: 'Track' or first LineString (optional 'Target line', or 'Start'/'End' Points).
+          <bold>kmz</>: zipped kml.
+          <bold>nmea</>: GGA and RMC sentences.
+          <bold>sml</>: scoremyline attempt (with target line).
+          <bold>tcx</>: first activity (all laps).
 "
 );
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
-enum Format {
-    Csv,
-    Gpx,
-}
-
 fn parse_point(value: &str) -> Result<Coordinates> {
     let (lat, lon) = value.split_once(',').ok_or(anyhow!("No comma found."))?;
     let lat = lat.parse::<f64>()?;
@@ -53,127 +82,317 @@ fn parse_point(value: &str) -> Result<Coordinates> {
     })
 }
 
+fn format_duration(seconds: f64) -> String {
+    let seconds = seconds.round() as u64;
+    format!(
+        "{}:{:02}:{:02}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+fn print_update(fix: &files::nmea::Fix, update: &TrackerUpdate, scheme: &RankScheme) {
+    let time = fix
+        .point
+        .time
+        .map(|t| t.to_string())
+        .unwrap_or_else(|| "-".into());
+    let progress = match update.progress {
+        Progress::Standby => "standby".to_string(),
+        Progress::EnRoute { made_good, .. } => format!(
+            "{:.2} km, {:.1} m off",
+            made_good / 1000_f64,
+            update.progress.deviation().unwrap_or(0.0)
+        ),
+        Progress::Arrived => "arrived".to_string(),
+    };
+    let rank = update.rank.and_then(|r| scheme.name(r)).unwrap_or("-");
+    println!(
+        "{}  {}  (max. {:.1} m, {}, {:.1} %)",
+        time, progress, update.max_deviation, rank, update.score
+    );
+}
+
+fn track_live(start: Coordinates, end: Coordinates, scheme: RankScheme) -> Result<()> {
+    let mut tracker = SlmTracker::new(start, end)?.with_rank_scheme(scheme.clone());
+    let mut parser = files::nmea::Parser::new();
+
+    for line in io::stdin().lock().lines() {
+        if let Some(fix) = parser.parse_line(&line?)? {
+            print_update(&fix, &tracker.update(fix)?, &scheme);
+        }
+    }
+    if let Some(fix) = parser.finish() {
+        print_update(&fix, &tracker.update(fix)?, &scheme);
+    }
+
+    Ok(())
+}
+
 fn main() -> Result<()> {
     let mut start: Option<Coordinates> = None;
     let mut end: Option<Coordinates> = None;
     let mut input_format: Option<Format> = None;
     let mut input_path: Option<PathBuf> = None;
+    let mut live = false;
+    let mut track_selector: Option<String> = None;
+    let mut line_from_gpx = false;
+    let mut csv_options = files::csv::CsvOptions::new();
+    let mut csv_dialect = false;
+    let mut mission: Option<Mission> = None;
 
     let mut args = env::args().skip(1);
 
-    loop {
-        if let Some(arg) = args.next() {
-            match arg.as_str() {
-                "-s" | "--start" => {
-                    let value = args.next().ok_or(anyhow!(
-                        "option {} requires a <POINT> value.\n\n{}",
-                        arg,
-                        USAGE
-                    ))?;
-                    start.replace(parse_point(&value)?);
-                }
-                "-e" | "--end" => {
-                    let value = args.next().ok_or(anyhow!(
-                        "option {} requires a POINT value.\n\n{}",
-                        arg,
-                        USAGE
-                    ))?;
-                    end.replace(parse_point(&value)?);
-                }
-                "-f" | "--format" => match args
-                    .next()
-                    .ok_or(anyhow!(
-                        "option {} requires a 'csv' or 'gpx' value.\n\n{}",
-                        arg,
-                        USAGE
-                    ))?
-                    .as_str()
-                {
-                    "csv" => {
-                        input_format.replace(Format::Csv);
-                    }
-                    "gpx" => {
-                        input_format.replace(Format::Gpx);
-                    }
-                    token => {
-                        bail!("Unsupported input format: {}\n\n{}", token, USAGE);
-                    }
-                },
-                "-h" | "--help" => {
-                    println!("{}", USAGE);
-                    return Ok(());
-                }
-                token => {
-                    if token.starts_with("-") {
-                        bail!("Unsupported option: {}\n\n{}", token, USAGE);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-s" | "--start" => {
+                let value = args.next().ok_or(anyhow!(
+                    "option {} requires a <POINT> value.\n\n{}",
+                    arg,
+                    USAGE
+                ))?;
+                start.replace(parse_point(&value)?);
+            }
+            "-e" | "--end" => {
+                let value = args.next().ok_or(anyhow!(
+                    "option {} requires a POINT value.\n\n{}",
+                    arg,
+                    USAGE
+                ))?;
+                end.replace(parse_point(&value)?);
+            }
+            "-m" | "--mission" => {
+                let value = args.next().ok_or(anyhow!(
+                    "option {} requires a FILE value.\n\n{}",
+                    arg,
+                    USAGE
+                ))?;
+                mission.replace(files::mission::load(&fs::read(value)?)?);
+            }
+            "-f" | "--format" => {
+                let value = args.next().ok_or(anyhow!(
+                    "option {} requires a FORMAT value.\n\n{}",
+                    arg,
+                    USAGE
+                ))?;
+                input_format.replace(
+                    value
+                        .parse::<Format>()
+                        .map_err(|_| anyhow!("Unsupported input format: {}\n\n{}", value, USAGE))?,
+                );
+            }
+            "-d" | "--delimiter" => {
+                let value = args.next().ok_or(anyhow!(
+                    "option {} requires a CHAR value.\n\n{}",
+                    arg,
+                    USAGE
+                ))?;
+                let delimiter = match value.as_str() {
+                    "\\t" | "tab" => '\t',
+                    value => {
+                        let mut chars = value.chars();
+                        match (chars.next(), chars.next()) {
+                            (Some(c), None) => c,
+                            _ => bail!("Invalid delimiter: {}\n\n{}", value, USAGE),
+                        }
                     }
-                    input_path.replace(token.into());
+                };
+                csv_options = csv_options.delimiter(delimiter);
+                csv_dialect = true;
+            }
+            "--decimal-comma" => {
+                csv_options = csv_options.decimal_comma(true);
+                csv_dialect = true;
+            }
+            "-t" | "--track" => {
+                let value = args.next().ok_or(anyhow!(
+                    "option {} requires a TRACK value.\n\n{}",
+                    arg,
+                    USAGE
+                ))?;
+                track_selector.replace(value);
+            }
+            "--line-from-gpx" => {
+                line_from_gpx = true;
+            }
+            "-l" | "--live" => {
+                live = true;
+            }
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return Ok(());
+            }
+            token => {
+                if token.starts_with("-") {
+                    bail!("Unsupported option: {}\n\n{}", token, USAGE);
                 }
+                input_path.replace(token.into());
             }
-        } else {
-            break;
         }
     }
 
+    if let Some(mission) = &mission {
+        if start.is_some() || end.is_some() || line_from_gpx {
+            bail!(
+                "Options '--start', '--end' and '--line-from-gpx' conflict with '--mission'.\n\n{}",
+                USAGE
+            );
+        }
+        start.replace(mission.start);
+        end.replace(mission.end);
+    }
+
+    if live {
+        let (Some(start), Some(end)) = (start, end) else {
+            bail!("Live mode requires start and end points.\n\n{}", USAGE);
+        };
+        let scheme = mission
+            .as_ref()
+            .map(Mission::rank_scheme)
+            .unwrap_or_default();
+        return track_live(start, end, scheme);
+    }
+
     let input_path = if let Some(input_path) = input_path {
         input_path
     } else {
         bail!("Missing input file.\n\n{}", USAGE);
     };
 
-    let input_format = if let Some(input_format) = input_format {
-        input_format
-    } else {
-        let ext = input_path
-            .extension()
-            .and_then(|e| e.to_str())
-            .ok_or(anyhow!(
-                "Unable to determine input format from file extension; consider '-f' option.\n\n{}",
-                USAGE
-            ))?;
-
-        match ext {
-            "csv" => Format::Csv,
-            "gpx" => Format::Gpx,
-            _ => bail!(
-                "Unsupported file extension '{}'; consider '-f' option.\n\n{}",
-                ext,
-                USAGE
-            ),
-        }
-    };
+    let mut buf = fs::read(&input_path)?;
+
+    // gzipped file, or zip archive (other than a KMZ document)
+    if input_format != Some(Format::Kmz) && files::detect(&buf).is_none() {
+        buf = files::archive::decompress(&buf)?.into_owned();
+    }
+
+    let input_format = input_format
+        .or_else(|| files::detect(&buf))
+        .or_else(|| {
+            let name = input_path.file_name()?.to_str()?;
+            let (_, ext) = name.strip_suffix(".gz").unwrap_or(name).rsplit_once('.')?;
+            Format::from_extension(ext)
+        })
+        .ok_or(anyhow!(
+            "Unable to determine input format; consider '-f' option.\n\n{}",
+            USAGE
+        ))?;
+
+    if input_format != Format::Gpx && (track_selector.is_some() || line_from_gpx) {
+        bail!(
+            "Options '--track' and '--line-from-gpx' require a GPX input file.\n\n{}",
+            USAGE
+        );
+    }
 
-    let buf = fs::read(input_path)?;
+    if input_format != Format::Csv && csv_dialect {
+        bail!(
+            "Options '--delimiter' and '--decimal-comma' require a CSV input file.\n\n{}",
+            USAGE
+        );
+    }
 
-    let track = match input_format {
-        Format::Csv => files::csv::load(&buf)?,
-        Format::Gpx => files::gpx::load(&buf)?,
+    let track: Vec<TrackPoint> = match input_format {
+        Format::Csv => csv_options.load(&buf)?,
+        Format::Gpx => {
+            let gpx = files::gpx::load_gpx(&buf)?;
+            if line_from_gpx {
+                let (route_start, route_end) = gpx.target_line().ok_or(anyhow!(
+                    "No target line (two-point route or start/end waypoints) in GPX file."
+                ))?;
+                start = start.or(Some(route_start));
+                end = end.or(Some(route_end));
+            }
+            let track = match &track_selector {
+                None => gpx.tracks.first(),
+                Some(selector) => match selector.parse::<usize>() {
+                    Ok(number) => number.checked_sub(1).and_then(|i| gpx.tracks.get(i)),
+                    Err(_) => gpx.track_by_name(selector),
+                }
+                .ok_or(anyhow!("Track not found in GPX file: {}", selector))
+                .map(Some)?,
+            };
+            track
+                .map(|track| track.points().collect())
+                .unwrap_or_default()
+        }
+        format => {
+            let any = files::load_as(format, &buf)?;
+            start = start.or(any.start);
+            end = end.or(any.end);
+            any.track
+        }
     };
 
     if track.is_empty() {
         bail!("Track is empty.");
     }
 
-    let start = start.unwrap_or_else(|| track.first().unwrap().clone());
-    let end = end.unwrap_or_else(|| track.last().unwrap().clone());
+    let start = start.unwrap_or_else(|| track.first().unwrap().coordinates);
+    let end = end.unwrap_or_else(|| track.last().unwrap().coordinates);
 
-    let stats = slmlib::analyze(start, end, track);
+    let stats = match &mission {
+        Some(mission) => {
+            println!("Mission:                  {}", mission.name);
+            mission.analyze(track)?
+        }
+        None => slmlib::try_analyze(start, end, track)?,
+    };
     println!(
         "Route length:             {:.1} km",
         (stats.route_length / 1000_f64)
     );
     println!("Max. deviation:           {:.1} m", stats.max_deviation);
 
-    let medal = geowizard::compute_rank(&stats);
-    let medal = medal.map(|r| r.to_str()).unwrap_or("-");
-    println!("Medal rank:               {}", medal);
+    if let Some(mission) = &mission {
+        if let (Some(corridor), Some(within)) = (mission.corridor, mission.within_corridor(&stats))
+        {
+            println!(
+                "Corridor:                 {} ({:.0} m)",
+                if within { "within" } else { "outside" },
+                corridor
+            );
+        }
+    }
+
+    let scorers: Vec<Box<dyn Scorer>> = match &mission {
+        Some(mission) if !mission.scoring.is_empty() => {
+            mission.scoring.iter().map(ScoringPreset::scorer).collect()
+        }
+        _ => vec![
+            Box::new(geowizard::MedalRank),
+            Box::new(burdell::LVL_PRO),
+            Box::new(burdell::LVL_AMATEUR),
+            Box::new(burdell::LVL_NEWBIE),
+        ],
+    };
+    for scorer in &scorers {
+        let score = scorer.score(&stats)?;
+        println!("{:<26}{}", format!("{}:", score.name), score);
+    }
 
-    let burdell_score = burdell::compute_score(burdell::LVL_PRO, &stats);
-    println!("Burdell score (PRO):      {:.1} %", burdell_score);
-    let burdell_score = burdell::compute_score(burdell::LVL_AMATEUR, &stats);
-    println!("Burdell score (AMATEUR):  {:.1} %", burdell_score);
-    let burdell_score = burdell::compute_score(burdell::LVL_NEWBIE, &stats);
-    println!("Burdell score (NEWBIE):   {:.1} %", burdell_score);
+    if let Some(timing) = stats.timing() {
+        println!(
+            "Duration:                 {}",
+            format_duration(timing.duration)
+        );
+        println!(
+            "Moving time:              {}",
+            format_duration(timing.moving_time)
+        );
+        println!(
+            "Average speed:            {:.1} km/h",
+            timing.average_speed * 3.6
+        );
+        for (i, split) in timing.splits.iter().enumerate() {
+            println!(
+                "Split #{:<3}                {}",
+                i + 1,
+                format_duration(*split)
+            );
+        }
+    }
 
     Ok(())
 }
@@ -14,59 +14,461 @@
 
 extern crate alloc;
 
-use crate::{Deviation, Point, Progress, Slm};
-use alloc::{vec, vec::Vec};
-use core::iter;
-use libm::{floor, log10, pow};
+use crate::{
+    scoring::{Score, Scorer, ScoringError, Value},
+    Deviation, Point, Progress, Slm,
+};
+use alloc::{format, string::String, vec, vec::Vec};
+use core::{error, fmt, iter};
+use libm::{floor, log10, pow, round};
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
 ///
 /// A Burdell score penalty setting.
 ///
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "UncheckedSettings"))]
 pub struct BurdellSettings {
     /// Distance in meters over which each penalty term is computed (small is more severe).
     step: f64,
     /// A magic number (small is more severe).
     coefficient: f64,
+    segmentation: Segmentation,
+    gap_fill: GapFill,
+}
+
+/// How the route is divided into segments, and which segment a track point falls in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Segmentation {
+    /// Segments of `step` meters from the route start, the last one being shorter: a point falls in
+    /// segment `floor(made_good / step)`.
+    #[default]
+    FloorIndexed,
+    /// Segments of `step` meters centred on the route, the remainder being split between a shorter
+    /// first and last segment.
+    Centred,
+    /// Segments of `step` meters centred on the multiples of `step`: a point falls in segment
+    /// `round(made_good / step)`.
+    Rounded,
+    /// As [`Segmentation::FloorIndexed`], the last (shorter) segment being merged into the
+    /// previous one.
+    LastMerged,
+}
+
+impl Segmentation {
+    pub const ALL: [Segmentation; 4] = [
+        Segmentation::FloorIndexed,
+        Segmentation::Centred,
+        Segmentation::Rounded,
+        Segmentation::LastMerged,
+    ];
+
+    pub fn to_str(&self) -> &'static str {
+        match self {
+            Segmentation::FloorIndexed => "floor-indexed",
+            Segmentation::Centred => "centred",
+            Segmentation::Rounded => "rounded",
+            Segmentation::LastMerged => "last-merged",
+        }
+    }
+}
+
+/// How the deviation of the segments with no track point is filled in, from the closest filled
+/// segments on each side.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum GapFill {
+    /// The mean of the closest filled segments' deviations.
+    #[default]
+    LinearMidpoint,
+    /// The deviation of the closest filled segment (the previous one on a tie).
+    NearestNeighbour,
+    /// The linear interpolation of the closest filled segments' deviations, by distance along the
+    /// route (between the segments' middles).
+    LinearByDistance,
+}
+
+impl GapFill {
+    pub const ALL: [GapFill; 3] = [
+        GapFill::LinearMidpoint,
+        GapFill::NearestNeighbour,
+        GapFill::LinearByDistance,
+    ];
+
+    pub fn to_str(&self) -> &'static str {
+        match self {
+            GapFill::LinearMidpoint => "linear-midpoint",
+            GapFill::NearestNeighbour => "nearest-neighbour",
+            GapFill::LinearByDistance => "linear-by-distance",
+        }
+    }
+}
+
+/// The segments of a route, as divided by some settings
+#[derive(Debug, Clone, Copy)]
+struct Segments {
+    segmentation: Segmentation,
+    step: f64,
+    route_length: f64,
+    count: usize,
+}
+
+impl Segments {
+    fn new(config: &BurdellSettings, route_length: f64) -> Result<Self, ScoreError> {
+        let n = route_length / config.step;
+        let count = match config.segmentation {
+            Segmentation::FloorIndexed => floor(n) + 1.0,
+            Segmentation::Centred => floor(n) + 2.0,
+            Segmentation::Rounded => round(n) + 1.0,
+            Segmentation::LastMerged => f64::max(floor(n), 1.0),
+        };
+        if !(route_length > 0.0 && count.is_finite() && count < usize::MAX as f64) {
+            return Err(ScoreError::RouteLength(route_length));
+        }
+        Ok(Self {
+            segmentation: config.segmentation,
+            step: config.step,
+            route_length,
+            count: count as usize,
+        })
+    }
+
+    /// Length of the shorter first segment of a centred segmentation
+    fn offset(&self) -> f64 {
+        (self.route_length - floor(self.route_length / self.step) * self.step) / 2.0
+    }
+
+    /// Index of the segment a (non negative) made good distance falls in, possibly out of range
+    fn index(&self, made_good: f64) -> usize {
+        match self.segmentation {
+            Segmentation::FloorIndexed => floor(made_good / self.step) as usize,
+            Segmentation::Centred => {
+                let offset = self.offset();
+                if made_good < offset {
+                    0
+                } else {
+                    floor((made_good - offset) / self.step) as usize + 1
+                }
+            }
+            Segmentation::Rounded => round(made_good / self.step) as usize,
+            Segmentation::LastMerged => match floor(made_good / self.step) as usize {
+                index if index == self.count => index - 1,
+                index => index,
+            },
+        }
+    }
+
+    /// Along-track distances of a segment's start and end
+    fn range(&self, index: usize) -> (f64, f64) {
+        let i = index as f64;
+        let (start, end) = match self.segmentation {
+            Segmentation::FloorIndexed | Segmentation::LastMerged => {
+                (i * self.step, (i + 1.0) * self.step)
+            }
+            Segmentation::Centred if index == 0 => (0.0, self.offset()),
+            Segmentation::Centred => (
+                self.offset() + (i - 1.0) * self.step,
+                self.offset() + i * self.step,
+            ),
+            Segmentation::Rounded => ((i - 0.5) * self.step, (i + 0.5) * self.step),
+        };
+        let end = if index == self.count - 1 {
+            self.route_length
+        } else {
+            f64::min(end, self.route_length)
+        };
+        (f64::max(start, 0.0), end)
+    }
+
+    /// Along-track distance of a segment's middle
+    fn middle(&self, index: usize) -> f64 {
+        let (start, end) = self.range(index);
+        (start + end) / 2.0
+    }
+
+    /// Deviation of an unfilled segment, between the filled segments `i1` (of deviation `d1`) and
+    /// `i2` (of deviation `d2`)
+    fn fill(
+        &self,
+        gap_fill: GapFill,
+        index: usize,
+        (i1, d1): (usize, f64),
+        (i2, d2): (usize, f64),
+    ) -> f64 {
+        match gap_fill {
+            GapFill::LinearMidpoint => (d1 + d2) / 2.0,
+            GapFill::NearestNeighbour => {
+                let (m, m1, m2) = (self.middle(index), self.middle(i1), self.middle(i2));
+                if m - m1 <= m2 - m {
+                    d1
+                } else {
+                    d2
+                }
+            }
+            GapFill::LinearByDistance => {
+                let (m, m1, m2) = (self.middle(index), self.middle(i1), self.middle(i2));
+                if m2 > m1 {
+                    d1 + (d2 - d1) * (m - m1) / (m2 - m1)
+                } else {
+                    (d1 + d2) / 2.0
+                }
+            }
+        }
+    }
+}
+
+/// An error occurring while creating Burdell settings.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SettingsError {
+    /// The step is not a positive finite value.
+    Step(f64),
+    /// The coefficient is not a positive finite value.
+    Coefficient(f64),
+}
+
+impl error::Error for SettingsError {}
+
+impl fmt::Display for SettingsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SettingsError::Step(step) => write!(f, "invalid step {}", step),
+            SettingsError::Coefficient(coefficient) => {
+                write!(f, "invalid coefficient {}", coefficient)
+            }
+        }
+    }
+}
+
+impl BurdellSettings {
+    /// Create custom settings, from a (positive) step in meters and a (positive) coefficient.
+    pub fn new(step: f64, coefficient: f64) -> Result<Self, SettingsError> {
+        if !(step.is_finite() && step > 0.0) {
+            return Err(SettingsError::Step(step));
+        }
+        if !(coefficient.is_finite() && coefficient > 0.0) {
+            return Err(SettingsError::Coefficient(coefficient));
+        }
+        Ok(Self {
+            step,
+            coefficient,
+            segmentation: Segmentation::FloorIndexed,
+            gap_fill: GapFill::LinearMidpoint,
+        })
+    }
+
+    /// The same settings, with another segmentation strategy.
+    pub fn with_segmentation(self, segmentation: Segmentation) -> Self {
+        Self {
+            segmentation,
+            ..self
+        }
+    }
+
+    /// The same settings, with another gap-fill policy.
+    pub fn with_gap_fill(self, gap_fill: GapFill) -> Self {
+        Self { gap_fill, ..self }
+    }
+
+    /// Distance in meters over which each penalty term is computed.
+    pub fn step(&self) -> f64 {
+        self.step
+    }
+
+    /// The magic number the deviations are divided by.
+    pub fn coefficient(&self) -> f64 {
+        self.coefficient
+    }
+
+    pub fn segmentation(&self) -> Segmentation {
+        self.segmentation
+    }
+
+    pub fn gap_fill(&self) -> GapFill {
+        self.gap_fill
+    }
+
+    /// Name of the level, for predefined step and coefficient.
+    pub fn level(&self) -> Option<&'static str> {
+        [
+            (LVL_PRO, "PRO"),
+            (LVL_AMATEUR, "AMATEUR"),
+            (LVL_NEWBIE, "NEWBIE"),
+        ]
+        .into_iter()
+        .find(|(level, _)| (level.step, level.coefficient) == (self.step, self.coefficient))
+        .map(|(_, name)| name)
+    }
+}
+
+/// Deserialized, yet to be validated, settings
+#[cfg(feature = "serde")]
+#[derive(Deserialize)]
+struct UncheckedSettings {
+    step: f64,
+    coefficient: f64,
+    #[serde(default)]
+    segmentation: Segmentation,
+    #[serde(default)]
+    gap_fill: GapFill,
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<UncheckedSettings> for BurdellSettings {
+    type Error = SettingsError;
+
+    fn try_from(value: UncheckedSettings) -> Result<Self, Self::Error> {
+        Ok(BurdellSettings::new(value.step, value.coefficient)?
+            .with_segmentation(value.segmentation)
+            .with_gap_fill(value.gap_fill))
+    }
 }
 
 /// The "Pro" penalty settings.
 pub const LVL_PRO: BurdellSettings = BurdellSettings {
     step: 1.0,
     coefficient: 150.0,
+    segmentation: Segmentation::FloorIndexed,
+    gap_fill: GapFill::LinearMidpoint,
 };
 
 /// The "Amateur" penalty settings.
 pub const LVL_AMATEUR: BurdellSettings = BurdellSettings {
     step: 5.0,
     coefficient: 175.0,
+    segmentation: Segmentation::FloorIndexed,
+    gap_fill: GapFill::LinearMidpoint,
 };
 
 /// The "Newbie" penalty settings.
 pub const LVL_NEWBIE: BurdellSettings = BurdellSettings {
     step: 25.0,
     coefficient: 200.0,
+    segmentation: Segmentation::FloorIndexed,
+    gap_fill: GapFill::LinearMidpoint,
 };
 
+/// An error occurring while computing a Burdell score.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ScoreError {
+    /// The route length is not a positive finite value or is too long for the settings' step.
+    RouteLength(f64),
+    /// A track point's "made good" distance lies outside of the route (index of the point).
+    MadeGood(usize),
+}
+
+impl error::Error for ScoreError {}
+
+impl fmt::Display for ScoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScoreError::RouteLength(route_length) => {
+                write!(f, "invalid route length {}", route_length)
+            }
+            ScoreError::MadeGood(index) => {
+                write!(f, "track point #{} made good out of route", index)
+            }
+        }
+    }
+}
+
 ///
 /// Burdell score computation
 ///
+/// # Panics
+/// Panics if the computation fails, see [`try_compute_score`] for a fallible version.
+///
 pub fn compute_score(config: BurdellSettings, slm: &Slm) -> f64 {
-    let segment_count = floor(slm.route_length / config.step) + 1.0;
+    match try_compute_score(config, slm) {
+        Ok(score) => score,
+        Err(error) => panic!("{}", error),
+    }
+}
+
+///
+/// Burdell score computation, failing (instead of panicking) on inconsistent input
+///
+pub fn try_compute_score(config: BurdellSettings, slm: &Slm) -> Result<f64, ScoreError> {
+    Ok(try_compute_score_detailed(config, slm)?.score)
+}
+
+/// How the deviation of a segment was obtained.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum SegmentFill {
+    /// First or last segment, with no track point: zero deviation.
+    Boundary,
+    /// Max deviation of the track points within the segment.
+    Measured,
+    /// No track point within the segment: filled from the closest filled segments on each side (the
+    /// gap-filling pass, see [`GapFill`]).
+    Interpolated,
+}
+
+/// A segment of the route, as accounted in a Burdell score.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SegmentScore {
+    /// Index of the segment, from the route start.
+    pub index: usize,
+    /// Along-track distance (in meters) of the segment's start.
+    pub start: f64,
+    /// Along-track distance (in meters) of the segment's end (the route length for the last one).
+    pub end: f64,
+    /// Deviation (in meters) accounted for the segment.
+    pub max_deviation: f64,
+    pub fill: SegmentFill,
+    /// Penalty (in percentage points) of the segment.
+    pub penalty: f64,
+}
+
+/// A Burdell score, along with its breakdown.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ScoreDetails {
+    /// The score (in percent).
+    pub score: f64,
+    /// The exponent the normalized deviations are raised to: `log10(route_length)`.
+    pub exponent: f64,
+    pub segments: Vec<SegmentScore>,
+}
+
+///
+/// Burdell score computation, with the detail of every segment
+///
+/// # Panics
+/// Panics if the computation fails, see [`try_compute_score_detailed`] for a fallible version.
+///
+pub fn compute_score_detailed(config: BurdellSettings, slm: &Slm) -> ScoreDetails {
+    match try_compute_score_detailed(config, slm) {
+        Ok(details) => details,
+        Err(error) => panic!("{}", error),
+    }
+}
+
+///
+/// Burdell score computation, with the detail of every segment, failing (instead of panicking) on
+/// inconsistent input
+///
+pub fn try_compute_score_detailed(
+    config: BurdellSettings,
+    slm: &Slm,
+) -> Result<ScoreDetails, ScoreError> {
+    let division = Segments::new(&config, slm.route_length)?;
 
-    let mut segments: Vec<Option<f64>> = vec![None; segment_count as usize];
+    let mut segments: Vec<Option<(f64, SegmentFill)>> = vec![None; division.count];
     let mut filled_segments: Vec<usize> = Vec::with_capacity(segments.len());
 
-    segments.first_mut().unwrap().replace(0.0);
+    segments[0].replace((0.0, SegmentFill::Boundary));
     filled_segments.push(0);
 
-    segments.last_mut().unwrap().replace(0.0);
-    filled_segments.push(segments.len() - 1);
+    let last = segments.len() - 1;
+    segments[last].replace((0.0, SegmentFill::Boundary));
+    filled_segments.push(last);
 
-    for point in slm.track.iter() {
+    for (i, point) in slm.track.iter().enumerate() {
         let Point { progress, .. } = point;
         let (made_good, deviation) = match progress {
             Progress::Standby => continue,
@@ -84,20 +486,25 @@ pub fn compute_score(config: BurdellSettings, slm: &Slm) -> f64 {
             Progress::Arrived => continue,
         };
 
-        // Trivial segment division: get the best overall results
-        // (we tried "centering" the segments on the total route, it gets worse)
-        let segment_index = floor(made_good / config.step) as usize;
-        let segment = segments.get_mut(segment_index).unwrap();
+        if made_good.is_nan() || made_good < 0.0 {
+            return Err(ScoreError::MadeGood(i));
+        }
+
+        let segment_index = division.index(made_good);
+        let segment = segments
+            .get_mut(segment_index)
+            .ok_or(ScoreError::MadeGood(i))?;
 
         match segment {
-            Some(max_deviation) => {
+            Some((max_deviation, fill)) => {
                 if deviation > *max_deviation {
-                    segment.replace(deviation);
+                    *max_deviation = deviation;
                 }
+                *fill = SegmentFill::Measured;
             }
             None => {
                 filled_segments.push(segment_index);
-                segment.replace(deviation);
+                segment.replace((deviation, SegmentFill::Measured));
             }
         };
     }
@@ -109,18 +516,572 @@ pub fn compute_score(config: BurdellSettings, slm: &Slm) -> f64 {
         filled_segments.iter().skip(1).cloned(),
     ) {
         if i2 - i1 > 1 {
-            let fill = (segments[i1].unwrap() + segments[i2].unwrap()) / 2.0;
-            for segment in segments.iter_mut().take(i2).skip(i1 + 1) {
-                segment.replace(fill);
+            let deviation = |i: usize| segments[i].map_or(0.0, |(deviation, _)| deviation);
+            let (d1, d2) = (deviation(i1), deviation(i2));
+            for (i, segment) in segments.iter_mut().enumerate().take(i2).skip(i1 + 1) {
+                let fill = division.fill(config.gap_fill, i, (i1, d1), (i2, d2));
+                segment.replace((fill, SegmentFill::Interpolated));
             }
         }
     }
 
     let log = log10(slm.route_length);
     let mut penalities: f64 = 0.0;
-    for s in segments {
-        penalities += 100.0 * pow(s.unwrap() / config.coefficient, log);
+    let segments: Vec<SegmentScore> = segments
+        .into_iter()
+        .enumerate()
+        .map(|(index, s)| {
+            let (max_deviation, fill) = s.unwrap_or((0.0, SegmentFill::Interpolated));
+            let penalty = 100.0 * pow(max_deviation / config.coefficient, log);
+            penalities += penalty;
+            let (start, end) = division.range(index);
+            SegmentScore {
+                index,
+                start,
+                end,
+                max_deviation,
+                fill,
+                penalty,
+            }
+        })
+        .collect();
+
+    Ok(ScoreDetails {
+        score: f64::max(100.0 - penalities, 0.0),
+        exponent: log,
+        segments,
+    })
+}
+
+impl Scorer for BurdellSettings {
+    fn name(&self) -> String {
+        let mut parameters = match self.level() {
+            Some(level) => level.into(),
+            None => format!("{} m, {}", self.step, self.coefficient),
+        };
+        if self.segmentation != Segmentation::default() {
+            parameters = format!("{}, {}", parameters, self.segmentation.to_str());
+        }
+        if self.gap_fill != GapFill::default() {
+            parameters = format!("{}, {}", parameters, self.gap_fill.to_str());
+        }
+        format!("Burdell score ({})", parameters)
+    }
+
+    fn score(&self, slm: &Slm) -> Result<Score, ScoringError> {
+        Ok(Score {
+            name: self.name(),
+            value: Value::Number(try_compute_score(*self, slm)?),
+            unit: Some("%".into()),
+            details: vec![
+                ("step".into(), self.step.into()),
+                ("coefficient".into(), self.coefficient.into()),
+                ("segmentation".into(), self.segmentation.to_str().into()),
+                ("gap_fill".into(), self.gap_fill.to_str().into()),
+            ],
+        })
+    }
+}
+
+/// The set of filled segments of a [`RunningScore`], as a hierarchy of bitsets: each level has a bit
+/// per 64-bit word of the level below, set if the word is not zero.
+///
+/// Finding the filled neighbours of a segment takes a word operation per level, that is, at most 4
+/// for up to 16 million segments.
+#[derive(Debug, Clone)]
+struct FilledSegments {
+    levels: Vec<Vec<u64>>,
+}
+
+impl FilledSegments {
+    fn new(count: usize) -> Self {
+        let mut levels = vec![vec![0; count.div_ceil(64)]];
+        while let Some(words) = levels.last().map(Vec::len).filter(|&words| words > 1) {
+            levels.push(vec![0; words.div_ceil(64)]);
+        }
+        Self { levels }
+    }
+
+    fn insert(&mut self, index: usize) {
+        let mut index = index;
+        for level in &mut self.levels {
+            let word = &mut level[index / 64];
+            let was_empty = *word == 0;
+            *word |= 1 << (index % 64);
+            if !was_empty {
+                return;
+            }
+            index /= 64;
+        }
+    }
+
+    /// The last filled segment before `index`
+    fn previous(&self, index: usize) -> Option<usize> {
+        let mut index = index;
+        let mut level = 0;
+        loop {
+            let bits = self.levels.get(level)?[index / 64]
+                & u64::MAX.checked_shr(64 - (index % 64) as u32).unwrap_or(0);
+            if bits != 0 {
+                index = index / 64 * 64 + 63 - bits.leading_zeros() as usize;
+                break;
+            }
+            index /= 64;
+            level += 1;
+        }
+        while level > 0 {
+            level -= 1;
+            index = index * 64 + 63 - self.levels[level][index].leading_zeros() as usize;
+        }
+        Some(index)
+    }
+
+    /// The first filled segment after `index`
+    fn next(&self, index: usize) -> Option<usize> {
+        let mut index = index;
+        let mut level = 0;
+        loop {
+            let bits = self.levels.get(level)?[index / 64]
+                & u64::MAX.checked_shl((index % 64) as u32 + 1).unwrap_or(0);
+            if bits != 0 {
+                index = index / 64 * 64 + bits.trailing_zeros() as usize;
+                break;
+            }
+            index /= 64;
+            level += 1;
+        }
+        while level > 0 {
+            level -= 1;
+            index = index * 64 + self.levels[level][index].trailing_zeros() as usize;
+        }
+        Some(index)
+    }
+}
+
+/// Incremental Burdell score computation, for track points fed one at a time.
+///
+/// Memory is bounded by the route length (one value per segment), regardless of the track length.
+/// Each segment keeps its running max deviation, and only the gaps around an updated segment are
+/// accounted for again: with the (default) linear midpoint gap fill, an update takes constant time.
+#[derive(Debug, Clone)]
+pub(crate) struct RunningScore {
+    config: BurdellSettings,
+    division: Segments,
+    exponent: f64,
+    segments: Vec<Option<f64>>,
+    filled: FilledSegments,
+    penalities: f64,
+}
+
+impl RunningScore {
+    pub(crate) fn new(config: BurdellSettings, route_length: f64) -> Result<Self, ScoreError> {
+        let division = Segments::new(&config, route_length)?;
+
+        let mut segments: Vec<Option<f64>> = vec![None; division.count];
+        let mut filled = FilledSegments::new(division.count);
+        let last = segments.len() - 1;
+        for index in [0, last] {
+            segments[index].replace(0.0);
+            filled.insert(index);
+        }
+
+        Ok(Self {
+            config,
+            division,
+            exponent: log10(route_length),
+            segments,
+            filled,
+            penalities: 0.0,
+        })
+    }
+
+    /// Penalty of a single segment
+    fn penalty(&self, deviation: f64) -> f64 {
+        100.0 * pow(deviation / self.config.coefficient, self.exponent)
+    }
+
+    /// Penalty of the (unfilled) segments between two filled segments
+    fn gap_penalty(&self, i1: usize, i2: usize) -> f64 {
+        if i2 - i1 <= 1 {
+            return 0.0;
+        }
+        let (d1, d2) = (
+            self.segments[i1].unwrap_or(0.0),
+            self.segments[i2].unwrap_or(0.0),
+        );
+        match self.config.gap_fill {
+            GapFill::LinearMidpoint => (i2 - i1 - 1) as f64 * self.penalty((d1 + d2) / 2.0),
+            gap_fill => (i1 + 1..i2)
+                .map(|i| self.penalty(self.division.fill(gap_fill, i, (i1, d1), (i2, d2))))
+                .sum(),
+        }
+    }
+
+    /// Account for an en route track point
+    pub(crate) fn update(&mut self, made_good: f64, deviation: f64) {
+        let index = usize::min(
+            self.division.index(f64::max(made_good, 0.0)),
+            self.segments.len() - 1,
+        );
+
+        if matches!(self.segments[index], Some(max_deviation) if deviation <= max_deviation) {
+            return;
+        }
+
+        let previous = self.filled.previous(index);
+        let next = self.filled.next(index);
+
+        let gaps = |this: &Self| {
+            previous.map_or(0.0, |p| this.gap_penalty(p, index))
+                + next.map_or(0.0, |n| this.gap_penalty(index, n))
+        };
+
+        match self.segments[index] {
+            Some(max_deviation) => self.penalities -= self.penalty(max_deviation) + gaps(self),
+            None => {
+                // first and last segments are always filled
+                if let (Some(p), Some(n)) = (previous, next) {
+                    self.penalities -= self.gap_penalty(p, n);
+                }
+                self.filled.insert(index);
+            }
+        }
+
+        self.segments[index].replace(deviation);
+        self.penalities += self.penalty(deviation) + gaps(self);
+    }
+
+    /// The score, considering the track points so far
+    pub(crate) fn score(&self) -> f64 {
+        f64::max(100.0 - self.penalities, 0.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::super::*;
+    use super::*;
+    use approx::assert_abs_diff_eq;
+    use std::{fs, path};
+
+    fn leniency_score_test(name: &str) {
+        let base = path::Path::new("fixtures");
+
+        let sml = {
+            let sml_path = base.join(name).with_extension("sml");
+            let buf = fs::read(sml_path).expect("read SML file");
+            files::sml::load(&buf).expect("parse SML file")
+        };
+
+        let fix = {
+            let path = base.join(name).with_extension("json");
+            let buf = fs::read(path).expect("read FIX file");
+            files::fix::load(&buf).expect("parse FIX file")
+        };
+
+        let (start, end) = sml.route();
+        let mission = analyze(start, end, sml.track());
+
+        for score in fix.scores {
+            let Some(ignore) = score.ignore else {
+                continue;
+            };
+            let lenient = apply_leniency(&mission, ignore.into());
+            for (settings, published) in [
+                (LVL_PRO, score.scores.pro),
+                (LVL_AMATEUR, score.scores.amateur),
+                (LVL_NEWBIE, score.scores.newbie),
+            ] {
+                let level = settings.level().unwrap();
+                let computed = compute_score(settings, &lenient);
+                match LENIENCY_DISCREPANCIES
+                    .iter()
+                    .find(|(n, i, l, _, _)| (*n, *i, *l) == (name, ignore, level))
+                {
+                    Some((_, _, _, expected, ours)) => {
+                        assert_eq!(published, *expected);
+                        assert_abs_diff_eq!(computed, *ours, epsilon = 1e-2);
+                    }
+                    None => assert_abs_diff_eq!(computed, published, epsilon = LENIENCY_EPSILON),
+                }
+            }
+        }
+    }
+
+    /// Tolerance on the published leniency scores
+    const LENIENCY_EPSILON: f64 = 5e-2;
+
+    /// Known discrepancies on the published leniency scores (see README): mission, ignored
+    /// percentage, level, published score and our score
+    const LENIENCY_DISCREPANCIES: [(&str, i32, &str, f64, f64); 10] = [
+        ("geowizard-wales1a", 5, "AMATEUR", 85.26, 85.42),
+        ("geowizard-wales1a", 5, "NEWBIE", 97.94, 98.00),
+        ("hiiumaa", 1, "AMATEUR", 84.62, 85.26),
+        ("hiiumaa", 1, "NEWBIE", 97.41, 97.49),
+        ("hiiumaa", 2, "AMATEUR", 85.15, 85.75),
+        ("hiiumaa", 2, "NEWBIE", 97.53, 97.61),
+        ("new-forest", 1, "AMATEUR", 70.51, 71.27),
+        ("new-forest", 1, "NEWBIE", 95.52, 95.68),
+        ("new-forest", 2, "AMATEUR", 73.34, 73.97),
+        ("new-forest", 2, "NEWBIE", 96.02, 96.16),
+    ];
+
+    fn route_slm(route_length: f64, made_good: f64) -> Slm {
+        let coordinates = Coordinates {
+            latitude: 0.0,
+            longitude: 0.0,
+        };
+        Slm {
+            route_start: coordinates,
+            route_end: coordinates,
+            route_length,
+            max_deviation: 0.0,
+            track: alloc::vec![Point {
+                coordinates,
+                time: None,
+                elevation: None,
+                progress: Progress::EnRoute {
+                    on_route: coordinates,
+                    made_good,
+                    deviation: None,
+                },
+            }],
+        }
+    }
+
+    macro_rules! try_compute_score_err_tests {
+        ($($f:ident: ($route_length:expr, $made_good:expr) => $err:pat,)*) => {
+        $(
+            #[test]
+            fn $f() {
+                let slm = route_slm($route_length, $made_good);
+                assert!(matches!(try_compute_score(LVL_PRO, &slm), Err($err)));
+            }
+        )*
+        }
+    }
+    try_compute_score_err_tests! {
+        try_compute_score_nan_length: (f64::NAN, 0.0) => ScoreError::RouteLength(_),
+        try_compute_score_zero_length: (0.0, 0.0) => ScoreError::RouteLength(_),
+        try_compute_score_infinite_length: (f64::INFINITY, 0.0) => ScoreError::RouteLength(_),
+        try_compute_score_negative_made_good: (100.0, -5.0) => ScoreError::MadeGood(0),
+        try_compute_score_beyond_made_good: (100.0, 105.0) => ScoreError::MadeGood(0),
+        try_compute_score_nan_made_good: (100.0, f64::NAN) => ScoreError::MadeGood(0),
+    }
+
+    macro_rules! settings_tests {
+        ($($f:ident: ($step:expr, $coefficient:expr) => $result:expr,)*) => {
+        $(
+            #[test]
+            fn $f() {
+                assert_eq!(BurdellSettings::new($step, $coefficient), $result);
+            }
+        )*
+        }
+    }
+    settings_tests! {
+        settings_pro: (1.0, 150.0) => Ok(LVL_PRO),
+        settings_custom: (10.0, 180.0) => Ok(BurdellSettings { step: 10.0, coefficient: 180.0, ..LVL_PRO }),
+        settings_zero_step: (0.0, 150.0) => Err(SettingsError::Step(0.0)),
+        settings_infinite_step: (f64::INFINITY, 150.0) => Err(SettingsError::Step(f64::INFINITY)),
+        settings_negative_coefficient: (1.0, -150.0) => Err(SettingsError::Coefficient(-150.0)),
+    }
+
+    /// A straight line mission, with track points at given made good distances and deviations
+    fn segments_slm(route_length: f64, points: &[(f64, f64)]) -> Slm {
+        let mut slm = route_slm(route_length, 0.0);
+        let point = slm.track.pop().unwrap();
+        slm.track = points
+            .iter()
+            .cloned()
+            .map(|(made_good, deviation)| Point {
+                progress: if made_good < 0.0 {
+                    Progress::Standby
+                } else {
+                    Progress::EnRoute {
+                        on_route: point.coordinates,
+                        made_good,
+                        deviation: Some(Deviation::Left(deviation)),
+                    }
+                },
+                ..point.clone()
+            })
+            .collect();
+        slm
+    }
+
+    #[test]
+    fn compute_score_detailed_segments() {
+        let slm = segments_slm(
+            100.0,
+            &[(-5.0, 50.0), (30.0, 10.0), (40.0, 5.0), (60.0, 20.0)],
+        );
+
+        let settings = BurdellSettings::new(25.0, 100.0).unwrap();
+        let details = compute_score_detailed(settings, &slm);
+
+        assert_eq!(details.exponent, 2.0);
+        let segments: Vec<_> = details
+            .segments
+            .iter()
+            .map(|s| (s.index, s.start, s.end, s.max_deviation, s.fill))
+            .collect();
+        assert_eq!(
+            segments,
+            [
+                (0, 0.0, 25.0, 0.0, SegmentFill::Boundary),
+                (1, 25.0, 50.0, 10.0, SegmentFill::Measured),
+                (2, 50.0, 75.0, 20.0, SegmentFill::Measured),
+                (3, 75.0, 100.0, 10.0, SegmentFill::Interpolated),
+                (4, 100.0, 100.0, 0.0, SegmentFill::Boundary),
+            ]
+        );
+        assert_abs_diff_eq!(details.segments[2].penalty, 4.0, epsilon = 1e-9);
+        assert_abs_diff_eq!(details.score, 100.0 - 1.0 - 4.0 - 1.0, epsilon = 1e-9);
+        assert_eq!(details.score, compute_score(settings, &slm));
+    }
+
+    macro_rules! strategy_tests {
+        ($($f:ident: ($segmentation:expr, $gap_fill:expr) => $segments:expr,)*) => {
+        $(
+            #[test]
+            fn $f() {
+                let slm = segments_slm(110.0, &[(30.0, 10.0), (40.0, 5.0), (60.0, 20.0)]);
+                let settings = BurdellSettings::new(25.0, 100.0)
+                    .unwrap()
+                    .with_segmentation($segmentation)
+                    .with_gap_fill($gap_fill);
+                let details = compute_score_detailed(settings, &slm);
+
+                let segments: Vec<_> = details
+                    .segments
+                    .iter()
+                    .map(|s| (s.start, s.end, s.max_deviation))
+                    .collect();
+                assert_eq!(segments, $segments);
+
+                let mut running = RunningScore::new(settings, slm.route_length).unwrap();
+                for point in &slm.track {
+                    if let Progress::EnRoute { made_good, deviation: Some(Deviation::Left(deviation)), .. } = point.progress {
+                        running.update(made_good, deviation);
+                    }
+                }
+                assert_abs_diff_eq!(running.score(), details.score, epsilon = 1e-9);
+            }
+        )*
+        }
+    }
+    strategy_tests! {
+        strategy_floor_indexed: (Segmentation::FloorIndexed, GapFill::LinearMidpoint) => [
+            (0.0, 25.0, 0.0),
+            (25.0, 50.0, 10.0),
+            (50.0, 75.0, 20.0),
+            (75.0, 100.0, 10.0),
+            (100.0, 110.0, 0.0),
+        ],
+        strategy_centred: (Segmentation::Centred, GapFill::LinearMidpoint) => [
+            (0.0, 5.0, 0.0),
+            (5.0, 30.0, 5.0),
+            (30.0, 55.0, 10.0),
+            (55.0, 80.0, 20.0),
+            (80.0, 105.0, 10.0),
+            (105.0, 110.0, 0.0),
+        ],
+        strategy_centred_nearest: (Segmentation::Centred, GapFill::NearestNeighbour) => [
+            (0.0, 5.0, 0.0),
+            (5.0, 30.0, 0.0),
+            (30.0, 55.0, 10.0),
+            (55.0, 80.0, 20.0),
+            (80.0, 105.0, 0.0),
+            (105.0, 110.0, 0.0),
+        ],
+        strategy_centred_by_distance: (Segmentation::Centred, GapFill::LinearByDistance) => [
+            (0.0, 5.0, 0.0),
+            (5.0, 30.0, 3.75),
+            (30.0, 55.0, 10.0),
+            (55.0, 80.0, 20.0),
+            (80.0, 105.0, 7.5),
+            (105.0, 110.0, 0.0),
+        ],
+        strategy_rounded: (Segmentation::Rounded, GapFill::LinearMidpoint) => [
+            (0.0, 12.5, 0.0),
+            (12.5, 37.5, 10.0),
+            (37.5, 62.5, 20.0),
+            (62.5, 87.5, 10.0),
+            (87.5, 110.0, 0.0),
+        ],
+        strategy_last_merged: (Segmentation::LastMerged, GapFill::NearestNeighbour) => [
+            (0.0, 25.0, 0.0),
+            (25.0, 50.0, 10.0),
+            (50.0, 75.0, 20.0),
+            (75.0, 110.0, 0.0),
+        ],
     }
 
-    f64::max(100.0 - penalities, 0.0)
+    #[test]
+    fn filled_segments() {
+        for count in [1, 2, 64, 65, 4096, 5000] {
+            let mut filled = FilledSegments::new(count);
+            let mut naive = vec![false; count];
+            // a deterministic scattering of the segments
+            for k in 0..count / 3 {
+                let index = (k * 7919) % count;
+                filled.insert(index);
+                naive[index] = true;
+            }
+            for index in 0..count {
+                assert_eq!(
+                    filled.previous(index),
+                    naive[..index].iter().rposition(|&f| f),
+                );
+                assert_eq!(
+                    filled.next(index),
+                    naive[index + 1..]
+                        .iter()
+                        .position(|&f| f)
+                        .map(|i| index + 1 + i),
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn settings_level() {
+        assert_eq!(LVL_NEWBIE.level(), Some("NEWBIE"));
+        assert_eq!(
+            LVL_NEWBIE.with_segmentation(Segmentation::Centred).level(),
+            Some("NEWBIE")
+        );
+        assert_eq!(BurdellSettings::new(25.0, 201.0).unwrap().level(), None);
+    }
+
+    macro_rules! leniency_score_tests {
+        ($($f:ident: $n:expr,)*) => {
+        $(
+            #[test]
+            fn $f() {
+                leniency_score_test($n)
+            }
+        )*
+        }
+    }
+    leniency_score_tests! {
+        leniency_score_archie_iom: "archie-iom",
+        leniency_score_archie_scotland: "archie-scotland",
+        leniency_score_archie_wales_run: "archie-wales-run",
+        leniency_score_archie_wales_walk: "archie-wales-walk",
+        leniency_score_geowizard_iom: "geowizard-iom",
+        leniency_score_geowizard_norway: "geowizard-norway",
+        leniency_score_geowizard_scotland: "geowizard-scotland",
+        leniency_score_geowizard_wales1a: "geowizard-wales1a",
+        leniency_score_geowizard_wales1b: "geowizard-wales1b",
+        leniency_score_geowizard_wales2: "geowizard-wales2",
+        leniency_score_geowizard_wales3: "geowizard-wales3",
+        leniency_score_geowizard_wales4: "geowizard-wales4",
+        leniency_score_hiiumaa: "hiiumaa",
+        leniency_score_muhu: "muhu",
+        leniency_score_new_forest: "new-forest",
+    }
 }
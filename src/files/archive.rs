@@ -0,0 +1,369 @@
+// Copyright 2024 Barbagus
+//
+// This file is part of slmlib.
+//
+// slmlib is free software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// slmlib is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without even
+// the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU General
+// Public License for more details.
+// You should have received a copy of the GNU General Public License along with slmlib. If not, see
+// <https://www.gnu.org/licenses/>.
+
+//! Compressed files: [gzip](https://www.rfc-editor.org/rfc/rfc1952) streams and zip archives.
+//!
+//! Only single member gzip streams are supported, and only stored or deflated zip entries.
+extern crate alloc;
+
+use super::Format;
+use alloc::{borrow::Cow, vec::Vec};
+use core::{error, fmt, str};
+use miniz_oxide::inflate;
+
+#[derive(Debug, Clone)]
+pub enum Error {
+    /// Malformed or unsupported gzip stream, at the given byte offset
+    Gzip(usize),
+    /// Malformed or unsupported zip archive, at the given byte offset
+    Zip(usize),
+    /// No track file in the archive
+    NoTrack,
+    /// Failed to decompress the data
+    Inflate,
+    /// Checksum mismatch of the decompressed data
+    Crc,
+}
+
+impl error::Error for Error {}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Gzip(offset) => write!(f, "invalid gzip stream at byte {}", offset),
+            Error::Zip(offset) => write!(f, "invalid zip archive at byte {}", offset),
+            Error::NoTrack => write!(f, "no track file in archive"),
+            Error::Inflate => write!(f, "failed to decompress data"),
+            Error::Crc => write!(f, "decompressed data checksum mismatch"),
+        }
+    }
+}
+
+pub(crate) const LOCAL_HEADER_SIGNATURE: u32 = 0x04034b50;
+pub(crate) const CENTRAL_HEADER_SIGNATURE: u32 = 0x02014b50;
+pub(crate) const END_SIGNATURE: u32 = 0x06054b50;
+
+pub(crate) const METHOD_STORED: u16 = 0;
+pub(crate) const METHOD_DEFLATED: u16 = 8;
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+const GZIP_FLAG_HCRC: u8 = 0x02;
+const GZIP_FLAG_EXTRA: u8 = 0x04;
+const GZIP_FLAG_NAME: u8 = 0x08;
+const GZIP_FLAG_COMMENT: u8 = 0x10;
+
+pub(crate) fn crc32(buf: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in buf {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = (crc >> 1) ^ (0xedb88320 & (!(crc & 1)).wrapping_add(1));
+        }
+    }
+    !crc
+}
+
+fn u16_at(buf: &[u8], offset: usize) -> Option<u16> {
+    buf.get(offset..offset + 2)
+        .map(|b| u16::from_le_bytes([b[0], b[1]]))
+}
+
+pub(crate) fn u32_at(buf: &[u8], offset: usize) -> Option<u32> {
+    buf.get(offset..offset + 4)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+/// Whether the data is a gzip stream
+pub fn is_gzip(buf: &[u8]) -> bool {
+    buf.starts_with(&GZIP_MAGIC)
+}
+
+/// Whether the data is a zip archive
+pub fn is_zip(buf: &[u8]) -> bool {
+    u32_at(buf, 0) == Some(LOCAL_HEADER_SIGNATURE)
+}
+
+/// Decompress a gzip stream
+pub fn gunzip(buf: &[u8]) -> Result<Vec<u8>, Error> {
+    if !is_gzip(buf) {
+        return Err(Error::Gzip(0));
+    }
+    if buf.get(2) != Some(&(METHOD_DEFLATED as u8)) {
+        return Err(Error::Gzip(2));
+    }
+    let flags = *buf.get(3).ok_or(Error::Gzip(3))?;
+
+    let mut offset = 10;
+    if flags & GZIP_FLAG_EXTRA != 0 {
+        offset += 2 + u16_at(buf, offset).ok_or(Error::Gzip(offset))? as usize;
+    }
+    for flag in [GZIP_FLAG_NAME, GZIP_FLAG_COMMENT] {
+        if flag & flags != 0 {
+            // zero terminated string
+            offset += 1 + buf
+                .get(offset..)
+                .and_then(|rest| rest.iter().position(|b| *b == 0))
+                .ok_or(Error::Gzip(offset))?;
+        }
+    }
+    if flags & GZIP_FLAG_HCRC != 0 {
+        offset += 2;
+    }
+
+    let trailer = buf.len().checked_sub(8).ok_or(Error::Gzip(buf.len()))?;
+    let data = buf.get(offset..trailer).ok_or(Error::Gzip(offset))?;
+    let data = inflate::decompress_to_vec(data).map_err(|_| Error::Inflate)?;
+
+    if u32_at(buf, trailer + 4) != Some(data.len() as u32) {
+        return Err(Error::Gzip(trailer + 4));
+    }
+    if u32_at(buf, trailer) != Some(crc32(&data)) {
+        return Err(Error::Crc);
+    }
+    Ok(data)
+}
+
+/// A file in a zip archive
+#[derive(Debug, Clone)]
+pub struct ZipEntry<'a> {
+    /// Path of the file in the archive (as raw bytes, usually UTF-8 or ASCII)
+    pub name: &'a [u8],
+    method: u16,
+    crc: u32,
+    local: usize,
+    data: &'a [u8],
+}
+
+impl<'a> ZipEntry<'a> {
+    /// Whether the entry is a directory
+    pub fn is_dir(&self) -> bool {
+        self.name.ends_with(b"/")
+    }
+
+    /// The (decompressed) content of the file
+    pub fn contents(&self) -> Result<Cow<'a, [u8]>, Error> {
+        let contents = match self.method {
+            METHOD_STORED => Cow::Borrowed(self.data),
+            METHOD_DEFLATED => {
+                Cow::Owned(inflate::decompress_to_vec(self.data).map_err(|_| Error::Inflate)?)
+            }
+            _ => return Err(Error::Zip(self.local + 8)),
+        };
+        if crc32(&contents) != self.crc {
+            return Err(Error::Crc);
+        }
+        Ok(contents)
+    }
+
+    /// The format of the file, from its extension (ignoring a `.gz` extension)
+    pub fn format(&self) -> Option<Format> {
+        let name = str::from_utf8(self.name).ok()?;
+        let name = name.strip_suffix(".gz").unwrap_or(name);
+        let (_, extension) = name.rsplit_once('.')?;
+        Format::from_extension(extension)
+    }
+}
+
+/// List the entries of a zip archive, as listed in its central directory
+pub fn zip_entries(buf: &[u8]) -> Result<Vec<ZipEntry<'_>>, Error> {
+    // the end of central directory record is at least 22 bytes, followed by up to 64k of comment
+    let end = (0..=buf.len().saturating_sub(22))
+        .rev()
+        .take(22 + 0xffff)
+        .find(|&offset| u32_at(buf, offset) == Some(END_SIGNATURE))
+        .ok_or(Error::Zip(buf.len()))?;
+
+    let u16_at = |offset| u16_at(buf, offset).ok_or(Error::Zip(offset));
+    let u32_at = |offset| u32_at(buf, offset).ok_or(Error::Zip(offset));
+
+    let count = u16_at(end + 10)?;
+    let mut offset = u32_at(end + 16)? as usize;
+    let mut entries = Vec::with_capacity(count as usize);
+
+    for _ in 0..count {
+        if u32_at(offset)? != CENTRAL_HEADER_SIGNATURE {
+            return Err(Error::Zip(offset));
+        }
+        let method = u16_at(offset + 10)?;
+        let crc = u32_at(offset + 16)?;
+        let compressed_size = u32_at(offset + 20)? as usize;
+        let name_length = u16_at(offset + 28)? as usize;
+        let extra_length = u16_at(offset + 30)? as usize;
+        let comment_length = u16_at(offset + 32)? as usize;
+        let local = u32_at(offset + 42)? as usize;
+        let name = buf
+            .get(offset + 46..offset + 46 + name_length)
+            .ok_or(Error::Zip(offset + 46))?;
+        offset += 46 + name_length + extra_length + comment_length;
+
+        if u32_at(local)? != LOCAL_HEADER_SIGNATURE {
+            return Err(Error::Zip(local));
+        }
+        let data = local + 30 + u16_at(local + 26)? as usize + u16_at(local + 28)? as usize;
+        let data = buf
+            .get(data..data + compressed_size)
+            .ok_or(Error::Zip(data))?;
+
+        entries.push(ZipEntry {
+            name,
+            method,
+            crc,
+            local,
+            data,
+        });
+    }
+    Ok(entries)
+}
+
+/// Decompress a file: a gzip stream is inflated, the first track file (i.e. with a known format
+/// extension, possibly gzipped) of a zip archive is extracted; any other data is left as is.
+pub fn decompress(buf: &[u8]) -> Result<Cow<'_, [u8]>, Error> {
+    if is_gzip(buf) {
+        return Ok(Cow::Owned(gunzip(buf)?));
+    }
+    if !is_zip(buf) {
+        return Ok(Cow::Borrowed(buf));
+    }
+
+    let entry = zip_entries(buf)?
+        .into_iter()
+        .find(|entry| {
+            !entry.is_dir() && !entry.name.starts_with(b"__MACOSX/") && entry.format().is_some()
+        })
+        .ok_or(Error::NoTrack)?;
+    let contents = entry.contents()?;
+    if is_gzip(&contents) {
+        return Ok(Cow::Owned(gunzip(&contents)?));
+    }
+    Ok(contents)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use miniz_oxide::deflate;
+
+    const GPX: &[u8] = br#"<gpx><trk><trkseg><trkpt lat="45" lon="7"/></trkseg></trk></gpx>"#;
+
+    fn gzip(data: &[u8], header: &[u8]) -> Vec<u8> {
+        let mut gz = Vec::new();
+        gz.extend_from_slice(header);
+        gz.extend_from_slice(&deflate::compress_to_vec(data, 6));
+        gz.extend_from_slice(&crc32(data).to_le_bytes());
+        gz.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        gz
+    }
+
+    /// A zip archive of stored files
+    fn zip(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut zip = Vec::new();
+        let mut central = Vec::new();
+        for (name, data) in files {
+            let mut common = Vec::new();
+            common.extend_from_slice(&[20, 0, 0, 0, 0, 0, 0, 0, 0x21, 0]);
+            common.extend_from_slice(&crc32(data).to_le_bytes());
+            common.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            common.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            common.extend_from_slice(&(name.len() as u16).to_le_bytes());
+            common.extend_from_slice(&0u16.to_le_bytes());
+
+            central.extend_from_slice(&CENTRAL_HEADER_SIGNATURE.to_le_bytes());
+            central.extend_from_slice(&20u16.to_le_bytes());
+            central.extend_from_slice(&common);
+            central.extend_from_slice(&[0; 10]);
+            central.extend_from_slice(&(zip.len() as u32).to_le_bytes());
+            central.extend_from_slice(name.as_bytes());
+
+            zip.extend_from_slice(&LOCAL_HEADER_SIGNATURE.to_le_bytes());
+            zip.extend_from_slice(&common);
+            zip.extend_from_slice(name.as_bytes());
+            zip.extend_from_slice(data);
+        }
+        let offset = zip.len();
+        zip.extend_from_slice(&central);
+        zip.extend_from_slice(&END_SIGNATURE.to_le_bytes());
+        zip.extend_from_slice(&[0, 0, 0, 0]);
+        zip.extend_from_slice(&(files.len() as u16).to_le_bytes());
+        zip.extend_from_slice(&(files.len() as u16).to_le_bytes());
+        zip.extend_from_slice(&(central.len() as u32).to_le_bytes());
+        zip.extend_from_slice(&(offset as u32).to_le_bytes());
+        zip.extend_from_slice(&[0, 0]);
+        zip
+    }
+
+    #[test]
+    fn crc32_check() {
+        assert_eq!(crc32(b"123456789"), 0xcbf43926);
+    }
+
+    macro_rules! decompress_tests {
+        ($($name:ident: $buf:expr,)*) => {
+        $(
+            #[test]
+            fn $name() {
+                let buf: Vec<u8> = $buf;
+                assert_eq!(&*decompress(&buf).unwrap(), GPX);
+            }
+        )*
+        }
+    }
+
+    decompress_tests! {
+        decompress_plain: GPX.to_vec(),
+        decompress_gzip: gzip(GPX, &[0x1f, 0x8b, 8, 0, 0, 0, 0, 0, 0, 3]),
+        decompress_gzip_name: gzip(GPX, b"\x1f\x8b\x08\x08\x00\x00\x00\x00\x00\x03a.gpx\x00"),
+        decompress_gzip_extra_comment: gzip(
+            GPX,
+            b"\x1f\x8b\x08\x14\x00\x00\x00\x00\x00\x03\x02\x00ab-\x00"
+        ),
+        decompress_zip: zip(&[("readme.txt", b"hello"), ("tracks/a.gpx", GPX), ("tracks/b.gpx", b"")]),
+        decompress_zip_gzip: zip(&[
+            ("__MACOSX/a.gpx.gz", b""),
+            ("a.gpx.gz", &gzip(GPX, &[0x1f, 0x8b, 8, 0, 0, 0, 0, 0, 0, 3])),
+        ]),
+    }
+
+    macro_rules! decompress_err_tests {
+        ($($name:ident: $buf:expr => $err:pat,)*) => {
+        $(
+            #[test]
+            fn $name() {
+                let buf: Vec<u8> = $buf;
+                assert!(matches!(decompress(&buf), Err($err)));
+            }
+        )*
+        }
+    }
+
+    decompress_err_tests! {
+        decompress_err_gzip_method: {
+            let mut gz = gzip(GPX, &[0x1f, 0x8b, 8, 0, 0, 0, 0, 0, 0, 3]);
+            gz[2] = 0;
+            gz
+        } => Error::Gzip(2),
+        decompress_err_gzip_crc: {
+            let mut gz = gzip(GPX, &[0x1f, 0x8b, 8, 0, 0, 0, 0, 0, 0, 3]);
+            let len = gz.len();
+            gz[len - 8] ^= 0xff;
+            gz
+        } => Error::Crc,
+        decompress_err_gzip_truncated: {
+            let gz = gzip(GPX, &[0x1f, 0x8b, 8, 0, 0, 0, 0, 0, 0, 3]);
+            gz[..20].to_vec()
+        } => Error::Inflate | Error::Gzip(_),
+        decompress_err_zip_no_track: zip(&[("readme.txt", b"hello")]) => Error::NoTrack,
+        decompress_err_zip_truncated: zip(&[("a.gpx", GPX)])[..40].to_vec() => Error::Zip(_),
+    }
+}
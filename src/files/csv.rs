@@ -12,7 +12,7 @@
 // You should have received a copy of the GNU General Public License along with slmlib. If not, see
 // <https://www.gnu.org/licenses/>.
 
-//! A straight line mission track as a CSV file. Specifically:
+//! A straight line mission track as a CSV file. By default:
 //!  - Each line of the file should have only one latitude/longitude pair (separated by a comma).
 //!  - Latitudes and longitudes should be expressed in decimal degrees.
 //!  - Degrees North and East should be expressed as positive values.
@@ -28,11 +28,21 @@
 //!52.6062,-1.91788
 //! ```
 //!
+//! Other dialects (delimiter, quotes, comments, decimal comma, column order, time and elevation
+//! columns) can be read with [`CsvOptions`].
+//!
+//! Example:
+//! ```csv
+//!# exported 2024-05-12
+//!"Time";"Alt";"Lng";"Lat"
+//!"2024-05-12T09:41:07Z";"12,5";"-1,91786";"52,6060"
+//!"2024-05-12T09:41:08Z";"12,7";"-1,91787";"52,6061"
+//! ```
 extern crate alloc;
 
-use crate::Coordinates;
-use alloc::vec::Vec;
-use core::{error, fmt, num, str, u64};
+use crate::{Coordinates, Timestamp, TrackPoint};
+use alloc::{borrow::Cow, string::String, vec::Vec};
+use core::{error, fmt, num, str};
 
 #[derive(Debug, Clone)]
 pub enum ErrorKind {
@@ -44,7 +54,10 @@ pub enum ErrorKind {
 
 #[derive(Debug, Clone)]
 pub struct Error {
+    /// Line number (starting from 1)
     pub row: u64,
+    /// Character position of the offending field in the line (starting from 1), 0 for the whole
+    /// line
     pub column: u64,
     pub kind: ErrorKind,
 }
@@ -60,9 +73,9 @@ impl fmt::Display for Error {
         write!(f, "{}:{} ", self.row, self.column)?;
         match self.kind {
             ErrorKind::Overflow => f.write_str("row/line too large"),
-            ErrorKind::Syntax => f.write_str("no comma separator"),
+            ErrorKind::Syntax => f.write_str("missing field or unterminated quote"),
             ErrorKind::Utf8 => f.write_str("invalid utf-8 encoding"),
-            ErrorKind::Value => f.write_str("ill-formed coordinate value"),
+            ErrorKind::Value => f.write_str("ill-formed value"),
         }
     }
 }
@@ -82,7 +95,6 @@ impl From<fmt::Error> for Error {
 }
 
 const LINE_SEP: u8 = b'\n';
-const FIELD_SEP: &str = ",";
 
 macro_rules! inc {
     ($i:expr) => {{
@@ -91,78 +103,334 @@ macro_rules! inc {
     }};
 }
 
-pub fn load(buf: &[u8]) -> Result<Vec<Coordinates>, Error> {
-    let buf = if let Some(b) = buf.last() {
-        if b == &LINE_SEP {
-            &buf[..(buf.len() - 1)]
-        } else {
-            buf
+/// Header row handling
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Header {
+    /// The first row is a header if its latitude field is not a number
+    #[default]
+    Auto,
+    /// The first row is a header
+    Present,
+    /// There is no header row
+    Absent,
+}
+
+/// Recognized header names (case insensitive) of the latitude, longitude, time and elevation
+/// columns
+const LATITUDE_NAMES: [&str; 2] = ["lat", "latitude"];
+const LONGITUDE_NAMES: [&str; 4] = ["lon", "lng", "long", "longitude"];
+const TIME_NAMES: [&str; 3] = ["time", "timestamp", "datetime"];
+const ELEVATION_NAMES: [&str; 4] = ["ele", "elevation", "alt", "altitude"];
+
+/// A CSV dialect and column mapping.
+///
+/// Columns are indexed from 0. Unless explicitly set, they are looked up by name in the header row
+/// (if any), latitude and longitude defaulting to the first and second columns.
+///
+/// ```
+/// use slmlib::files::csv::CsvOptions;
+///
+/// let options = CsvOptions::new()
+///     .delimiter(';')
+///     .decimal_comma(true)
+///     .comment(Some('#'));
+/// let track = options.load(b"# export\nlat;lon\n52,6060;-1,91786\n").unwrap();
+/// assert_eq!(track[0].coordinates.longitude, -1.91786);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CsvOptions {
+    delimiter: char,
+    quote: Option<char>,
+    comment: Option<char>,
+    header: Header,
+    decimal_comma: bool,
+    latitude: Option<usize>,
+    longitude: Option<usize>,
+    time: Option<usize>,
+    elevation: Option<usize>,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        Self {
+            delimiter: ',',
+            quote: Some('"'),
+            comment: None,
+            header: Header::Auto,
+            decimal_comma: false,
+            latitude: None,
+            longitude: None,
+            time: None,
+            elevation: None,
         }
-    } else {
-        return Ok(Vec::new());
-    };
-
-    let capacity: usize = buf
-        .iter()
-        .filter(|c| *c == &LINE_SEP)
-        .count()
-        .checked_add(1)
-        .ok_or(ERR_OVERFLOW.clone())?;
-
-    let mut track: Vec<Coordinates> = Vec::with_capacity(capacity);
-
-    for (i, line) in buf.split(|c| c == &LINE_SEP).enumerate() {
-        let line = match str::from_utf8(line) {
-            Ok(line) => line,
-            Err(_) => {
-                return Err(Error {
-                    row: inc!(i),
-                    column: 0,
-                    kind: ErrorKind::Utf8,
-                });
+    }
+}
+
+/// Column indices of a file
+#[derive(Debug, Clone, Copy)]
+struct Columns {
+    latitude: usize,
+    longitude: usize,
+    time: Option<usize>,
+    elevation: Option<usize>,
+}
+
+impl CsvOptions {
+    /// The default dialect: comma delimiter, double quotes, optional header, latitude then
+    /// longitude
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the field delimiter (e.g. `,`, `;` or `\t`)
+    pub fn delimiter(mut self, delimiter: char) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    /// Set the quote character (if any) enclosing fields; a doubled quote within a quoted field
+    /// stands for the quote itself
+    pub fn quote(mut self, quote: Option<char>) -> Self {
+        self.quote = quote;
+        self
+    }
+
+    /// Set the leading character (if any) of comment lines
+    pub fn comment(mut self, comment: Option<char>) -> Self {
+        self.comment = comment;
+        self
+    }
+
+    /// Set the header row handling
+    pub fn header(mut self, header: Header) -> Self {
+        self.header = header;
+        self
+    }
+
+    /// Read numbers with a decimal comma (e.g. `52,6060`) instead of a decimal point
+    pub fn decimal_comma(mut self, decimal_comma: bool) -> Self {
+        self.decimal_comma = decimal_comma;
+        self
+    }
+
+    /// Set the latitude column
+    pub fn latitude_column(mut self, index: usize) -> Self {
+        self.latitude = Some(index);
+        self
+    }
+
+    /// Set the longitude column
+    pub fn longitude_column(mut self, index: usize) -> Self {
+        self.longitude = Some(index);
+        self
+    }
+
+    /// Set the time column: RFC 3339 timestamps, or seconds since the Unix epoch
+    pub fn time_column(mut self, index: usize) -> Self {
+        self.time = Some(index);
+        self
+    }
+
+    /// Set the elevation column (in meters)
+    pub fn elevation_column(mut self, index: usize) -> Self {
+        self.elevation = Some(index);
+        self
+    }
+
+    /// Split a line into fields, along with their (1-based) character position; `None` on an
+    /// unterminated quote
+    fn fields<'a>(&self, line: &'a str) -> Option<Vec<(u64, Cow<'a, str>)>> {
+        let mut fields = Vec::new();
+        let mut start = 0;
+
+        loop {
+            let rest = &line[start..];
+            let column = line[..start].chars().count() as u64 + 1;
+            let trimmed =
+                rest.trim_start_matches(|c: char| c != self.delimiter && c.is_whitespace());
+
+            let end = match self.quote.filter(|quote| trimmed.starts_with(*quote)) {
+                Some(quote) => {
+                    let body = line.len() - trimmed.len() + quote.len_utf8();
+                    let mut field = String::new();
+                    let mut chars = line[body..].char_indices();
+                    let end = loop {
+                        let (i, c) = chars.next()?;
+                        if c != quote {
+                            field.push(c);
+                        } else if line[body + i + c.len_utf8()..].starts_with(quote) {
+                            chars.next();
+                            field.push(quote);
+                        } else {
+                            break body + i + c.len_utf8();
+                        }
+                    };
+                    fields.push((column, Cow::Owned(field)));
+                    // anything between the closing quote and the delimiter is ignored
+                    line[end..].find(self.delimiter).map(|i| end + i)
+                }
+                None => {
+                    let end = rest.find(self.delimiter).map(|i| start + i);
+                    fields.push((
+                        column,
+                        Cow::Borrowed(&line[start..end.unwrap_or(line.len())]),
+                    ));
+                    end
+                }
+            };
+
+            match end {
+                Some(end) => start = end + self.delimiter.len_utf8(),
+                None => return Some(fields),
             }
+        }
+    }
+
+    fn number(&self, field: &str) -> Option<f64> {
+        let field = field.trim();
+        if self.decimal_comma {
+            field.replace(',', ".").parse::<f64>().ok()
+        } else {
+            field.parse::<f64>().ok()
+        }
+    }
+
+    /// Column indices, from the header (if any)
+    fn columns(&self, header: Option<&[(u64, Cow<'_, str>)]>) -> Columns {
+        let find = |names: &[&str]| {
+            header?
+                .iter()
+                .position(|(_, name)| names.iter().any(|n| name.trim().eq_ignore_ascii_case(n)))
         };
+        Columns {
+            latitude: self.latitude.or_else(|| find(&LATITUDE_NAMES)).unwrap_or(0),
+            longitude: self
+                .longitude
+                .or_else(|| find(&LONGITUDE_NAMES))
+                .unwrap_or(1),
+            time: self.time.or_else(|| find(&TIME_NAMES)),
+            elevation: self.elevation.or_else(|| find(&ELEVATION_NAMES)),
+        }
+    }
+
+    /// Load a track
+    pub fn load(&self, buf: &[u8]) -> Result<Vec<TrackPoint>, Error> {
+        let mut track: Vec<TrackPoint> = Vec::new();
+        let mut columns: Option<Columns> = None;
 
-        let (lhs, rhs) = line.split_once(FIELD_SEP).ok_or(Error {
-            row: inc!(i),
-            column: 0,
-            kind: ErrorKind::Syntax,
-        })?;
-
-        let latitude = match lhs.trim().parse::<f64>() {
-            Ok(lat) => lat,
-            Err(_) => {
-                if i == 0 {
-                    // optional header
-                    continue;
-                } else {
+        for (i, line) in buf.split(|c| c == &LINE_SEP).enumerate() {
+            let row = inc!(i);
+            let line = match str::from_utf8(line) {
+                Ok(line) => line.strip_suffix('\r').unwrap_or(line),
+                Err(_) => {
                     return Err(Error {
-                        row: inc!(i),
+                        row,
                         column: 0,
-                        kind: ErrorKind::Value,
+                        kind: ErrorKind::Utf8,
                     });
                 }
+            };
+
+            if line.trim().is_empty()
+                || self
+                    .comment
+                    .is_some_and(|c| line.trim_start().starts_with(c))
+            {
+                continue;
             }
-        };
 
-        let longitude = match rhs.trim().parse::<f64>() {
-            Ok(lon) => lon,
-            Err(_) => {
-                return Err(Error {
-                    row: inc!(i),
-                    column: inc!(lhs.len()),
+            let fields = self.fields(line).ok_or(Error {
+                row,
+                column: 0,
+                kind: ErrorKind::Syntax,
+            })?;
+
+            let columns = match columns {
+                Some(columns) => columns,
+                None => {
+                    let defaults = self.columns(None);
+                    let is_header = match self.header {
+                        Header::Auto => fields
+                            .get(defaults.latitude)
+                            .and_then(|(_, field)| self.number(field))
+                            .is_none(),
+                        Header::Present => true,
+                        Header::Absent => false,
+                    };
+                    if is_header {
+                        columns.replace(self.columns(Some(&fields)));
+                        continue;
+                    }
+                    *columns.insert(defaults)
+                }
+            };
+
+            let field = |index: usize| {
+                fields.get(index).ok_or(Error {
+                    row,
+                    column: 0,
+                    kind: ErrorKind::Syntax,
+                })
+            };
+            let number = |index: usize| {
+                let (column, value) = field(index)?;
+                self.number(value).ok_or(Error {
+                    row,
+                    column: *column,
                     kind: ErrorKind::Value,
-                });
-            }
-        };
+                })
+            };
+
+            let latitude = number(columns.latitude)?;
+            let longitude = number(columns.longitude)?;
+            let elevation = match columns.elevation {
+                Some(index) if !field(index)?.1.trim().is_empty() => Some(number(index)?),
+                _ => None,
+            };
+            let time = match columns.time {
+                Some(index) if !field(index)?.1.trim().is_empty() => {
+                    let (column, value) = field(index)?;
+                    let value = value.trim();
+                    Some(
+                        value
+                            .parse::<Timestamp>()
+                            .ok()
+                            .or_else(|| {
+                                self.number(value)
+                                    .map(|seconds| Timestamp(libm::round(seconds * 1000.0) as i64))
+                            })
+                            .ok_or(Error {
+                                row,
+                                column: *column,
+                                kind: ErrorKind::Value,
+                            })?,
+                    )
+                }
+                _ => None,
+            };
+
+            track.push(TrackPoint {
+                coordinates: Coordinates {
+                    latitude,
+                    longitude,
+                },
+                time,
+                elevation,
+                speed: None,
+            });
+        }
 
-        track.push(Coordinates {
-            latitude,
-            longitude,
-        });
+        Ok(track)
     }
+}
 
-    Ok(track)
+/// Load a track (coordinates only) with the default options, see [`CsvOptions::new`]
+pub fn load(buf: &[u8]) -> Result<Vec<Coordinates>, Error> {
+    Ok(CsvOptions::default()
+        .load(buf)?
+        .into_iter()
+        .map(|point| point.coordinates)
+        .collect())
 }
 
 #[cfg(test)]
@@ -203,5 +471,122 @@ mod tests {
         load_without_headers: "54.29600470,-4.58877725
 54.29600654,-4.58877590
 54.29600906,-4.58876509",
+        load_crlf: "Latitude,Longitude\r\n54.29600470,-4.58877725\r\n54.29600654,-4.58877590\r\n54.29600906,-4.58876509\r\n",
+        load_blank_lines: "\n54.29600470,-4.58877725\n\n54.29600654,-4.58877590\n54.29600906,-4.58876509\n\n",
+    }
+
+    macro_rules! csv_options_tests {
+            ($($name:ident: $options:expr, $csv:expr,)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    let options: CsvOptions = $options;
+                    let track = options.load($csv.as_bytes()).unwrap();
+                    let check = alloc::vec![
+                        Coordinates{latitude: 54.29600470, longitude: -4.58877725},
+                        Coordinates{latitude: 54.29600654, longitude:-4.58877590},
+                        Coordinates{latitude: 54.29600906, longitude:-4.58876509},
+                    ];
+
+                    assert_eq!(track.iter().map(|p| p.coordinates).collect::<Vec<_>>(), check);
+                }
+            )*
+            }
+        }
+    csv_options_tests! {
+        options_default: CsvOptions::new(), CSV,
+        options_semicolon: CsvOptions::new().delimiter(';'), "lat;lon
+54.29600470;-4.58877725
+54.29600654;-4.58877590
+54.29600906;-4.58876509",
+        options_tab: CsvOptions::new().delimiter('\t'), "54.29600470\t-4.58877725
+54.29600654\t-4.58877590
+54.29600906\t-4.58876509",
+        options_decimal_comma: CsvOptions::new().delimiter(';').decimal_comma(true), "Lat;Lng
+54,29600470;-4,58877725
+54,29600654;-4,58877590
+54,29600906;-4,58876509",
+        options_quoted: CsvOptions::new().decimal_comma(true), r#""Latitude","Longitude"
+"54,29600470","-4,58877725"
+ "54,29600654" ,"-4,58877590"
+"54,29600906","-4,58876509""#,
+        options_header_names: CsvOptions::new(), "id,longitude,name,LAT
+1,-4.58877725,a,54.29600470
+2,-4.58877590,b,54.29600654
+3,-4.58876509,c,54.29600906",
+        options_columns: CsvOptions::new().header(Header::Present).latitude_column(2).longitude_column(0), "x,y,z
+-4.58877725,0,54.29600470
+-4.58877590,0,54.29600654
+-4.58876509,0,54.29600906",
+        options_comments: CsvOptions::new().comment(Some('#')), "# exported by hand
+54.29600470,-4.58877725
+  # a pause
+54.29600654,-4.58877590
+54.29600906,-4.58876509",
+    }
+
+    #[test]
+    fn options_time_elevation() {
+        let track = CsvOptions::new()
+            .delimiter(';')
+            .decimal_comma(true)
+            .load(
+                r#""Time";"Alt";"Lng";"Lat"
+"2024-05-12T09:41:07Z";"12,5";"-4,58877725";"54,29600470"
+"1715506868,5";"";"-4,58877590";"54,29600654"
+;13;-4,58876509;54,29600906"#
+                    .as_bytes(),
+            )
+            .unwrap();
+
+        assert_eq!(
+            track.iter().map(|p| p.time).collect::<Vec<_>>(),
+            [
+                Some(Timestamp(1715506867000)),
+                Some(Timestamp(1715506868500)),
+                None
+            ]
+        );
+        assert_eq!(
+            track.iter().map(|p| p.elevation).collect::<Vec<_>>(),
+            [Some(12.5), None, Some(13.0)]
+        );
+    }
+
+    #[test]
+    fn options_quote_escape() {
+        let options = CsvOptions::new();
+        let fields = options.fields(r#"a,"b ""c"", d",,e"#).unwrap();
+        assert_eq!(
+            fields,
+            [
+                (1, Cow::from("a")),
+                (3, Cow::from(r#"b "c", d"#)),
+                (16, Cow::from("")),
+                (17, Cow::from("e"))
+            ]
+        );
+        assert_eq!(options.fields(r#"a,"b"#), None);
+    }
+
+    macro_rules! csv_load_err_tests {
+            ($($name:ident: $options:expr, $csv:expr => ($row:expr, $column:expr, $kind:pat),)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    let options: CsvOptions = $options;
+                    let err = options.load($csv.as_bytes()).unwrap_err();
+                    assert_eq!((err.row, err.column), ($row, $column));
+                    assert!(matches!(err.kind, $kind));
+                }
+            )*
+            }
+        }
+    csv_load_err_tests! {
+        load_err_missing_field: CsvOptions::new(), "54.29600470,-4.58877725\n54.29600654" => (2, 0, ErrorKind::Syntax),
+        load_err_value: CsvOptions::new(), "54.29600470,-4.58877725\n54.29600654, west" => (2, 13, ErrorKind::Value),
+        load_err_quote: CsvOptions::new(), "\"54.29600470,-4.58877725" => (1, 0, ErrorKind::Syntax),
+        load_err_header: CsvOptions::new().header(Header::Absent), "lat,lon\n54.29600470,-4.58877725" => (1, 1, ErrorKind::Value),
+        load_err_time: CsvOptions::new().time_column(2), "54.29600470,-4.58877725,noon" => (1, 25, ErrorKind::Value),
     }
 }
@@ -0,0 +1,259 @@
+// Copyright 2024 Barbagus
+//
+// This file is part of slmlib.
+//
+// slmlib is free software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// slmlib is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without even
+// the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU General
+// Public License for more details.
+// You should have received a copy of the GNU General Public License along with slmlib. If not, see
+// <https://www.gnu.org/licenses/>.
+
+//! File format detection, from the content or the file extension.
+
+use core::{error, fmt, str};
+
+/// A file format
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Format {
+    Csv,
+    Fit,
+    /// scoremyline results (no track)
+    Fix,
+    GeoJson,
+    Gpx,
+    Kml,
+    Kmz,
+    Nmea,
+    Sml,
+    Tcx,
+}
+
+impl Format {
+    pub const ALL: [Format; 10] = [
+        Format::Csv,
+        Format::Fit,
+        Format::Fix,
+        Format::GeoJson,
+        Format::Gpx,
+        Format::Kml,
+        Format::Kmz,
+        Format::Nmea,
+        Format::Sml,
+        Format::Tcx,
+    ];
+
+    /// The (lowercase) name of the format
+    pub fn to_str(&self) -> &'static str {
+        match self {
+            Format::Csv => "csv",
+            Format::Fit => "fit",
+            Format::Fix => "fix",
+            Format::GeoJson => "geojson",
+            Format::Gpx => "gpx",
+            Format::Kml => "kml",
+            Format::Kmz => "kmz",
+            Format::Nmea => "nmea",
+            Format::Sml => "sml",
+            Format::Tcx => "tcx",
+        }
+    }
+
+    /// The format of a file extension (case insensitive), if known
+    pub fn from_extension(extension: &str) -> Option<Format> {
+        Format::ALL
+            .into_iter()
+            .find(|format| format.to_str().eq_ignore_ascii_case(extension))
+            .or_else(|| match extension.to_ascii_lowercase().as_str() {
+                "json" => Some(Format::GeoJson),
+                "nmea0183" | "nma" => Some(Format::Nmea),
+                _ => None,
+            })
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.to_str())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ParseFormatError;
+
+impl fmt::Display for ParseFormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("unknown file format")
+    }
+}
+
+impl error::Error for ParseFormatError {}
+
+impl str::FromStr for Format {
+    type Err = ParseFormatError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Format::ALL
+            .into_iter()
+            .find(|format| format.to_str() == s)
+            .ok_or(ParseFormatError)
+    }
+}
+
+/// Max. number of bytes looked at, beyond the first bytes of the content
+const SNIFF_LENGTH: usize = 4096;
+
+/// Name of the root element of an XML document (without namespace prefix)
+fn xml_root(text: &str) -> Option<&str> {
+    let mut text = text;
+    loop {
+        text = text.trim_start().strip_prefix('<')?;
+        if let Some(rest) = text.strip_prefix('?') {
+            text = &rest[rest.find("?>")? + 2..];
+        } else if let Some(rest) = text.strip_prefix("!--") {
+            text = &rest[rest.find("-->")? + 3..];
+        } else if let Some(rest) = text.strip_prefix('!') {
+            text = &rest[rest.find('>')? + 1..];
+        } else {
+            let end = text.find(|c: char| c.is_whitespace() || c == '>' || c == '/')?;
+            let name = &text[..end];
+            return Some(name.rsplit(':').next().unwrap_or(name));
+        }
+    }
+}
+
+/// The first key of a JSON object
+fn json_first_key(text: &str) -> Option<&str> {
+    let text = text.strip_prefix('{')?.trim_start().strip_prefix('"')?;
+    Some(&text[..text.find('"')?])
+}
+
+fn is_nmea(line: &str) -> bool {
+    let Some(sentence) = line.strip_prefix('$') else {
+        return false;
+    };
+    let address = sentence.split(',').next().unwrap_or_default();
+    address.len() == 5 && address.bytes().all(|b| b.is_ascii_alphanumeric())
+}
+
+/// Whether a line has (at least) two numeric fields
+fn is_numeric_csv(line: &str) -> bool {
+    [',', ';', '\t'].into_iter().any(|delimiter| {
+        let mut fields = line.split(delimiter).map(|field| {
+            field
+                .trim()
+                .trim_matches('"')
+                .replace(',', ".")
+                .parse::<f64>()
+                .is_ok()
+        });
+        matches!((fields.next(), fields.next()), (Some(true), Some(true)))
+    })
+}
+
+/// Detect the format of a file from its content: XML root element (GPX, KML, TCX), FIT header,
+/// KMZ archive, JSON shape (SML, scoremyline results, GeoJSON), NMEA sentences or numeric CSV.
+pub fn detect(buf: &[u8]) -> Option<Format> {
+    if buf.len() >= 12 && matches!(buf[0], 12 | 14) && &buf[8..12] == b".FIT" {
+        return Some(Format::Fit);
+    }
+    if buf.starts_with(b"PK\x03\x04") {
+        // a KMZ archive has its KML document as the first entry, any other zip archive is unknown
+        let name_length = u16::from_le_bytes([*buf.get(26)?, *buf.get(27)?]) as usize;
+        let name = buf.get(30..30 + name_length)?;
+        return name
+            .to_ascii_lowercase()
+            .ends_with(b".kml")
+            .then_some(Format::Kmz);
+    }
+
+    let buf = buf.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(buf);
+    let buf = &buf[..buf.len().min(SNIFF_LENGTH)];
+    let text = match str::from_utf8(buf) {
+        Ok(text) => text,
+        // the sniffed length may split a character
+        Err(error) if error.error_len().is_none() => {
+            str::from_utf8(&buf[..error.valid_up_to()]).ok()?
+        }
+        Err(_) => return None,
+    };
+    let text = text.trim_start();
+
+    match text.chars().next()? {
+        '<' => match xml_root(text)? {
+            "gpx" => Some(Format::Gpx),
+            "kml" => Some(Format::Kml),
+            "TrainingCenterDatabase" => Some(Format::Tcx),
+            _ => None,
+        },
+        '{' => match json_first_key(text) {
+            Some("Attempt") => Some(Format::Sml),
+            Some("routeLength") | Some("scores") => Some(Format::Fix),
+            _ if text.contains("\"type\"") => Some(Format::GeoJson),
+            _ => None,
+        },
+        '$' => is_nmea(text.lines().next()?).then_some(Format::Nmea),
+        _ => text
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .take(2)
+            .any(is_numeric_csv)
+            .then_some(Format::Csv),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    macro_rules! detect_tests {
+        ($($name:ident: $buf:expr => $format:expr,)*) => {
+        $(
+            #[test]
+            fn $name() {
+                let buf: &[u8] = $buf;
+                assert_eq!(detect(buf), $format);
+            }
+        )*
+        }
+    }
+
+    detect_tests! {
+        detect_gpx: br#"<?xml version="1.0" encoding="UTF-8"?>
+<!-- exported -->
+<gpx version="1.1" xmlns="http://www.topografix.com/GPX/1/1"><trk/></gpx>"# => Some(Format::Gpx),
+        detect_kml: b"\xEF\xBB\xBF<kml xmlns=\"http://www.opengis.net/kml/2.2\">" => Some(Format::Kml),
+        detect_tcx: b"<?xml version=\"1.0\"?>\n<ns:TrainingCenterDatabase xmlns:ns=\"x\">" => Some(Format::Tcx),
+        detect_xml_other: b"<svg></svg>" => None,
+        detect_fit: b"\x0e\x20\x52\x08\x00\x00\x00\x00.FIT\x00\x00" => Some(Format::Fit),
+        detect_kmz: b"PK\x03\x04\x14\x00\x00\x00\x08\x00\x00\x00\x21\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x07\x00\x00\x00doc.kml" => Some(Format::Kmz),
+        detect_zip: b"PK\x03\x04\x14\x00\x00\x00\x08\x00\x00\x00\x21\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x05\x00\x00\x00a.gpx" => None,
+        detect_gzip: b"\x1f\x8b\x08\x00\x00\x00\x00\x00\x00\x03" => None,
+        detect_sml: b"{\n  \"Attempt\": {\"Points\": []}}" => Some(Format::Sml),
+        detect_fix: b"{\"routeLength\": 11.57, \"scores\": []}" => Some(Format::Fix),
+        detect_geojson: b"{\"type\": \"LineString\", \"coordinates\": []}" => Some(Format::GeoJson),
+        detect_geojson_late_type: b"{\"coordinates\": [], \"type\": \"LineString\"}" => Some(Format::GeoJson),
+        detect_json_other: b"{\"name\": 1}" => None,
+        detect_nmea: b"$GPGGA,094107.00,5417.76028,N,00435.32664,W,1,09,0.92,12.5,M,51.2,M,,*7C" => Some(Format::Nmea),
+        detect_csv: b"52.6060,-1.91786\n52.6061,-1.91787" => Some(Format::Csv),
+        detect_csv_header: b"Latitude;Longitude\n52,6060;-1,91786" => Some(Format::Csv),
+        detect_csv_quoted: b"\"lat\",\"lon\"\n\"52.6060\",\"-1.91786\"" => Some(Format::Csv),
+        detect_text: b"hello world\nthis is not a track" => None,
+        detect_empty: b"" => None,
+        detect_binary: b"\xff\xfe\x00\x01" => None,
+    }
+
+    #[test]
+    fn format_names() {
+        for format in Format::ALL {
+            assert_eq!(format.to_str().parse::<Format>().unwrap(), format);
+            assert_eq!(Format::from_extension(format.to_str()), Some(format));
+        }
+        assert_eq!(Format::from_extension("GPX"), Some(Format::Gpx));
+        assert_eq!(Format::from_extension("json"), Some(Format::GeoJson));
+        assert_eq!(Format::from_extension("txt"), None);
+    }
+}
@@ -0,0 +1,512 @@
+// Copyright 2024 Barbagus
+//
+// This file is part of slmlib.
+//
+// slmlib is free software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// slmlib is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without even
+// the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU General
+// Public License for more details.
+// You should have received a copy of the GNU General Public License along with slmlib. If not, see
+// <https://www.gnu.org/licenses/>.
+
+//! A straight line mission track as a Garmin [FIT](https://developer.garmin.com/fit/protocol/)
+//! activity file. Specifically:
+//!  - Only `record` messages are decoded (position, timestamp, altitude and speed), every other
+//!    message is skipped.
+//!  - Records without a (valid) position are ignored.
+//!  - Enhanced altitude and speed fields take precedence over their 16 bits counterparts.
+//!  - Chained FIT files are read in sequence.
+extern crate alloc;
+
+use crate::{Coordinates, Timestamp, TrackPoint};
+use alloc::vec::Vec;
+use core::{error, fmt};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// File checksum mismatch
+    Crc,
+    /// Missing or ill-formed file header
+    Header,
+    /// Data message of an undefined local message type
+    Undefined,
+    /// Unexpected end of file
+    Truncated,
+}
+
+#[derive(Debug, Clone)]
+pub struct Error {
+    /// Byte offset (from the start of the buffer) at which the error was found
+    pub offset: usize,
+    pub kind: ErrorKind,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "byte {}: ", self.offset)?;
+        match self.kind {
+            ErrorKind::Crc => f.write_str("checksum mismatch"),
+            ErrorKind::Header => f.write_str("invalid file header"),
+            ErrorKind::Undefined => f.write_str("undefined local message type"),
+            ErrorKind::Truncated => f.write_str("unexpected end of file"),
+        }
+    }
+}
+
+impl error::Error for Error {}
+
+/// `record` global message number
+const MESG_RECORD: u16 = 20;
+
+const FIELD_POSITION_LAT: u8 = 0;
+const FIELD_POSITION_LONG: u8 = 1;
+const FIELD_ALTITUDE: u8 = 2;
+const FIELD_SPEED: u8 = 6;
+const FIELD_ENHANCED_SPEED: u8 = 73;
+const FIELD_ENHANCED_ALTITUDE: u8 = 78;
+const FIELD_TIMESTAMP: u8 = 253;
+
+/// FIT epoch (1989-12-31T00:00:00Z) in seconds since the Unix epoch
+const FIT_EPOCH: i64 = 631065600;
+
+/// Degrees per semicircle
+const SEMICIRCLE: f64 = 180.0 / 2147483648.0;
+
+const CRC_TABLE: [u16; 16] = [
+    0x0000, 0xCC01, 0xD801, 0x1400, 0xF001, 0x3C00, 0x2800, 0xE401, 0xA001, 0x6C00, 0x7800, 0xB401,
+    0x5000, 0x9C01, 0x8801, 0x4400,
+];
+
+fn crc16(buf: &[u8]) -> u16 {
+    buf.iter().fold(0, |crc, byte| {
+        let crc = (crc >> 4) ^ CRC_TABLE[(crc & 0xF) as usize] ^ CRC_TABLE[(byte & 0xF) as usize];
+        (crc >> 4) ^ CRC_TABLE[(crc & 0xF) as usize] ^ CRC_TABLE[(byte >> 4) as usize]
+    })
+}
+
+/// A field of a definition message
+#[derive(Debug, Clone, Copy)]
+struct Field {
+    number: u8,
+    size: usize,
+}
+
+/// A definition message: the layout of subsequent data messages of the same local type
+#[derive(Debug, Clone, Default)]
+struct Definition {
+    big_endian: bool,
+    global: u16,
+    fields: Vec<Field>,
+    /// Total size of the data messages (including developer fields)
+    size: usize,
+}
+
+/// A FIT file reader over a byte buffer, keeping track of the current offset
+struct Reader<'a> {
+    buf: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], Error> {
+        let bytes = self
+            .buf
+            .get(self.offset..self.offset.saturating_add(n))
+            .ok_or(Error {
+                offset: self.offset,
+                kind: ErrorKind::Truncated,
+            })?;
+        self.offset += n;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, Error> {
+        Ok(self.take(1)?[0])
+    }
+}
+
+fn uint(bytes: &[u8], big_endian: bool) -> u32 {
+    let mut value = [0; 4];
+    match bytes.len() {
+        2 if big_endian => value[2..].copy_from_slice(bytes),
+        4 if big_endian => value.copy_from_slice(bytes),
+        2 | 4 => value[..bytes.len()].copy_from_slice(bytes),
+        _ => return u32::MAX,
+    }
+    if big_endian {
+        u32::from_be_bytes(value)
+    } else {
+        u32::from_le_bytes(value)
+    }
+}
+
+/// Decoded fields of a `record` message
+#[derive(Debug, Default)]
+struct Record {
+    latitude: Option<i32>,
+    longitude: Option<i32>,
+    altitude: Option<u32>,
+    enhanced_altitude: Option<u32>,
+    speed: Option<u32>,
+    enhanced_speed: Option<u32>,
+}
+
+impl Record {
+    fn point(&self, timestamp: Option<u32>) -> Option<TrackPoint> {
+        let latitude = self.latitude? as f64 * SEMICIRCLE;
+        let longitude = self.longitude? as f64 * SEMICIRCLE;
+        Some(TrackPoint {
+            coordinates: Coordinates {
+                latitude,
+                longitude,
+            },
+            time: timestamp.map(|t| Timestamp((FIT_EPOCH + t as i64) * 1000)),
+            elevation: self
+                .enhanced_altitude
+                .or(self.altitude)
+                .map(|a| a as f64 / 5.0 - 500.0),
+            speed: self
+                .enhanced_speed
+                .or(self.speed)
+                .map(|s| s as f64 / 1000.0),
+        })
+    }
+}
+
+/// Load a track from a FIT file
+pub fn load(buf: &[u8]) -> Result<Vec<TrackPoint>, Error> {
+    let mut reader = Reader { buf, offset: 0 };
+    let mut track: Vec<TrackPoint> = Vec::new();
+
+    while reader.offset < buf.len() {
+        load_file(&mut reader, &mut track)?;
+    }
+
+    Ok(track)
+}
+
+/// Load the records of a single (possibly chained) FIT file
+fn load_file(reader: &mut Reader, track: &mut Vec<TrackPoint>) -> Result<(), Error> {
+    let start = reader.offset;
+    let header_error = Error {
+        offset: start,
+        kind: ErrorKind::Header,
+    };
+
+    let header_size = reader.u8()? as usize;
+    if header_size < 12 {
+        return Err(header_error);
+    }
+    let header = reader
+        .take(header_size - 1)
+        .map_err(|_| header_error.clone())?;
+    if &header[7..11] != b".FIT" {
+        return Err(header_error);
+    }
+    if header_size >= 14 {
+        let crc = u16::from_le_bytes([header[11], header[12]]);
+        if crc != 0 && crc != crc16(&reader.buf[start..start + 12]) {
+            return Err(Error {
+                offset: start,
+                kind: ErrorKind::Crc,
+            });
+        }
+    }
+    let data_size = u32::from_le_bytes([header[3], header[4], header[5], header[6]]) as usize;
+    let end = reader.offset.saturating_add(data_size);
+
+    let mut definitions: [Option<Definition>; 16] = Default::default();
+    let mut timestamp: Option<u32> = None;
+
+    while reader.offset < end {
+        let offset = reader.offset;
+        let record_header = reader.u8()?;
+
+        if record_header & 0x80 != 0 {
+            // compressed timestamp header
+            let local = (record_header >> 5) & 0x03;
+            let time_offset = (record_header & 0x1F) as u32;
+            // (rolling over, as 32 bits timestamps do)
+            timestamp = timestamp.map(|last| {
+                let t = (last & !0x1F).wrapping_add(time_offset);
+                if time_offset < last & 0x1F {
+                    t.wrapping_add(0x20)
+                } else {
+                    t
+                }
+            });
+            load_data(reader, &definitions, local, offset, &mut timestamp, track)?;
+        } else if record_header & 0x40 != 0 {
+            // definition message
+            let local = record_header & 0x0F;
+            let developer = record_header & 0x20 != 0;
+            let fixed = reader.take(5)?;
+            let big_endian = fixed[1] == 1;
+            let global = if big_endian {
+                u16::from_be_bytes([fixed[2], fixed[3]])
+            } else {
+                u16::from_le_bytes([fixed[2], fixed[3]])
+            };
+            let count = fixed[4] as usize;
+            let fields = reader
+                .take(count * 3)?
+                .chunks_exact(3)
+                .map(|f| Field {
+                    number: f[0],
+                    size: f[1] as usize,
+                })
+                .collect::<Vec<_>>();
+            let mut size = fields.iter().map(|f| f.size).sum();
+            if developer {
+                let count = reader.u8()? as usize;
+                size += reader
+                    .take(count * 3)?
+                    .chunks_exact(3)
+                    .map(|f| f[1] as usize)
+                    .sum::<usize>();
+            }
+            definitions[local as usize] = Some(Definition {
+                big_endian,
+                global,
+                fields,
+                size,
+            });
+        } else {
+            // normal data message
+            let local = record_header & 0x0F;
+            load_data(reader, &definitions, local, offset, &mut timestamp, track)?;
+        }
+    }
+
+    if reader.offset != end {
+        return Err(Error {
+            offset: end,
+            kind: ErrorKind::Truncated,
+        });
+    }
+
+    let crc_offset = reader.offset;
+    let crc = reader.take(2)?;
+    if u16::from_le_bytes([crc[0], crc[1]]) != crc16(&reader.buf[start..crc_offset]) {
+        return Err(Error {
+            offset: crc_offset,
+            kind: ErrorKind::Crc,
+        });
+    }
+
+    Ok(())
+}
+
+/// Read a data message, pushing a track point if it is a positioned `record`
+fn load_data(
+    reader: &mut Reader,
+    definitions: &[Option<Definition>; 16],
+    local: u8,
+    offset: usize,
+    timestamp: &mut Option<u32>,
+    track: &mut Vec<TrackPoint>,
+) -> Result<(), Error> {
+    let definition = definitions[local as usize].as_ref().ok_or(Error {
+        offset,
+        kind: ErrorKind::Undefined,
+    })?;
+    let mut data = reader.take(definition.size)?;
+
+    let mut record = Record::default();
+    for field in &definition.fields {
+        let (value, rest) = data.split_at(field.size);
+        data = rest;
+
+        let value = uint(value, definition.big_endian);
+        match (field.number, field.size) {
+            (FIELD_TIMESTAMP, 4) if value != u32::MAX => {
+                timestamp.replace(value);
+            }
+            _ if definition.global != MESG_RECORD => continue,
+            (FIELD_POSITION_LAT, 4) if value != i32::MAX as u32 => {
+                record.latitude = Some(value as i32);
+            }
+            (FIELD_POSITION_LONG, 4) if value != i32::MAX as u32 => {
+                record.longitude = Some(value as i32);
+            }
+            (FIELD_ALTITUDE, 2) if value != u16::MAX as u32 => {
+                record.altitude = Some(value);
+            }
+            (FIELD_ENHANCED_ALTITUDE, 4) if value != u32::MAX => {
+                record.enhanced_altitude = Some(value);
+            }
+            (FIELD_SPEED, 2) if value != u16::MAX as u32 => {
+                record.speed = Some(value);
+            }
+            (FIELD_ENHANCED_SPEED, 4) if value != u32::MAX => {
+                record.enhanced_speed = Some(value);
+            }
+            _ => continue,
+        }
+    }
+
+    if definition.global == MESG_RECORD {
+        if let Some(point) = record.point(*timestamp) {
+            track.push(point);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    /// Wrap records into a FIT file (14 bytes header, with checksums)
+    fn fit(records: &[u8]) -> Vec<u8> {
+        let mut buf = vec![14, 0x20, 0x52, 0x08];
+        buf.extend_from_slice(&(records.len() as u32).to_le_bytes());
+        buf.extend_from_slice(b".FIT");
+        buf.extend_from_slice(&crc16(&buf).to_le_bytes());
+        buf.extend_from_slice(records);
+        buf.extend_from_slice(&crc16(&buf).to_le_bytes());
+        buf
+    }
+
+    /// Definition of a `record` (local type 0): timestamp, position, altitude, speed
+    const RECORD_DEFINITION: [u8; 21] = [
+        0x40, 0, 0, 20, 0, 5, // header, reserved, little endian, global 20, 5 fields
+        253, 4, 0x86, // timestamp
+        0, 4, 0x85, // position_lat
+        1, 4, 0x85, // position_long
+        2, 2, 0x84, // altitude
+        6, 2, 0x84, // speed
+    ];
+
+    fn record(header: u8, timestamp: u32, lat: i32, lon: i32, alt: u16, speed: u16) -> Vec<u8> {
+        let mut buf = vec![header];
+        buf.extend_from_slice(&timestamp.to_le_bytes());
+        buf.extend_from_slice(&lat.to_le_bytes());
+        buf.extend_from_slice(&lon.to_le_bytes());
+        buf.extend_from_slice(&alt.to_le_bytes());
+        buf.extend_from_slice(&speed.to_le_bytes());
+        buf
+    }
+
+    /// 2024-05-12T09:41:07Z
+    const TIMESTAMP: u32 = 1715506867 - FIT_EPOCH as u32;
+
+    #[test]
+    fn crc16_check() {
+        assert_eq!(crc16(b"123456789"), 0xBB3D);
+    }
+
+    #[test]
+    fn load_records() {
+        let mut records = RECORD_DEFINITION.to_vec();
+        // 54.296°N 4.58877725°W
+        records.extend(record(0, TIMESTAMP, 647776568, -54746245, 2560, 4250));
+        // an unknown message (global 21, event), skipped
+        records.extend([0x41, 0, 0, 21, 0, 2, 253, 4, 0x86, 0, 1, 0x00]);
+        records.extend([0x01, 1, 2, 3, 4, 5]);
+        // no position
+        records.extend(record(
+            0,
+            TIMESTAMP + 1,
+            i32::MAX,
+            i32::MAX,
+            u16::MAX,
+            u16::MAX,
+        ));
+        records.extend(record(0, TIMESTAMP + 2, 647776600, -54746200, u16::MAX, 0));
+
+        let track = load(&fit(&records)).unwrap();
+        assert_eq!(track.len(), 2);
+
+        approx::assert_abs_diff_eq!(track[0].coordinates.latitude, 54.2960047, epsilon = 1e-6);
+        approx::assert_abs_diff_eq!(track[0].coordinates.longitude, -4.5887773, epsilon = 1e-6);
+        assert_eq!(track[0].time, Some(Timestamp(1715506867000)));
+        assert_eq!(track[0].elevation, Some(12.0));
+        assert_eq!(track[0].speed, Some(4.25));
+
+        assert_eq!(track[1].time, Some(Timestamp(1715506869000)));
+        assert_eq!(track[1].elevation, None);
+        assert_eq!(track[1].speed, Some(0.0));
+    }
+
+    #[test]
+    fn load_compressed_timestamps() {
+        let mut records = RECORD_DEFINITION.to_vec();
+        records.extend(record(0, TIMESTAMP, 0, 0, 0, 0));
+        // position only, local type 1
+        records.extend([0x41, 0, 0, 20, 0, 2, 0, 4, 0x85, 1, 4, 0x85]);
+        // compressed timestamp header, local type 1, 10 seconds later
+        records.push(0x80 | (1 << 5) | ((TIMESTAMP + 10) & 0x1F) as u8);
+        records.extend(1000_i32.to_le_bytes());
+        records.extend(2000_i32.to_le_bytes());
+
+        let track = load(&fit(&records)).unwrap();
+        assert_eq!(track.len(), 2);
+        assert_eq!(track[1].time, Some(Timestamp(1715506877000)));
+    }
+
+    #[test]
+    fn load_compressed_timestamps_rollover() {
+        let mut records = RECORD_DEFINITION.to_vec();
+        records.extend(record(0, 0xFFFF_FFFE, 0, 0, 0, 0));
+        records.extend([0x41, 0, 0, 20, 0, 2, 0, 4, 0x85, 1, 4, 0x85]);
+        // compressed timestamp headers, local type 1: 0xFFFF_FFFF, then 0 (one second later)
+        for time_offset in [0x1F, 0] {
+            records.push(0x80 | (1 << 5) | time_offset);
+            records.extend(1000_i32.to_le_bytes());
+            records.extend(2000_i32.to_le_bytes());
+        }
+
+        let track = load(&fit(&records)).unwrap();
+        assert_eq!(track.len(), 3);
+        assert_eq!(
+            track[1].time,
+            Some(Timestamp((FIT_EPOCH + 0xFFFF_FFFF) * 1000))
+        );
+        assert_eq!(track[2].time, Some(Timestamp(FIT_EPOCH * 1000)));
+    }
+
+    #[test]
+    fn load_big_endian() {
+        let mut records = vec![0x40, 0, 1, 0, 20, 2, 0, 4, 0x85, 1, 4, 0x85, 0x00];
+        records.extend(647776568_i32.to_be_bytes());
+        records.extend((-54746245_i32).to_be_bytes());
+
+        let track = load(&fit(&records)).unwrap();
+        approx::assert_abs_diff_eq!(track[0].coordinates.latitude, 54.2960047, epsilon = 1e-6);
+        approx::assert_abs_diff_eq!(track[0].coordinates.longitude, -4.5887773, epsilon = 1e-6);
+    }
+
+    macro_rules! load_err_tests {
+        ($($name:ident: $buf:expr => ($offset:expr, $kind:expr),)*) => {
+        $(
+            #[test]
+            fn $name() {
+                let buf: Vec<u8> = $buf;
+                let err = load(&buf).unwrap_err();
+                assert_eq!((err.offset, err.kind), ($offset, $kind));
+            }
+        )*
+        }
+    }
+
+    load_err_tests! {
+        load_err_header: b"not a fit file".to_vec() => (0, ErrorKind::Header),
+        load_err_undefined: fit(&[0x03, 0, 0]) => (14, ErrorKind::Undefined),
+        load_err_truncated: {
+            let mut buf = fit(&RECORD_DEFINITION);
+            buf.truncate(20);
+            buf
+        } => (20, ErrorKind::Truncated),
+        load_err_crc: {
+            let mut buf = fit(&RECORD_DEFINITION);
+            buf[20] ^= 0xFF;
+            buf
+        } => (35, ErrorKind::Crc),
+    }
+}
@@ -50,5 +50,5 @@ pub struct SMLScores {
 }
 
 pub fn load(buf: &[u8]) -> Result<SMLScores, serde_json::Error> {
-    Ok(serde_json::from_reader::<_, SMLScores>(buf)?)
+    serde_json::from_reader::<_, SMLScores>(buf)
 }
@@ -0,0 +1,530 @@
+// Copyright 2024 Barbagus
+//
+// This file is part of slmlib.
+//
+// slmlib is free software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// slmlib is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without even
+// the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU General
+// Public License for more details.
+// You should have received a copy of the GNU General Public License along with slmlib. If not, see
+// <https://www.gnu.org/licenses/>.
+
+//! A straight line mission track as a [GeoJSON](https://www.rfc-editor.org/rfc/rfc7946) document.
+//!
+//! The track is read from:
+//!  - a `LineString` or `MultiLineString` geometry (positions may carry an elevation),
+//!  - a `Feature` with such a geometry,
+//!  - a `FeatureCollection` of such features or of `Point` features (in track order, with an
+//!    optional RFC 3339 `time` property).
+//!
+//! The route may be given as `start` and `end` properties (`[longitude, latitude]` positions) of
+//! any feature.
+//!
+//! Features written by [`dump`] have a `kind` property; only the `track` kind ones are read as
+//! part of the track, so that a written document can be read back.
+extern crate alloc;
+
+use crate::{geowizard::RankScheme, Coordinates, Deviation, Progress, Slm, TargetLine, TrackPoint};
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::{error, fmt};
+use serde_json::{json, Map, Value};
+
+#[derive(Debug)]
+pub enum Error {
+    /// Not a JSON document
+    Json(serde_json::Error),
+    /// Not a GeoJSON object, or not one describing a track
+    Object,
+    /// Ill-formed position
+    Position,
+    /// Ill-formed `time` property
+    Time,
+}
+
+impl error::Error for Error {}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Json(error) => error.fmt(f),
+            Error::Object => f.write_str("no track found in GeoJSON object"),
+            Error::Position => f.write_str("ill-formed GeoJSON position"),
+            Error::Time => f.write_str("ill-formed time property"),
+        }
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(value: serde_json::Error) -> Self {
+        Error::Json(value)
+    }
+}
+
+/// A track, along with an optional route, as read from a GeoJSON document
+#[derive(Debug, Clone, PartialEq)]
+pub struct GeoJSONTrack {
+    pub track: Vec<TrackPoint>,
+    pub start: Option<Coordinates>,
+    pub end: Option<Coordinates>,
+}
+
+fn position(value: &Value) -> Result<TrackPoint, Error> {
+    let position = value.as_array().ok_or(Error::Position)?;
+    let (longitude, latitude, elevation) = match position.as_slice() {
+        [longitude, latitude] => (longitude, latitude, None),
+        [longitude, latitude, elevation, ..] => (longitude, latitude, Some(elevation)),
+        _ => return Err(Error::Position),
+    };
+    Ok(TrackPoint {
+        coordinates: Coordinates {
+            latitude: latitude.as_f64().ok_or(Error::Position)?,
+            longitude: longitude.as_f64().ok_or(Error::Position)?,
+        },
+        time: None,
+        elevation: match elevation {
+            Some(elevation) => Some(elevation.as_f64().ok_or(Error::Position)?),
+            None => None,
+        },
+        speed: None,
+    })
+}
+
+fn positions(value: &Value) -> Result<Vec<TrackPoint>, Error> {
+    value
+        .as_array()
+        .ok_or(Error::Position)?
+        .iter()
+        .map(position)
+        .collect()
+}
+
+fn load_geometry(
+    geometry: &Value,
+    properties: Option<&Value>,
+    into: &mut GeoJSONTrack,
+) -> Result<(), Error> {
+    let coordinates = geometry.get("coordinates").ok_or(Error::Object)?;
+    match geometry.get("type").and_then(Value::as_str) {
+        Some("LineString") => into.track.extend(positions(coordinates)?),
+        Some("MultiLineString") => {
+            for line in coordinates.as_array().ok_or(Error::Position)? {
+                into.track.extend(positions(line)?);
+            }
+        }
+        Some("Point") => {
+            let mut point = position(coordinates)?;
+            if let Some(time) = properties.and_then(|p| p.get("time")) {
+                let time = time.as_str().ok_or(Error::Time)?;
+                point.time.replace(time.parse().map_err(|_| Error::Time)?);
+            }
+            into.track.push(point);
+        }
+        _ => return Err(Error::Object),
+    }
+    Ok(())
+}
+
+fn load_feature(feature: &Value, into: &mut GeoJSONTrack) -> Result<(), Error> {
+    let properties = feature.get("properties").filter(|p| p.is_object());
+    if let Some(properties) = properties {
+        if let Some(start) = properties.get("start") {
+            into.start.replace(position(start)?.coordinates);
+        }
+        if let Some(end) = properties.get("end") {
+            into.end.replace(position(end)?.coordinates);
+        }
+    }
+    let kind = properties.and_then(|p| p.get("kind"));
+    match feature.get("geometry") {
+        Some(Value::Null) | None => Ok(()),
+        Some(_) if kind.is_some_and(|kind| kind != "track") => Ok(()),
+        Some(geometry) => load_geometry(geometry, properties, into),
+    }
+}
+
+/// Load a track (and optionally the route) from a GeoJSON document
+pub fn load(buf: &[u8]) -> Result<GeoJSONTrack, Error> {
+    let value: Value = serde_json::from_slice(buf)?;
+
+    let mut track = GeoJSONTrack {
+        track: Vec::new(),
+        start: None,
+        end: None,
+    };
+
+    match value.get("type").and_then(Value::as_str) {
+        Some("FeatureCollection") => {
+            for feature in value
+                .get("features")
+                .and_then(Value::as_array)
+                .ok_or(Error::Object)?
+            {
+                load_feature(feature, &mut track)?;
+            }
+        }
+        Some("Feature") => load_feature(&value, &mut track)?,
+        Some("LineString" | "MultiLineString") => load_geometry(&value, None, &mut track)?,
+        _ => return Err(Error::Object),
+    }
+
+    Ok(track)
+}
+
+/// Max. distance (in meters) between the vertices of the target line and corridors
+const VERTEX_SPACING: f64 = 500.0;
+
+fn lon_lat(coordinates: Coordinates) -> Value {
+    json!([coordinates.longitude, coordinates.latitude])
+}
+
+fn feature(geometry: Value, properties: Map<String, Value>) -> Value {
+    json!({
+        "type": "Feature",
+        "geometry": geometry,
+        "properties": properties,
+    })
+}
+
+/// Dump an analyzed straight line mission as a GeoJSON `FeatureCollection`, comprising of:
+///   - the medal corridors (`Polygon`s, from the widest to the narrowest),
+///   - the target line (`LineString`),
+///   - the track (`LineString`), along with per-vertex `progress`, `made_good` and (signed, positive
+///     on the right) `deviation` properties (as arrays),
+///   - the max. deviation point (`Point`), if any track point is en route.
+///
+/// The corridors are GeoWizard's, see [`dump_with_scheme`] for others.
+pub fn dump(slm: &Slm) -> String {
+    dump_with_scheme(slm, &RankScheme::geowizard())
+}
+
+/// Dump an analyzed straight line mission as a GeoJSON `FeatureCollection` (see [`dump`]), with the
+/// corridors of a rank scheme.
+pub fn dump_with_scheme(slm: &Slm, scheme: &RankScheme) -> String {
+    let target_line = TargetLine::new(slm.route_start, slm.route_end);
+    let mut features = Vec::new();
+
+    for tier in scheme.tiers().iter().rev() {
+        let mut properties = Map::new();
+        properties.insert("kind".into(), json!("corridor"));
+        properties.insert("rank".into(), json!(tier.rank.to_str()));
+        properties.insert("name".into(), json!(tier.name));
+        properties.insert("max_deviation".into(), json!(tier.max_deviation));
+        features.push(feature(
+            json!({
+                "type": "Polygon",
+                "coordinates": [target_line
+                    .corridor(tier.max_deviation, VERTEX_SPACING)
+                .expect("valid spacing")
+                    .into_iter()
+                    .map(lon_lat)
+                    .collect::<Vec<_>>()],
+            }),
+            properties,
+        ));
+    }
+
+    let line: Vec<Value> = target_line
+        .vertices(VERTEX_SPACING)
+        .expect("valid spacing")
+        .into_iter()
+        .map(lon_lat)
+        .collect();
+    let mut properties = Map::new();
+    properties.insert("kind".into(), json!("target-line"));
+    properties.insert("start".into(), lon_lat(slm.route_start));
+    properties.insert("end".into(), lon_lat(slm.route_end));
+    properties.insert("length".into(), json!(slm.route_length));
+    features.push(feature(
+        json!({"type": "LineString", "coordinates": line}),
+        properties,
+    ));
+
+    let mut coordinates = Vec::with_capacity(slm.track.len());
+    let mut progress = Vec::with_capacity(slm.track.len());
+    let mut made_good = Vec::with_capacity(slm.track.len());
+    let mut deviation = Vec::with_capacity(slm.track.len());
+    let mut time = Vec::with_capacity(slm.track.len());
+    let mut max_deviation = None;
+    for point in &slm.track {
+        coordinates.push(match point.elevation {
+            Some(elevation) => json!([
+                point.coordinates.longitude,
+                point.coordinates.latitude,
+                elevation
+            ]),
+            None => lon_lat(point.coordinates),
+        });
+        time.push(json!(point.time.map(|t| alloc::format!("{}", t))));
+        match &point.progress {
+            Progress::Standby => {
+                progress.push(json!("standby"));
+                made_good.push(Value::Null);
+                deviation.push(Value::Null);
+            }
+            Progress::EnRoute {
+                made_good: m,
+                deviation: d,
+                ..
+            } => {
+                progress.push(json!("en-route"));
+                made_good.push(json!(m));
+                deviation.push(json!(match d {
+                    Some(Deviation::Left(d)) => -d,
+                    Some(Deviation::Right(d)) => *d,
+                    None => 0.0,
+                }));
+                if max_deviation.is_none() && point.progress.deviation() >= Some(slm.max_deviation)
+                {
+                    max_deviation.replace(point);
+                }
+            }
+            Progress::Arrived => {
+                progress.push(json!("arrived"));
+                made_good.push(Value::Null);
+                deviation.push(Value::Null);
+            }
+        }
+    }
+    let mut properties = Map::new();
+    properties.insert("kind".into(), json!("track"));
+    properties.insert("progress".into(), json!(progress));
+    properties.insert("made_good".into(), json!(made_good));
+    properties.insert("deviation".into(), json!(deviation));
+    if slm.track.iter().any(|p| p.time.is_some()) {
+        properties.insert("time".into(), json!(time));
+    }
+    features.push(feature(
+        json!({"type": "LineString", "coordinates": coordinates}),
+        properties,
+    ));
+
+    if let Some(point) = max_deviation {
+        let mut properties = Map::new();
+        properties.insert("kind".into(), json!("max-deviation"));
+        properties.insert("deviation".into(), json!(slm.max_deviation));
+        if let Progress::EnRoute {
+            on_route,
+            made_good,
+            ..
+        } = point.progress
+        {
+            properties.insert("made_good".into(), json!(made_good));
+            properties.insert("on_route".into(), lon_lat(on_route));
+        }
+        features.push(feature(
+            json!({"type": "Point", "coordinates": lon_lat(point.coordinates)}),
+            properties,
+        ));
+    }
+
+    json!({"type": "FeatureCollection", "features": features}).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        geowizard::{Rank, RankTier},
+        Timestamp,
+    };
+    use alloc::vec;
+    use approx::assert_abs_diff_eq;
+
+    macro_rules! load_ok_tests {
+        ($($name:ident: $json:literal,)*) => {
+        $(
+            #[test]
+            fn $name() {
+                let track = load($json.as_bytes()).unwrap();
+                let check: Vec<Coordinates> = vec![
+                    Coordinates{latitude: 54.29600470, longitude: -4.58877725},
+                    Coordinates{latitude: 54.29600654, longitude: -4.58877590},
+                    Coordinates{latitude: 54.29600906, longitude: -4.58876509},
+                ];
+
+                assert_eq!(track.track.iter().map(|p| p.coordinates).collect::<Vec<_>>(), check);
+            }
+        )*
+        }
+    }
+    load_ok_tests! {
+        load_line_string: r#"{"type": "LineString", "coordinates": [
+            [-4.58877725, 54.29600470], [-4.58877590, 54.29600654], [-4.58876509, 54.29600906]
+        ]}"#,
+        load_multi_line_string: r#"{"type": "MultiLineString", "coordinates": [
+            [[-4.58877725, 54.29600470], [-4.58877590, 54.29600654]],
+            [[-4.58876509, 54.29600906]]
+        ]}"#,
+        load_feature: r#"{"type": "Feature", "properties": null, "geometry": {
+            "type": "LineString", "coordinates": [
+                [-4.58877725, 54.29600470, 12.5], [-4.58877590, 54.29600654], [-4.58876509, 54.29600906]
+            ]
+        }}"#,
+        load_points: r#"{"type": "FeatureCollection", "features": [
+            {"type": "Feature", "properties": {}, "geometry": {"type": "Point", "coordinates": [-4.58877725, 54.29600470]}},
+            {"type": "Feature", "properties": {}, "geometry": {"type": "Point", "coordinates": [-4.58877590, 54.29600654]}},
+            {"type": "Feature", "properties": {}, "geometry": {"type": "Point", "coordinates": [-4.58876509, 54.29600906]}}
+        ]}"#,
+    }
+
+    #[test]
+    fn load_properties() {
+        let track = load(
+            br#"{"type": "FeatureCollection", "features": [
+            {"type": "Feature", "properties": {"start": [-4.5, 54.2], "end": [-4.6, 54.3]}, "geometry": null},
+            {"type": "Feature", "properties": {"time": "2024-05-12T09:41:07Z"}, "geometry": {"type": "Point", "coordinates": [-4.58877725, 54.29600470, 12.5]}}
+        ]}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            track,
+            GeoJSONTrack {
+                track: vec![TrackPoint {
+                    coordinates: Coordinates {
+                        latitude: 54.29600470,
+                        longitude: -4.58877725
+                    },
+                    time: Some(Timestamp(1715506867000)),
+                    elevation: Some(12.5),
+                    speed: None,
+                }],
+                start: Some(Coordinates {
+                    latitude: 54.2,
+                    longitude: -4.5
+                }),
+                end: Some(Coordinates {
+                    latitude: 54.3,
+                    longitude: -4.6
+                }),
+            }
+        );
+    }
+
+    macro_rules! load_err_tests {
+        ($($name:ident: $json:literal => $err:pat,)*) => {
+        $(
+            #[test]
+            fn $name() {
+                assert!(matches!(load($json.as_bytes()), Err($err)));
+            }
+        )*
+        }
+    }
+    load_err_tests! {
+        load_not_json: "LineString" => Error::Json(_),
+        load_polygon: r#"{"type": "Polygon", "coordinates": [[[0, 0], [1, 0], [0, 1], [0, 0]]]}"# => Error::Object,
+        load_bad_position: r#"{"type": "LineString", "coordinates": [[0, 0], [1]]}"# => Error::Position,
+        load_bad_time: r#"{"type": "Feature", "properties": {"time": "noon"}, "geometry": {"type": "Point", "coordinates": [0, 0]}}"# => Error::Time,
+    }
+
+    #[test]
+    fn dump_load() {
+        let start = Coordinates {
+            latitude: 45.0,
+            longitude: 7.0,
+        };
+        let end = Coordinates {
+            latitude: 45.01,
+            longitude: 7.0,
+        };
+        let track = [
+            (44.999, 7.0),
+            (45.002, 7.0002),
+            (45.004, 7.0005),
+            (45.006, 6.9999),
+            (45.011, 7.0),
+        ]
+        .map(|(latitude, longitude)| Coordinates {
+            latitude,
+            longitude,
+        });
+        let slm = crate::analyze(start, end, track);
+
+        let geojson = dump(&slm);
+        let value: Value = serde_json::from_str(&geojson).unwrap();
+        let features = value["features"].as_array().unwrap();
+        assert_eq!(features.len(), 7);
+
+        // corridors
+        for (feature, rank) in features.iter().zip(Rank::ALL.iter().rev()) {
+            assert_eq!(feature["properties"]["rank"], rank.to_str());
+            let ring = feature["geometry"]["coordinates"][0].as_array().unwrap();
+            assert_eq!(ring.first(), ring.last());
+            for vertex in ring {
+                let vertex = position(vertex).unwrap().coordinates;
+                let projection = crate::TargetLine::new(start, end)
+                    .project(vertex, crate::Projection::Precise)
+                    .unwrap();
+                assert_abs_diff_eq!(
+                    projection.cross_track.abs(),
+                    rank.max_deviation(),
+                    epsilon = 1e-3
+                );
+            }
+        }
+
+        // worst deviation
+        let worst = &features[6];
+        assert_eq!(worst["geometry"]["coordinates"], json!([7.0005, 45.004]));
+        assert_eq!(worst["properties"]["deviation"], json!(slm.max_deviation));
+
+        // the target line and track are read back
+        let loaded = load(geojson.as_bytes()).unwrap();
+        assert_eq!(loaded.start, Some(start));
+        assert_eq!(loaded.end, Some(end));
+        let loaded_track: Vec<Coordinates> = loaded.track.iter().map(|p| p.coordinates).collect();
+        assert_eq!(loaded_track, track);
+    }
+
+    #[test]
+    fn dump_scheme() {
+        let slm = crate::analyze(
+            Coordinates {
+                latitude: 45.0,
+                longitude: 7.0,
+            },
+            Coordinates {
+                latitude: 45.01,
+                longitude: 7.0,
+            },
+            [Coordinates {
+                latitude: 45.005,
+                longitude: 7.0,
+            }],
+        );
+        let scheme = RankScheme::new(vec![
+            RankTier::new(Rank::Gold, 50.0),
+            RankTier::new(Rank::Silver, 100.0),
+            RankTier::new(Rank::Bronze, 150.0).with_name("COPPER"),
+        ])
+        .unwrap();
+
+        let value: Value = serde_json::from_str(&dump_with_scheme(&slm, &scheme)).unwrap();
+        let corridors: Vec<_> = value["features"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter(|feature| feature["properties"]["kind"] == "corridor")
+            .map(|feature| {
+                (
+                    feature["properties"]["name"].clone(),
+                    feature["properties"]["max_deviation"].clone(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            corridors,
+            [
+                (json!("COPPER"), json!(150.0)),
+                (json!("SILVER"), json!(100.0)),
+                (json!("GOLD"), json!(50.0))
+            ]
+        );
+    }
+}
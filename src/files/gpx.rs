@@ -14,15 +14,16 @@
 
 extern crate alloc;
 
-use crate::Coordinates;
-use alloc::vec::Vec;
-use core::{error, f64, fmt, str};
+use crate::{geowizard::RankScheme, Coordinates, Deviation, Progress, Slm, Timestamp, TrackPoint};
+use alloc::{string::String, vec::Vec};
+use core::{error, f64, fmt, fmt::Write, str};
 use xmlparser::{ElementEnd, TextPos, Token, Tokenizer};
 
 #[derive(Debug, Clone)]
 pub enum Error {
     DuplicateCoordinate(TextPos),
     InvalidCoordinate(TextPos),
+    InvalidElevation(TextPos),
     MissingCoordinate(TextPos),
     Utf8(str::Utf8Error),
     XmlForm(TextPos),
@@ -41,6 +42,9 @@ impl fmt::Display for Error {
             Error::InvalidCoordinate(text_pos) => {
                 write!(f, "invalid coordinate at {}", text_pos)
             }
+            Error::InvalidElevation(text_pos) => {
+                write!(f, "invalid elevation at {}", text_pos)
+            }
             Error::MissingCoordinate(text_pos) => {
                 write!(f, "missing coordinate at {}", text_pos)
             }
@@ -93,14 +97,159 @@ macro_rules! set_coordinate {
     }};
 }
 
+/// A waypoint, or a route point
+#[derive(Debug, Clone, PartialEq)]
+pub struct Waypoint {
+    pub coordinates: Coordinates,
+    pub name: Option<String>,
+    pub time: Option<Timestamp>,
+    pub elevation: Option<f64>,
+}
+
+/// A route: an ordered list of waypoints
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Route {
+    pub name: Option<String>,
+    pub points: Vec<Waypoint>,
+}
+
+/// A track: an ordered list of segments, each being a continuous span of track points (i.e. the
+/// device may have lost its fix, or been paused, between two segments)
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Track {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    /// Type of the track (`<type>` element), e.g. the activity
+    pub kind: Option<String>,
+    pub segments: Vec<Vec<TrackPoint>>,
+}
+
+impl Track {
+    /// The track points of all the segments, in order
+    pub fn points(&self) -> impl Iterator<Item = TrackPoint> + '_ {
+        self.segments.iter().flatten().copied()
+    }
+}
+
+/// A GPX document
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Gpx {
+    /// Name of the document (`<metadata>`)
+    pub name: Option<String>,
+    /// Creation time of the document (`<metadata>`)
+    pub time: Option<Timestamp>,
+    pub waypoints: Vec<Waypoint>,
+    pub routes: Vec<Route>,
+    pub tracks: Vec<Track>,
+}
+
+impl Gpx {
+    /// The track of the given name, if any
+    pub fn track_by_name(&self, name: &str) -> Option<&Track> {
+        self.tracks
+            .iter()
+            .find(|track| track.name.as_deref() == Some(name))
+    }
+
+    /// The target line (start and end) described in the document, if any: either the first route
+    /// of exactly two points, or the waypoints named "start" and "end" (case insensitive)
+    pub fn target_line(&self) -> Option<(Coordinates, Coordinates)> {
+        self.routes
+            .iter()
+            .find(|route| route.points.len() == 2)
+            .map(|route| (route.points[0].coordinates, route.points[1].coordinates))
+            .or_else(|| {
+                let waypoint = |name: &str| {
+                    self.waypoints.iter().find(|waypoint| {
+                        waypoint
+                            .name
+                            .as_deref()
+                            .is_some_and(|n| n.trim().eq_ignore_ascii_case(name))
+                    })
+                };
+                Some((waypoint("start")?.coordinates, waypoint("end")?.coordinates))
+            })
+    }
+}
+
+/// Parse an `xsd:dateTime`, a time without a time zone being UTC; `None` if invalid, as times are
+/// optional
+fn parse_time(text: &str) -> Option<Timestamp> {
+    let text = text.trim();
+    text.parse()
+        .or_else(|_| alloc::format!("{}Z", text).parse())
+        .ok()
+}
+
+/// Resolve the predefined and character entity references of an XML text
+fn unescape(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(i) = rest.find('&') {
+        unescaped.push_str(&rest[..i]);
+        rest = &rest[i..];
+        let Some(j) = rest.find(';') else { break };
+        let c = match &rest[1..j] {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            entity => entity
+                .strip_prefix("#x")
+                .map(|hex| u32::from_str_radix(hex, 16))
+                .or_else(|| entity.strip_prefix('#').map(|dec| dec.parse::<u32>()))
+                .and_then(|code| code.ok())
+                .and_then(char::from_u32),
+        };
+        match c {
+            Some(c) => {
+                unescaped.push(c);
+                rest = &rest[j + 1..];
+            }
+            None => {
+                unescaped.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    unescaped.push_str(rest);
+    unescaped
+}
+
+/// Load the coordinates of the first track
 pub fn load(buf: &[u8]) -> Result<Vec<Coordinates>, Error> {
+    Ok(load_points(buf)?
+        .into_iter()
+        .map(|point| point.coordinates)
+        .collect())
+}
+
+/// Load the points of the first track (all segments), along with their time and elevation
+pub fn load_points(buf: &[u8]) -> Result<Vec<TrackPoint>, Error> {
+    Ok(load_gpx(buf)?
+        .tracks
+        .first()
+        .map(|track| track.points().collect())
+        .unwrap_or_default())
+}
+
+/// Load every track, route and waypoint of a GPX document
+pub fn load_gpx(buf: &[u8]) -> Result<Gpx, Error> {
     let buf = str::from_utf8(buf)?;
 
-    let mut track: Vec<Coordinates> = Vec::new();
+    let mut gpx = Gpx::default();
     let mut stack: Vec<&str> = Vec::with_capacity(10);
 
+    let mut track: Option<Track> = None;
+    let mut segment: Option<Vec<TrackPoint>> = None;
+    let mut route: Option<Route> = None;
+
     let mut lat: Option<f64> = None;
     let mut lon: Option<f64> = None;
+    let mut time: Option<Timestamp> = None;
+    let mut ele: Option<f64> = None;
+    let mut name: Option<String> = None;
 
     let mut tokenizer = Tokenizer::from(buf);
     while let Some(token) = tokenizer.next() {
@@ -108,11 +257,24 @@ pub fn load(buf: &[u8]) -> Result<Vec<Coordinates>, Error> {
 
         match token {
             Token::ElementStart { local, .. } => {
-                stack.push(local.as_str());
+                let element = local.as_str();
+                match element {
+                    "trk" => {
+                        track.replace(Track::default());
+                    }
+                    "trkseg" => {
+                        segment.replace(Vec::new());
+                    }
+                    "rte" => {
+                        route.replace(Route::default());
+                    }
+                    _ => {}
+                }
+                stack.push(element);
             }
             Token::Attribute { local, value, .. } => {
                 match stack.last().ok_or_else(|| stack_error!(tokenizer))? {
-                    &"trkpt" => match local.as_str() {
+                    &"trkpt" | &"rtept" | &"wpt" => match local.as_str() {
                         "lat" => set_coordinate!(tokenizer, value, lat),
                         "lon" => set_coordinate!(tokenizer, value, lon),
                         _ => continue,
@@ -120,6 +282,48 @@ pub fn load(buf: &[u8]) -> Result<Vec<Coordinates>, Error> {
                     _ => continue,
                 }
             }
+            Token::Text { text } => {
+                match stack.as_slice() {
+                    [.., "trkpt" | "rtept" | "wpt", "ele"] => {
+                        ele.replace(text.trim().parse::<f64>().map_err(|_| {
+                            Error::InvalidElevation(tokenizer.stream().gen_text_pos())
+                        })?);
+                    }
+                    [.., "trkpt" | "rtept" | "wpt", "time"] => {
+                        time = parse_time(&text);
+                    }
+                    [.., "rtept" | "wpt", "name"] => {
+                        name.replace(unescape(text.trim()));
+                    }
+                    [.., "trk", "name"] => {
+                        if let Some(track) = track.as_mut() {
+                            track.name.replace(unescape(text.trim()));
+                        }
+                    }
+                    [.., "trk", "desc"] => {
+                        if let Some(track) = track.as_mut() {
+                            track.description.replace(unescape(text.trim()));
+                        }
+                    }
+                    [.., "trk", "type"] => {
+                        if let Some(track) = track.as_mut() {
+                            track.kind.replace(unescape(text.trim()));
+                        }
+                    }
+                    [.., "rte", "name"] => {
+                        if let Some(route) = route.as_mut() {
+                            route.name.replace(unescape(text.trim()));
+                        }
+                    }
+                    [.., "metadata", "name"] => {
+                        gpx.name.replace(unescape(text.trim()));
+                    }
+                    [.., "metadata", "time"] => {
+                        gpx.time = parse_time(&text);
+                    }
+                    _ => continue,
+                }
+            }
             Token::ElementEnd { end, .. } => {
                 let element = match end {
                     ElementEnd::Open => continue,
@@ -134,16 +338,49 @@ pub fn load(buf: &[u8]) -> Result<Vec<Coordinates>, Error> {
                 };
 
                 match element {
-                    "trk" => break,
-                    "trkpt" => {
-                        track.push(Coordinates {
+                    "trkpt" | "rtept" | "wpt" => {
+                        let coordinates = Coordinates {
                             latitude: lat.take().ok_or_else(|| {
                                 Error::MissingCoordinate(tokenizer.stream().gen_text_pos())
                             })?,
                             longitude: lon.take().ok_or_else(|| {
                                 Error::MissingCoordinate(tokenizer.stream().gen_text_pos())
                             })?,
-                        });
+                        };
+                        let (time, elevation, name) = (time.take(), ele.take(), name.take());
+                        match element {
+                            "trkpt" => {
+                                segment.get_or_insert_with(Vec::new).push(TrackPoint {
+                                    coordinates,
+                                    time,
+                                    elevation,
+                                    speed: None,
+                                });
+                            }
+                            _ => {
+                                let waypoint = Waypoint {
+                                    coordinates,
+                                    name,
+                                    time,
+                                    elevation,
+                                };
+                                match (element, route.as_mut()) {
+                                    ("rtept", Some(route)) => route.points.push(waypoint),
+                                    _ => gpx.waypoints.push(waypoint),
+                                }
+                            }
+                        }
+                    }
+                    "trkseg" => {
+                        if let (Some(track), Some(segment)) = (track.as_mut(), segment.take()) {
+                            track.segments.push(segment);
+                        }
+                    }
+                    "trk" => {
+                        gpx.tracks.extend(track.take());
+                    }
+                    "rte" => {
+                        gpx.routes.extend(route.take());
                     }
                     _ => continue,
                 }
@@ -151,12 +388,164 @@ pub fn load(buf: &[u8]) -> Result<Vec<Coordinates>, Error> {
             _ => continue,
         }
     }
-    Ok(track)
+    Ok(gpx)
+}
+
+/// XML namespace of the analysis extensions
+const SLM_NS: &str = "https://github.com/barbagus/slmlib/gpx/1";
+
+fn write_wpt(
+    w: &mut String,
+    coordinates: Coordinates,
+    time: Option<Timestamp>,
+    name: &str,
+    desc: fmt::Arguments,
+) -> fmt::Result {
+    writeln!(
+        w,
+        r#" <wpt lat="{}" lon="{}">"#,
+        coordinates.latitude, coordinates.longitude
+    )?;
+    if let Some(time) = time {
+        writeln!(w, "  <time>{}</time>", time)?;
+    }
+    writeln!(w, "  <name>{}</name>", name)?;
+    writeln!(w, "  <desc>{}</desc>", desc)?;
+    writeln!(w, " </wpt>")
+}
+
+fn write_gpx(w: &mut String, slm: &Slm, scheme: &RankScheme) -> fmt::Result {
+    writeln!(w, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(
+        w,
+        r#"<gpx version="1.1" creator="slmlib" xmlns="http://www.topografix.com/GPX/1/1" xmlns:slm="{}">"#,
+        SLM_NS
+    )?;
+
+    // waypoints: max deviation and medal boundary crossings
+    let mut max_deviation = None;
+    let mut previous: Option<f64> = None;
+    for point in &slm.track {
+        let Some(deviation) = point.progress.deviation() else {
+            continue;
+        };
+        if max_deviation.is_none() && deviation >= slm.max_deviation {
+            max_deviation.replace(point);
+        }
+        if let Some(previous) = previous {
+            // from the narrowest to the widest corridor
+            for tier in scheme.tiers() {
+                let bound = tier.max_deviation;
+                let name = super::escape_xml(&tier.name);
+                if previous < bound && deviation >= bound {
+                    write_wpt(
+                        w,
+                        point.coordinates,
+                        point.time,
+                        &alloc::format!("Exit {}", name),
+                        format_args!("Deviation over {} m", bound),
+                    )?;
+                } else if previous >= bound && deviation < bound {
+                    write_wpt(
+                        w,
+                        point.coordinates,
+                        point.time,
+                        &alloc::format!("Enter {}", name),
+                        format_args!("Deviation under {} m", bound),
+                    )?;
+                }
+            }
+        }
+        previous.replace(deviation);
+    }
+    if let Some(point) = max_deviation {
+        write_wpt(
+            w,
+            point.coordinates,
+            point.time,
+            "Max. deviation",
+            format_args!("{:.1} m", slm.max_deviation),
+        )?;
+    }
+
+    // target line
+    writeln!(w, " <rte>")?;
+    writeln!(w, "  <name>Target line</name>")?;
+    for (coordinates, name) in [(slm.route_start, "Start"), (slm.route_end, "End")] {
+        writeln!(
+            w,
+            r#"  <rtept lat="{}" lon="{}"><name>{}</name></rtept>"#,
+            coordinates.latitude, coordinates.longitude, name
+        )?;
+    }
+    writeln!(w, " </rte>")?;
+
+    // track
+    writeln!(w, " <trk>")?;
+    writeln!(w, "  <name>Track</name>")?;
+    writeln!(w, "  <trkseg>")?;
+    for point in &slm.track {
+        writeln!(
+            w,
+            r#"   <trkpt lat="{}" lon="{}">"#,
+            point.coordinates.latitude, point.coordinates.longitude
+        )?;
+        if let Some(elevation) = point.elevation {
+            writeln!(w, "    <ele>{}</ele>", elevation)?;
+        }
+        if let Some(time) = point.time {
+            writeln!(w, "    <time>{}</time>", time)?;
+        }
+        writeln!(w, "    <extensions>")?;
+        match &point.progress {
+            Progress::Standby => writeln!(w, "     <slm:progress>standby</slm:progress>")?,
+            Progress::EnRoute {
+                made_good,
+                deviation,
+                ..
+            } => {
+                writeln!(w, "     <slm:progress>en-route</slm:progress>")?;
+                writeln!(w, "     <slm:made_good>{}</slm:made_good>", made_good)?;
+                // signed deviation: positive to the right, negative to the left
+                let deviation = match deviation {
+                    Some(Deviation::Left(deviation)) => -deviation,
+                    Some(Deviation::Right(deviation)) => *deviation,
+                    None => 0.0,
+                };
+                writeln!(w, "     <slm:deviation>{}</slm:deviation>", deviation)?;
+            }
+            Progress::Arrived => writeln!(w, "     <slm:progress>arrived</slm:progress>")?,
+        }
+        writeln!(w, "    </extensions>")?;
+        writeln!(w, "   </trkpt>")?;
+    }
+    writeln!(w, "  </trkseg>")?;
+    writeln!(w, " </trk>")?;
+
+    writeln!(w, "</gpx>")
+}
+
+/// Dump an analyzed straight line mission as a GPX 1.1 document: the target line as a route, the
+/// track with per-point analysis extensions, and waypoints marking the max deviation and the medal
+/// boundary crossings.
+///
+/// The medals are GeoWizard's, see [`dump_with_scheme`] for others.
+pub fn dump(slm: &Slm) -> String {
+    dump_with_scheme(slm, &RankScheme::geowizard())
+}
+
+/// Dump an analyzed straight line mission as a GPX 1.1 document (see [`dump`]), marking the
+/// boundary crossings of a rank scheme.
+pub fn dump_with_scheme(slm: &Slm, scheme: &RankScheme) -> String {
+    let mut w = String::new();
+    write_gpx(&mut w, slm, scheme).expect("write to string");
+    w
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::geowizard::{Rank, RankTier};
 
     macro_rules! csv_load_ok_tests {
         ($($name:ident: $csv:literal,)*) => {
@@ -237,4 +626,263 @@ mod tests {
 </gpx>
 "#,
     }
+
+    #[test]
+    fn time_and_elevation() {
+        let points = load_points(
+            br#"<?xml version="1.0" encoding="UTF-8"?>
+<gpx>
+ <metadata>
+  <time> 2024-05-12T09:00:00Z </time>
+ </metadata>
+ <trk>
+  <trkseg>
+   <trkpt lat="47.6655080" lon="8.5671500">
+    <ele>402.5</ele>
+    <time>2024-05-12T09:41:07Z</time>
+   </trkpt>
+   <trkpt lat="47.6655040" lon="8.5671580">
+    <time>
+     2024-05-12T09:41:08.500Z
+    </time>
+   </trkpt>
+   <trkpt lat="47.6655010" lon="8.5671610" />
+  </trkseg>
+ </trk>
+</gpx>
+"#,
+        )
+        .unwrap();
+
+        let times: Vec<_> = points.iter().map(|p| p.time).collect();
+        assert_eq!(
+            times,
+            [
+                Some(Timestamp(1715506867000)),
+                Some(Timestamp(1715506868500)),
+                None
+            ]
+        );
+        let elevations: Vec<_> = points.iter().map(|p| p.elevation).collect();
+        assert_eq!(elevations, [Some(402.5), None, None]);
+    }
+
+    #[test]
+    fn lenient_time() {
+        let gpx = load_gpx(
+            br#"<gpx><metadata><time>2024-05-12</time></metadata><trk><trkseg>
+   <trkpt lat="47.6655080" lon="8.5671500"><time>2024-05-12T09:41:07</time></trkpt>
+   <trkpt lat="47.6655040" lon="8.5671580"><time>yesterday</time></trkpt>
+</trkseg></trk></gpx>"#,
+        )
+        .unwrap();
+
+        assert_eq!(gpx.time, None);
+        let times: Vec<_> = gpx.tracks[0].points().map(|p| p.time).collect();
+        assert_eq!(times, [Some(Timestamp(1715506867000)), None]);
+    }
+
+    #[test]
+    fn dump_load() {
+        let start = Coordinates {
+            latitude: 45.0,
+            longitude: 7.0,
+        };
+        let end = Coordinates {
+            latitude: 45.01,
+            longitude: 7.0,
+        };
+        let track = [
+            (44.999, 7.0),
+            (45.002, 7.0002),
+            (45.004, 7.0005),
+            (45.006, 6.9999),
+            (45.011, 7.0),
+        ]
+        .map(|(latitude, longitude)| TrackPoint {
+            coordinates: Coordinates {
+                latitude,
+                longitude,
+            },
+            time: Some(Timestamp(1715506867000)),
+            elevation: Some(400.0),
+            speed: None,
+        });
+        let slm = crate::analyze(start, end, track);
+
+        let gpx = dump(&slm);
+        assert_eq!(load_points(gpx.as_bytes()).unwrap(), track);
+
+        // signed deviations
+        assert!(gpx.contains("<slm:deviation>15.7"));
+        assert!(gpx.contains("<slm:deviation>39.4"));
+        assert!(gpx.contains("<slm:deviation>-7.8"));
+        assert_eq!(gpx.matches("<slm:progress>standby").count(), 1);
+        assert_eq!(gpx.matches("<slm:progress>en-route").count(), 3);
+        assert_eq!(gpx.matches("<slm:progress>arrived").count(), 1);
+
+        // waypoints
+        assert_eq!(gpx.matches("<wpt ").count(), 3);
+        assert!(gpx.contains("<name>Exit PLATINUM</name>"));
+        assert!(gpx.contains("<name>Enter PLATINUM</name>"));
+        assert!(gpx.contains("<name>Max. deviation</name>"));
+
+        // target line
+        assert!(gpx.contains(r#"<rtept lat="45" lon="7"><name>Start</name></rtept>"#));
+        assert!(gpx.contains(r#"<rtept lat="45.01" lon="7"><name>End</name></rtept>"#));
+        assert_eq!(
+            load_gpx(gpx.as_bytes()).unwrap().target_line(),
+            Some((start, end))
+        );
+    }
+
+    #[test]
+    fn dump_scheme() {
+        let start = Coordinates {
+            latitude: 45.0,
+            longitude: 7.0,
+        };
+        let end = Coordinates {
+            latitude: 45.01,
+            longitude: 7.0,
+        };
+        let track = [
+            (44.999, 7.0),
+            (45.002, 7.0002),
+            (45.004, 7.0005),
+            (45.006, 6.9999),
+            (45.011, 7.0),
+        ]
+        .map(|(latitude, longitude)| Coordinates {
+            latitude,
+            longitude,
+        });
+        let slm = crate::analyze(start, end, track);
+        let scheme = RankScheme::new(alloc::vec![
+            RankTier::new(Rank::Platinum, 10.0).with_name("ON & ON"),
+            RankTier::new(Rank::Gold, 30.0),
+        ])
+        .unwrap();
+
+        // deviations: 15.7 m, 39.4 m, 7.8 m
+        let gpx = dump_with_scheme(&slm, &scheme);
+        assert_eq!(gpx.matches("<wpt ").count(), 4);
+        assert!(gpx.contains("<name>Exit GOLD</name>"));
+        assert!(gpx.contains("<name>Enter GOLD</name>"));
+        assert!(gpx.contains("<name>Enter ON &amp; ON</name>"));
+        assert!(!gpx.contains("PLATINUM"));
+    }
+
+    const GPX: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<gpx version="1.1" xmlns="http://www.topografix.com/GPX/1/1">
+ <metadata>
+  <name>Schaffhausen &amp; around</name>
+  <time>2024-05-12T09:00:00Z</time>
+ </metadata>
+ <wpt lat="47.66" lon="8.56"><name>Parking</name></wpt>
+ <wpt lat="47.6655" lon="8.5671"><name>START</name></wpt>
+ <wpt lat="47.6755" lon="8.5771"><name>End</name><ele>410</ele></wpt>
+ <rte>
+  <name>Scouting</name>
+  <rtept lat="47.66" lon="8.56" />
+  <rtept lat="47.67" lon="8.57" />
+  <rtept lat="47.68" lon="8.58" />
+ </rte>
+ <trk>
+  <name>Warm-up</name>
+  <trkseg>
+   <trkpt lat="-47.6655080" lon="-8.5671500" />
+  </trkseg>
+ </trk>
+ <trk>
+  <name>Attempt</name>
+  <desc>Second try</desc>
+  <type>hiking</type>
+  <trkseg>
+   <trkpt lat="47.6655080" lon="8.5671500" />
+  </trkseg>
+  <trkseg>
+   <trkpt lat="47.6655040" lon="8.5671580" />
+   <trkpt lat="47.6655010" lon="8.5671610" />
+  </trkseg>
+ </trk>
+</gpx>
+"#;
+
+    #[test]
+    fn load_full() {
+        let gpx = load_gpx(GPX.as_bytes()).unwrap();
+
+        assert_eq!(gpx.name.as_deref(), Some("Schaffhausen & around"));
+        assert_eq!(gpx.time, Some(Timestamp(1715504400000)));
+        assert_eq!(gpx.waypoints.len(), 3);
+        assert_eq!(gpx.waypoints[2].elevation, Some(410.0));
+        assert_eq!(gpx.routes.len(), 1);
+        assert_eq!(gpx.routes[0].name.as_deref(), Some("Scouting"));
+        assert_eq!(gpx.routes[0].points.len(), 3);
+
+        assert_eq!(gpx.tracks.len(), 2);
+        let track = gpx.track_by_name("Attempt").unwrap();
+        assert_eq!(track, &gpx.tracks[1]);
+        assert_eq!(track.description.as_deref(), Some("Second try"));
+        assert_eq!(track.kind.as_deref(), Some("hiking"));
+        assert_eq!(
+            track.segments.iter().map(Vec::len).collect::<Vec<_>>(),
+            [1, 2]
+        );
+        assert_eq!(track.points().count(), 3);
+        assert!(gpx.track_by_name("Cool-down").is_none());
+    }
+
+    #[test]
+    fn target_line_from_waypoints() {
+        let gpx = load_gpx(GPX.as_bytes()).unwrap();
+        assert_eq!(
+            gpx.target_line(),
+            Some((
+                Coordinates {
+                    latitude: 47.6655,
+                    longitude: 8.5671
+                },
+                Coordinates {
+                    latitude: 47.6755,
+                    longitude: 8.5771
+                }
+            ))
+        );
+    }
+
+    #[test]
+    fn target_line_from_route() {
+        let gpx = load_gpx(
+            br#"<gpx>
+ <wpt lat="47.6655" lon="8.5671"><name>start</name></wpt>
+ <wpt lat="47.6755" lon="8.5771"><name>end</name></wpt>
+ <rte><rtept lat="47.66" lon="8.56" /><rtept lat="47.67" lon="8.57" /></rte>
+</gpx>"#,
+        )
+        .unwrap();
+        assert_eq!(
+            gpx.target_line(),
+            Some((
+                Coordinates {
+                    latitude: 47.66,
+                    longitude: 8.56
+                },
+                Coordinates {
+                    latitude: 47.67,
+                    longitude: 8.57
+                }
+            ))
+        );
+        assert_eq!(load_gpx(b"<gpx></gpx>").unwrap().target_line(), None);
+    }
+
+    #[test]
+    fn unescape_entities() {
+        assert_eq!(
+            unescape("a &amp; b &lt;c&gt; &#233;&#x20AC; &unknown; &"),
+            "a & b <c> \u{e9}\u{20ac} &unknown; &"
+        );
+    }
 }
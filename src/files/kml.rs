@@ -0,0 +1,637 @@
+// Copyright 2024 Barbagus
+//
+// This file is part of slmlib.
+//
+// slmlib is free software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// slmlib is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without even
+// the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU General
+// Public License for more details.
+// You should have received a copy of the GNU General Public License along with slmlib. If not, see
+// <https://www.gnu.org/licenses/>.
+
+//! A straight line mission as a [KML](https://developers.google.com/kml/documentation) document
+//! (e.g. as drawn in Google Earth).
+//!
+//! Placemarks are identified by their `id` or their name (case insensitive): the target line is the
+//! two-point `LineString` of the `target-line` placemark (or named "Target line"), or else the
+//! `Point`s of the `start` and `end` placemarks. The track is the `LineString` of the `track`
+//! placemark, or else the first other `LineString`. Placemarks of the `deviation-bands` folder (as
+//! written by [`dump`]) are ignored.
+extern crate alloc;
+
+use crate::{
+    geowizard::{Rank, RankScheme},
+    Coordinates, Deviation, Progress, Slm, TargetLine, Timestamp, TrackPoint,
+};
+use alloc::{string::String, vec::Vec};
+use core::{error, fmt, fmt::Write, str};
+use xmlparser::{ElementEnd, TextPos, Token, Tokenizer};
+
+#[derive(Debug, Clone)]
+pub enum Error {
+    InvalidCoordinate(TextPos),
+    Utf8(str::Utf8Error),
+    XmlForm(TextPos),
+    XmlStack(TextPos),
+    XmlStream(xmlparser::Error),
+}
+
+impl error::Error for Error {}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::InvalidCoordinate(text_pos) => {
+                write!(f, "invalid coordinate at {}", text_pos)
+            }
+            Error::Utf8(utf8_error) => utf8_error.fmt(f),
+            Error::XmlForm(text_pos) => {
+                write!(f, "malformed xml at {}", text_pos)
+            }
+            Error::XmlStack(text_pos) => {
+                write!(f, "xml stack at {} (you found a bug !)", text_pos)
+            }
+            Error::XmlStream(error) => error.fmt(f),
+        }
+    }
+}
+
+impl From<str::Utf8Error> for Error {
+    fn from(value: str::Utf8Error) -> Self {
+        Error::Utf8(value)
+    }
+}
+impl From<xmlparser::Error> for Error {
+    fn from(value: xmlparser::Error) -> Self {
+        Self::XmlStream(value)
+    }
+}
+
+macro_rules! stack_error {
+    ($tokenizer: expr) => {
+        Error::XmlStack($tokenizer.stream().gen_text_pos())
+    };
+}
+
+macro_rules! form_error {
+    ($tokenizer: expr) => {
+        Err(Error::XmlForm($tokenizer.stream().gen_text_pos()))
+    };
+}
+
+/// A track, along with an optional route, as read from a KML document
+#[derive(Debug, Clone, PartialEq)]
+pub struct KMLTrack {
+    pub track: Vec<TrackPoint>,
+    pub start: Option<Coordinates>,
+    pub end: Option<Coordinates>,
+}
+
+/// Parse a KML `coordinates` element text: whitespace separated `longitude,latitude[,altitude]`
+/// tuples
+fn parse_coordinates(text: &str) -> Option<Vec<TrackPoint>> {
+    text.split_ascii_whitespace()
+        .map(|tuple| {
+            let mut values = tuple.split(',');
+            let longitude = values.next()?.parse::<f64>().ok()?;
+            let latitude = values.next()?.parse::<f64>().ok()?;
+            let elevation = match values.next() {
+                Some(elevation) => Some(elevation.parse::<f64>().ok()?),
+                None => None,
+            };
+            if values.next().is_some() {
+                return None;
+            }
+            Some(TrackPoint {
+                coordinates: Coordinates {
+                    latitude,
+                    longitude,
+                },
+                time: None,
+                elevation,
+                speed: None,
+            })
+        })
+        .collect()
+}
+
+/// Identifier of the folder of the deviation bands, as written by [`dump`]
+const BANDS_FOLDER: &str = "deviation-bands";
+
+/// A placemark's identification and (first) geometries
+#[derive(Debug, Default)]
+struct Placemark<'a> {
+    id: Option<&'a str>,
+    name: Option<&'a str>,
+    /// Whether the placemark is part of the deviation bands folder
+    bands: bool,
+    line: Option<Vec<TrackPoint>>,
+    point: Option<Coordinates>,
+}
+
+impl Placemark<'_> {
+    /// Whether the placemark has the given `id`, or the given name (case insensitive)
+    fn is(&self, id: &str, name: &str) -> bool {
+        self.id == Some(id)
+            || self
+                .name
+                .is_some_and(|n| n.trim().eq_ignore_ascii_case(name))
+    }
+}
+
+/// Load a track (and optionally the route) from a KML document
+pub fn load(buf: &[u8]) -> Result<KMLTrack, Error> {
+    let buf = str::from_utf8(buf)?;
+
+    // elements, along with their `id` attribute
+    let mut stack: Vec<(&str, Option<&str>)> = Vec::with_capacity(10);
+    let mut placemarks: Vec<Placemark> = Vec::new();
+    let mut placemark: Option<Placemark> = None;
+
+    let mut tokenizer = Tokenizer::from(buf);
+    while let Some(token) = tokenizer.next() {
+        let token = token?;
+
+        match token {
+            Token::ElementStart { local, .. } => {
+                stack.push((local.as_str(), None));
+                if local.as_str() == "Placemark" {
+                    placemark.replace(Placemark {
+                        bands: stack.contains(&("Folder", Some(BANDS_FOLDER))),
+                        ..Default::default()
+                    });
+                }
+            }
+            Token::Attribute { local, value, .. } => {
+                if local.as_str() == "id" {
+                    if let Some((element, id)) = stack.last_mut() {
+                        id.replace(value.as_str());
+                        if *element == "Placemark" {
+                            if let Some(placemark) = placemark.as_mut() {
+                                placemark.id.replace(value.as_str());
+                            }
+                        }
+                    }
+                }
+            }
+            Token::Text { text } => {
+                let Some(placemark) = placemark.as_mut() else {
+                    continue;
+                };
+                let is_point = match stack.as_slice() {
+                    [.., ("Placemark", _), ("name", _)] => {
+                        placemark.name.replace(text.as_str());
+                        continue;
+                    }
+                    [.., ("LineString", _), ("coordinates", _)] => false,
+                    [.., ("Point", _), ("coordinates", _)] => true,
+                    _ => continue,
+                };
+                let coordinates = parse_coordinates(&text)
+                    .ok_or_else(|| Error::InvalidCoordinate(tokenizer.stream().gen_text_pos()))?;
+                if is_point {
+                    match coordinates.as_slice() {
+                        [point] => {
+                            placemark.point.get_or_insert(point.coordinates);
+                        }
+                        _ => {
+                            return Err(Error::InvalidCoordinate(tokenizer.stream().gen_text_pos()))
+                        }
+                    }
+                } else {
+                    placemark.line.get_or_insert(coordinates);
+                }
+            }
+            Token::ElementEnd { end, .. } => match end {
+                ElementEnd::Open => continue,
+                ElementEnd::Close(_, local) => {
+                    let (element, _) = stack.pop().ok_or_else(|| stack_error!(tokenizer))?;
+                    if local != element {
+                        return form_error!(tokenizer);
+                    }
+                    if element == "Placemark" {
+                        placemarks.extend(placemark.take());
+                    }
+                }
+                ElementEnd::Empty => {
+                    let (element, _) = stack.pop().ok_or_else(|| stack_error!(tokenizer))?;
+                    if element == "Placemark" {
+                        placemarks.extend(placemark.take());
+                    }
+                }
+            },
+            _ => continue,
+        }
+    }
+
+    placemarks.retain(|placemark| !placemark.bands);
+    let is_target_line = |placemark: &Placemark| placemark.is("target-line", "target line");
+
+    let route = placemarks
+        .iter()
+        .filter(|placemark| is_target_line(placemark))
+        .find_map(|placemark| match placemark.line.as_deref() {
+            Some([start, end]) => Some((start.coordinates, end.coordinates)),
+            _ => None,
+        })
+        .or_else(|| {
+            let point = |id: &str| {
+                placemarks
+                    .iter()
+                    .filter(|placemark| placemark.is(id, id))
+                    .find_map(|placemark| placemark.point)
+            };
+            point("start").zip(point("end"))
+        });
+
+    let track = placemarks
+        .iter()
+        .position(|placemark| placemark.line.is_some() && placemark.is("track", "track"))
+        .or_else(|| {
+            placemarks
+                .iter()
+                .position(|placemark| placemark.line.is_some() && !is_target_line(placemark))
+        })
+        .and_then(|i| placemarks.swap_remove(i).line);
+
+    Ok(KMLTrack {
+        track: track.unwrap_or_default(),
+        start: route.map(|(start, _)| start),
+        end: route.map(|(_, end)| end),
+    })
+}
+
+/// Max. distance (in meters) between the vertices of the corridors
+const VERTEX_SPACING: f64 = 500.0;
+
+/// Style identifiers and colors (`aabbggrr`) of the deviation bands: the ranks, out of any rank,
+/// and not en route
+const BANDS: [(&str, &str); 6] = [
+    ("platinum", "ffe5e4e5"),
+    ("gold", "ff37afd4"),
+    ("silver", "ffc0c0c0"),
+    ("bronze", "ff327fcd"),
+    ("none", "ff0000ff"),
+    ("off", "ff7f7f7f"),
+];
+
+fn rank_band(rank: Rank) -> &'static str {
+    match rank {
+        Rank::Platinum => BANDS[0].0,
+        Rank::Gold => BANDS[1].0,
+        Rank::Silver => BANDS[2].0,
+        Rank::Bronze => BANDS[3].0,
+    }
+}
+
+fn band(scheme: &RankScheme, progress: &Progress) -> &'static str {
+    match progress.deviation().map(|deviation| scheme.rank(deviation)) {
+        Some(Some(rank)) => rank_band(rank),
+        Some(None) => BANDS[4].0,
+        None => BANDS[5].0,
+    }
+}
+
+fn write_coordinates<I>(w: &mut String, coordinates: I) -> fmt::Result
+where
+    I: IntoIterator<Item = (Coordinates, Option<f64>)>,
+{
+    w.push_str("<coordinates>");
+    for (i, (coordinates, elevation)) in coordinates.into_iter().enumerate() {
+        if i > 0 {
+            w.push(' ');
+        }
+        write!(w, "{},{}", coordinates.longitude, coordinates.latitude)?;
+        if let Some(elevation) = elevation {
+            write!(w, ",{}", elevation)?;
+        }
+    }
+    w.push_str("</coordinates>");
+    Ok(())
+}
+
+fn write_kml(w: &mut String, slm: &Slm, scheme: &RankScheme) -> fmt::Result {
+    let target_line = TargetLine::new(slm.route_start, slm.route_end);
+
+    writeln!(w, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(w, r#"<kml xmlns="http://www.opengis.net/kml/2.2">"#)?;
+    writeln!(w, "<Document>")?;
+    writeln!(w, "<name>Straight line mission</name>")?;
+
+    // styles
+    writeln!(
+        w,
+        r#"<Style id="target-line"><LineStyle><color>ff000000</color><width>3</width></LineStyle></Style>"#
+    )?;
+    writeln!(
+        w,
+        r#"<Style id="track"><LineStyle><color>ffff0000</color><width>1</width></LineStyle></Style>"#
+    )?;
+    for (id, color) in BANDS {
+        writeln!(
+            w,
+            r#"<Style id="{}"><LineStyle><color>{}</color><width>2</width></LineStyle><PolyStyle><color>3f{}</color><outline>0</outline></PolyStyle></Style>"#,
+            id,
+            color,
+            &color[2..]
+        )?;
+    }
+
+    // target line, as a two-point line (KML viewers draw lines along great circles)
+    writeln!(w, r#"<Placemark id="target-line">"#)?;
+    writeln!(w, "<name>Target line</name>")?;
+    writeln!(w, "<styleUrl>#target-line</styleUrl>")?;
+    w.push_str("<LineString><tessellate>1</tessellate>");
+    write_coordinates(w, [(target_line.start(), None), (target_line.end(), None)])?;
+    writeln!(w, "</LineString>")?;
+    writeln!(w, "</Placemark>")?;
+
+    // corridors, from the widest to the narrowest
+    writeln!(w, "<Folder>")?;
+    writeln!(w, "<name>Corridors</name>")?;
+    for tier in scheme.tiers().iter().rev() {
+        writeln!(w, "<Placemark>")?;
+        writeln!(
+            w,
+            "<name>{} ({} m)</name>",
+            super::escape_xml(&tier.name),
+            tier.max_deviation
+        )?;
+        writeln!(w, "<styleUrl>#{}</styleUrl>", rank_band(tier.rank))?;
+        w.push_str("<Polygon><tessellate>1</tessellate><outerBoundaryIs><LinearRing>");
+        write_coordinates(
+            w,
+            target_line
+                .corridor(tier.max_deviation, VERTEX_SPACING)
+                .expect("valid spacing")
+                .into_iter()
+                .map(|c| (c, None)),
+        )?;
+        writeln!(w, "</LinearRing></outerBoundaryIs></Polygon>")?;
+        writeln!(w, "</Placemark>")?;
+    }
+    writeln!(w, "</Folder>")?;
+
+    // track, as a single line so that it is read back as is
+    writeln!(w, r#"<Placemark id="track">"#)?;
+    writeln!(w, "<name>Track</name>")?;
+    writeln!(w, "<styleUrl>#track</styleUrl>")?;
+    w.push_str("<LineString><tessellate>1</tessellate>");
+    write_coordinates(
+        w,
+        slm.track
+            .iter()
+            .map(|point| (point.coordinates, point.elevation)),
+    )?;
+    writeln!(w, "</LineString>")?;
+    writeln!(w, "</Placemark>")?;
+
+    // deviation bands: the track, split into runs of the same band (sharing their boundary points)
+    writeln!(w, r#"<Folder id="{}">"#, BANDS_FOLDER)?;
+    writeln!(w, "<name>Deviation bands</name>")?;
+    let mut start = 0;
+    while start < slm.track.len() {
+        let current = band(scheme, &slm.track[start].progress);
+        let end = slm.track[start..]
+            .iter()
+            .position(|point| band(scheme, &point.progress) != current)
+            .map(|i| start + i)
+            .unwrap_or(slm.track.len());
+
+        writeln!(w, "<Placemark>")?;
+        writeln!(w, "<styleUrl>#{}</styleUrl>", current)?;
+        if let Some(time) = slm.track[start].time {
+            write!(w, "<TimeSpan><begin>{}</begin>", time)?;
+            let last: Option<Timestamp> = slm.track[end.min(slm.track.len() - 1)].time;
+            if let Some(time) = last {
+                write!(w, "<end>{}</end>", time)?;
+            }
+            writeln!(w, "</TimeSpan>")?;
+        }
+        w.push_str("<LineString><tessellate>1</tessellate>");
+        write_coordinates(
+            w,
+            slm.track[start..usize::min(end + 1, slm.track.len())]
+                .iter()
+                .map(|point| (point.coordinates, point.elevation)),
+        )?;
+        writeln!(w, "</LineString>")?;
+        writeln!(w, "</Placemark>")?;
+
+        start = end;
+    }
+    writeln!(w, "</Folder>")?;
+
+    // max. deviation
+    if let Some(point) = slm.track.iter().find(|point| {
+        matches!(point.progress, Progress::EnRoute { .. })
+            && point.progress.deviation() >= Some(slm.max_deviation)
+    }) {
+        let side = match point.progress {
+            Progress::EnRoute {
+                deviation: Some(Deviation::Left(_)),
+                ..
+            } => " (left)",
+            Progress::EnRoute {
+                deviation: Some(Deviation::Right(_)),
+                ..
+            } => " (right)",
+            _ => "",
+        };
+        writeln!(w, r#"<Placemark id="max-deviation">"#)?;
+        writeln!(w, "<name>Max. deviation</name>")?;
+        writeln!(
+            w,
+            "<description>{:.1} m{}</description>",
+            slm.max_deviation, side
+        )?;
+        w.push_str("<Point>");
+        write_coordinates(w, [(point.coordinates, None)])?;
+        writeln!(w, "</Point>")?;
+        writeln!(w, "</Placemark>")?;
+    }
+
+    writeln!(w, "</Document>")?;
+    writeln!(w, "</kml>")
+}
+
+/// Dump an analyzed straight line mission as a styled KML document: the target line, the medal
+/// corridors (as polygons), the track, the track colour-coded by deviation band and the max.
+/// deviation point.
+///
+/// The corridors and bands are GeoWizard's, see [`dump_with_scheme`] for others.
+pub fn dump(slm: &Slm) -> String {
+    dump_with_scheme(slm, &RankScheme::geowizard())
+}
+
+/// Dump an analyzed straight line mission as a styled KML document (see [`dump`]), with the
+/// corridors and bands of a rank scheme.
+pub fn dump_with_scheme(slm: &Slm, scheme: &RankScheme) -> String {
+    let mut w = String::new();
+    write_kml(&mut w, slm, scheme).expect("write to string");
+    w
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    macro_rules! load_ok_tests {
+        ($($name:ident: $kml:literal => $route:expr,)*) => {
+        $(
+            #[test]
+            fn $name() {
+                let kml = load($kml.as_bytes()).unwrap();
+                let check = vec![
+                    Coordinates{latitude: 54.29600470, longitude: -4.58877725},
+                    Coordinates{latitude: 54.29600654, longitude: -4.58877590},
+                    Coordinates{latitude: 54.29600906, longitude: -4.58876509},
+                ];
+
+                assert_eq!(kml.track.iter().map(|p| p.coordinates).collect::<Vec<_>>(), check);
+                assert_eq!(kml.start.zip(kml.end), $route);
+            }
+        )*
+        }
+    }
+    load_ok_tests! {
+        load_track: r#"<?xml version="1.0" encoding="UTF-8"?>
+<kml xmlns="http://www.opengis.net/kml/2.2">
+ <Placemark>
+  <name>Track</name>
+  <LineString>
+   <coordinates>
+    -4.58877725,54.29600470,12.0 -4.58877590,54.29600654,12.5
+    -4.58876509,54.29600906,13.0
+   </coordinates>
+  </LineString>
+ </Placemark>
+</kml>"# => None,
+        load_line_route: r#"<kml xmlns="http://www.opengis.net/kml/2.2"><Document>
+ <Placemark><LineString><coordinates>-4.58877725,54.29600470 -4.58877590,54.29600654 -4.58876509,54.29600906</coordinates></LineString></Placemark>
+ <Placemark><name>Target line</name><LineString><coordinates>-4.5,54.2 -4.6,54.3</coordinates></LineString></Placemark>
+</Document></kml>"# => Some((
+            Coordinates{latitude: 54.2, longitude: -4.5},
+            Coordinates{latitude: 54.3, longitude: -4.6},
+        )),
+        load_line_id_route: r#"<kml xmlns="http://www.opengis.net/kml/2.2"><Document>
+ <Placemark id="target-line"><LineString><coordinates>-4.5,54.2 -4.6,54.3</coordinates></LineString></Placemark>
+ <Placemark><LineString><coordinates>-4.58877725,54.29600470 -4.58877590,54.29600654 -4.58876509,54.29600906</coordinates></LineString></Placemark>
+</Document></kml>"# => Some((
+            Coordinates{latitude: 54.2, longitude: -4.5},
+            Coordinates{latitude: 54.3, longitude: -4.6},
+        )),
+        load_unnamed_line: r#"<kml xmlns="http://www.opengis.net/kml/2.2"><Document>
+ <Placemark><LineString><coordinates>-4.58877725,54.29600470 -4.58877590,54.29600654 -4.58876509,54.29600906</coordinates></LineString></Placemark>
+ <Placemark><LineString><coordinates>-4.5,54.2 -4.6,54.3</coordinates></LineString></Placemark>
+</Document></kml>"# => None,
+        load_point_route: r#"<kml xmlns="http://www.opengis.net/kml/2.2"><Document>
+ <Placemark><name>Summit</name><Point><coordinates>-4.4,54.1,0</coordinates></Point></Placemark>
+ <Placemark><name>End</name><Point><coordinates>-4.6,54.3,0</coordinates></Point></Placemark>
+ <Placemark><name>Start</name><Point><coordinates>-4.5,54.2,0</coordinates></Point></Placemark>
+ <Placemark><LineString><coordinates>-4.58877725,54.29600470 -4.58877590,54.29600654 -4.58876509,54.29600906</coordinates></LineString></Placemark>
+</Document></kml>"# => Some((
+            Coordinates{latitude: 54.2, longitude: -4.5},
+            Coordinates{latitude: 54.3, longitude: -4.6},
+        )),
+        load_named_track: r#"<kml xmlns="http://www.opengis.net/kml/2.2"><Document>
+ <Placemark><name>Other</name><LineString><coordinates>-4.5,54.2 -4.6,54.3 -4.7,54.4</coordinates></LineString></Placemark>
+ <Placemark><name>Track</name><LineString><coordinates>-4.58877725,54.29600470 -4.58877590,54.29600654 -4.58876509,54.29600906</coordinates></LineString></Placemark>
+</Document></kml>"# => None,
+    }
+
+    #[test]
+    fn load_invalid_coordinates() {
+        let result = load(
+            br#"<kml><Placemark><LineString><coordinates>-4.5;54.2</coordinates></LineString></Placemark></kml>"#,
+        );
+        assert!(matches!(result, Err(Error::InvalidCoordinate(_))));
+    }
+
+    #[test]
+    fn dump_load() {
+        let start = Coordinates {
+            latitude: 45.0,
+            longitude: 7.0,
+        };
+        let end = Coordinates {
+            latitude: 45.01,
+            longitude: 7.0,
+        };
+        let track = [
+            (44.999, 7.0),
+            (45.002, 7.0002),
+            (45.004, 7.0005),
+            (45.006, 6.9999),
+            (45.011, 7.0),
+        ]
+        .map(|(latitude, longitude)| Coordinates {
+            latitude,
+            longitude,
+        });
+        let track: Vec<TrackPoint> = track
+            .into_iter()
+            .zip([Some(402.5), Some(403.0), None, Some(401.25), Some(400.0)])
+            .map(|(coordinates, elevation)| TrackPoint {
+                elevation,
+                ..coordinates.into()
+            })
+            .collect();
+        let slm = crate::analyze(start, end, track.clone());
+
+        let kml = dump(&slm);
+        // off (standby), platinum, gold, platinum, off (arrived)
+        assert_eq!(kml.matches("<styleUrl>#off</styleUrl>").count(), 2);
+        assert_eq!(kml.matches("<styleUrl>#platinum</styleUrl>").count(), 3);
+        assert_eq!(kml.matches("<styleUrl>#gold</styleUrl>").count(), 2);
+        assert_eq!(kml.matches("<Polygon>").count(), 4);
+        assert!(kml.contains("<description>39.4 m (right)</description>"));
+
+        let loaded = load(kml.as_bytes()).unwrap();
+        assert_eq!(loaded.track, track);
+        assert_eq!(loaded.start, Some(start));
+        assert_eq!(loaded.end, Some(end));
+    }
+
+    #[test]
+    fn dump_scheme() {
+        let start = Coordinates {
+            latitude: 45.0,
+            longitude: 7.0,
+        };
+        let end = Coordinates {
+            latitude: 45.01,
+            longitude: 7.0,
+        };
+        let track = [
+            (44.999, 7.0),
+            (45.002, 7.0002),
+            (45.004, 7.0005),
+            (45.006, 6.9999),
+            (45.011, 7.0),
+        ]
+        .map(|(latitude, longitude)| Coordinates {
+            latitude,
+            longitude,
+        });
+        let slm = crate::analyze(start, end, track);
+        let scheme = RankScheme::new(vec![
+            crate::geowizard::RankTier::new(Rank::Gold, 50.0),
+            crate::geowizard::RankTier::new(Rank::Silver, 100.0),
+            crate::geowizard::RankTier::new(Rank::Bronze, 150.0).with_name("COPPER & co"),
+        ])
+        .unwrap();
+
+        let kml = dump_with_scheme(&slm, &scheme);
+        // off (standby), gold, off (arrived)
+        assert_eq!(kml.matches("<styleUrl>#off</styleUrl>").count(), 2);
+        assert_eq!(kml.matches("<styleUrl>#platinum</styleUrl>").count(), 0);
+        assert_eq!(kml.matches("<styleUrl>#gold</styleUrl>").count(), 2);
+        assert_eq!(kml.matches("<Polygon>").count(), 3);
+        assert!(kml.contains("<name>COPPER &amp; co (150 m)</name>"));
+    }
+}
@@ -0,0 +1,245 @@
+// Copyright 2024 Barbagus
+//
+// This file is part of slmlib.
+//
+// slmlib is free software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// slmlib is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without even
+// the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU General
+// Public License for more details.
+// You should have received a copy of the GNU General Public License along with slmlib. If not, see
+// <https://www.gnu.org/licenses/>.
+
+//! A straight line mission as a KMZ archive: a zip file holding a [KML](super::kml) document.
+//!
+//! Only the first `.kml` entry of the archive is read, which must be either stored or deflated.
+extern crate alloc;
+
+use super::{
+    archive::{
+        self, crc32, CENTRAL_HEADER_SIGNATURE, END_SIGNATURE, LOCAL_HEADER_SIGNATURE,
+        METHOD_DEFLATED,
+    },
+    kml::{self, KMLTrack},
+};
+use crate::{geowizard::RankScheme, Slm};
+use alloc::{borrow::Cow, vec::Vec};
+use core::{error, fmt};
+use miniz_oxide::deflate;
+
+#[derive(Debug, Clone)]
+pub enum Error {
+    /// Malformed or unsupported zip archive, at the given byte offset
+    Zip(usize),
+    /// No KML document in the archive
+    NoKml,
+    /// Failed to decompress the KML document
+    Inflate,
+    /// Checksum mismatch of the KML document
+    Crc,
+    Kml(kml::Error),
+}
+
+impl error::Error for Error {}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Zip(offset) => write!(f, "invalid zip archive at byte {}", offset),
+            Error::NoKml => write!(f, "no kml document in archive"),
+            Error::Inflate => write!(f, "failed to decompress kml document"),
+            Error::Crc => write!(f, "kml document checksum mismatch"),
+            Error::Kml(error) => error.fmt(f),
+        }
+    }
+}
+
+impl From<kml::Error> for Error {
+    fn from(value: kml::Error) -> Self {
+        Self::Kml(value)
+    }
+}
+
+impl From<archive::Error> for Error {
+    fn from(value: archive::Error) -> Self {
+        match value {
+            archive::Error::Gzip(offset) | archive::Error::Zip(offset) => Error::Zip(offset),
+            archive::Error::NoTrack => Error::NoKml,
+            archive::Error::Inflate => Error::Inflate,
+            archive::Error::Crc => Error::Crc,
+        }
+    }
+}
+
+/// Name of the document entry of a written archive
+const DOC_NAME: &str = "doc.kml";
+
+/// Extract the first KML document of a zip archive
+fn extract(buf: &[u8]) -> Result<Cow<'_, [u8]>, Error> {
+    let entry = archive::zip_entries(buf)?
+        .into_iter()
+        .find(|entry| entry.name.to_ascii_lowercase().ends_with(b".kml"))
+        .ok_or(Error::NoKml)?;
+    Ok(entry.contents()?)
+}
+
+/// Load a track (and optionally the route) from a KMZ archive
+pub fn load(buf: &[u8]) -> Result<KMLTrack, Error> {
+    Ok(kml::load(&extract(buf)?)?)
+}
+
+/// Dump an analyzed straight line mission as a KMZ archive holding a single (deflated) styled KML
+/// document, see [`kml::dump`].
+pub fn dump(slm: &Slm) -> Vec<u8> {
+    dump_with_scheme(slm, &RankScheme::geowizard())
+}
+
+/// Dump an analyzed straight line mission as a KMZ archive (see [`dump`]), with the corridors and
+/// bands of a rank scheme.
+pub fn dump_with_scheme(slm: &Slm, scheme: &RankScheme) -> Vec<u8> {
+    let document = kml::dump_with_scheme(slm, scheme);
+    let document = document.as_bytes();
+    let compressed = deflate::compress_to_vec(document, 6);
+    let crc = crc32(document);
+
+    let mut w: Vec<u8> = Vec::with_capacity(compressed.len() + 2 * DOC_NAME.len() + 98);
+
+    // header fields shared by the local and central headers:
+    // version needed, flags, method, time, date, crc, sizes, name length, extra length
+    let mut common: Vec<u8> = Vec::with_capacity(26);
+    common.extend_from_slice(&20u16.to_le_bytes());
+    common.extend_from_slice(&0u16.to_le_bytes());
+    common.extend_from_slice(&METHOD_DEFLATED.to_le_bytes());
+    common.extend_from_slice(&0u16.to_le_bytes());
+    common.extend_from_slice(&0x21u16.to_le_bytes()); // 1980-01-01
+    common.extend_from_slice(&crc.to_le_bytes());
+    common.extend_from_slice(&(compressed.len() as u32).to_le_bytes());
+    common.extend_from_slice(&(document.len() as u32).to_le_bytes());
+    common.extend_from_slice(&(DOC_NAME.len() as u16).to_le_bytes());
+    common.extend_from_slice(&0u16.to_le_bytes());
+
+    w.extend_from_slice(&LOCAL_HEADER_SIGNATURE.to_le_bytes());
+    w.extend_from_slice(&common);
+    w.extend_from_slice(DOC_NAME.as_bytes());
+    w.extend_from_slice(&compressed);
+
+    let central = w.len();
+    w.extend_from_slice(&CENTRAL_HEADER_SIGNATURE.to_le_bytes());
+    w.extend_from_slice(&20u16.to_le_bytes()); // version made by
+    w.extend_from_slice(&common);
+    w.extend_from_slice(&0u16.to_le_bytes()); // comment length
+    w.extend_from_slice(&0u16.to_le_bytes()); // disk number
+    w.extend_from_slice(&0u16.to_le_bytes()); // internal attributes
+    w.extend_from_slice(&0u32.to_le_bytes()); // external attributes
+    w.extend_from_slice(&0u32.to_le_bytes()); // local header offset
+    w.extend_from_slice(DOC_NAME.as_bytes());
+    let central_size = w.len() - central;
+
+    w.extend_from_slice(&END_SIGNATURE.to_le_bytes());
+    w.extend_from_slice(&0u16.to_le_bytes()); // disk number
+    w.extend_from_slice(&0u16.to_le_bytes()); // central directory disk
+    w.extend_from_slice(&1u16.to_le_bytes()); // entries on disk
+    w.extend_from_slice(&1u16.to_le_bytes()); // entries
+    w.extend_from_slice(&(central_size as u32).to_le_bytes());
+    w.extend_from_slice(&(central as u32).to_le_bytes());
+    w.extend_from_slice(&0u16.to_le_bytes()); // comment length
+
+    w
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Coordinates;
+
+    #[test]
+    fn dump_load() {
+        let start = Coordinates {
+            latitude: 45.0,
+            longitude: 7.0,
+        };
+        let end = Coordinates {
+            latitude: 45.01,
+            longitude: 7.0,
+        };
+        let track =
+            [(44.999, 7.0), (45.005, 7.0002), (45.011, 7.0)].map(|(latitude, longitude)| {
+                Coordinates {
+                    latitude,
+                    longitude,
+                }
+            });
+        let slm = crate::analyze(start, end, track);
+
+        let kmz = dump(&slm);
+        assert_eq!(&*extract(&kmz).unwrap(), kml::dump(&slm).as_bytes());
+
+        let loaded = load(&kmz).unwrap();
+        assert_eq!(loaded.start, Some(start));
+        assert_eq!(loaded.end, Some(end));
+    }
+
+    #[test]
+    fn load_stored() {
+        let document = br#"<kml><Placemark><LineString><coordinates>7,45 7.1,45.1 7.2,45.2</coordinates></LineString></Placemark></kml>"#;
+        let mut kmz: Vec<u8> = Vec::new();
+        let crc = crc32(document);
+        kmz.extend_from_slice(&LOCAL_HEADER_SIGNATURE.to_le_bytes());
+        kmz.extend_from_slice(&[20, 0, 0, 0, 0, 0, 0, 0, 0x21, 0]);
+        kmz.extend_from_slice(&crc.to_le_bytes());
+        kmz.extend_from_slice(&(document.len() as u32).to_le_bytes());
+        kmz.extend_from_slice(&(document.len() as u32).to_le_bytes());
+        kmz.extend_from_slice(&5u16.to_le_bytes());
+        kmz.extend_from_slice(&0u16.to_le_bytes());
+        kmz.extend_from_slice(b"a.kml");
+        kmz.extend_from_slice(document);
+        let central = kmz.len();
+        kmz.extend_from_slice(&CENTRAL_HEADER_SIGNATURE.to_le_bytes());
+        kmz.extend_from_slice(&[20, 0, 20, 0, 0, 0, 0, 0, 0, 0, 0x21, 0]);
+        kmz.extend_from_slice(&crc.to_le_bytes());
+        kmz.extend_from_slice(&(document.len() as u32).to_le_bytes());
+        kmz.extend_from_slice(&(document.len() as u32).to_le_bytes());
+        kmz.extend_from_slice(&5u16.to_le_bytes());
+        kmz.extend_from_slice(&[0; 16]);
+        kmz.extend_from_slice(b"a.kml");
+        let size = kmz.len() - central;
+        kmz.extend_from_slice(&END_SIGNATURE.to_le_bytes());
+        kmz.extend_from_slice(&[0, 0, 0, 0, 1, 0, 1, 0]);
+        kmz.extend_from_slice(&(size as u32).to_le_bytes());
+        kmz.extend_from_slice(&(central as u32).to_le_bytes());
+        kmz.extend_from_slice(&[0, 0]);
+
+        let loaded = load(&kmz).unwrap();
+        assert_eq!(loaded.track.len(), 3);
+        assert_eq!(loaded.start, None);
+    }
+
+    macro_rules! load_err_tests {
+        ($($name:ident: $kmz:expr => $err:pat,)*) => {
+        $(
+            #[test]
+            fn $name() {
+                let kmz: Vec<u8> = $kmz;
+                assert!(matches!(load(&kmz), Err($err)));
+            }
+        )*
+        }
+    }
+
+    load_err_tests! {
+        load_err_not_zip: b"<kml></kml>".to_vec() => Error::Zip(_),
+        load_err_crc: {
+            let slm = crate::analyze(
+                Coordinates{latitude: 45.0, longitude: 7.0},
+                Coordinates{latitude: 45.1, longitude: 7.0},
+                [] as [Coordinates; 0],
+            );
+            let mut kmz = dump(&slm);
+            let central = archive::u32_at(&kmz, kmz.len() - 6).unwrap() as usize;
+            kmz[central + 16] ^= 0xff;
+            kmz
+        } => Error::Crc,
+    }
+}
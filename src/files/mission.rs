@@ -0,0 +1,504 @@
+// Copyright 2024 Barbagus
+//
+// This file is part of slmlib.
+//
+// slmlib is free software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// slmlib is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without even
+// the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU General
+// Public License for more details.
+// You should have received a copy of the GNU General Public License along with slmlib. If not, see
+// <https://www.gnu.org/licenses/>.
+
+//! A straight line mission definition, as a JSON document, so that the same line is scored the
+//! same way by everyone. E.g.:
+//! ```json
+//! {
+//!   "name": "Wales",
+//!   "start": {"latitude": 52.606, "longitude": -4.09},
+//!   "end": {"latitude": 52.554, "longitude": -3.131},
+//!   "corridor": 100.0,
+//!   "scoring": ["pro", "amateur", {"burdell": {"step": 10, "coefficient": 180}}, "rank"],
+//!   "leniency": 1.0,
+//!   "exclusion_zones": [
+//!     {"name": "Reservoir", "polygon": [
+//!       {"latitude": 52.58, "longitude": -3.7},
+//!       {"latitude": 52.59, "longitude": -3.7},
+//!       {"latitude": 52.59, "longitude": -3.68}
+//!     ]}
+//!   ],
+//!   "metadata": {"author": "GeoWizard", "date": "2024-05-12", "region": "Wales"}
+//! }
+//! ```
+//! Only the name and the target line endpoints are required.
+extern crate alloc;
+
+use crate::{
+    burdell, geowizard, scoring::Scorer, AnalyzeError, AnalyzeSettings, Coordinates, Slm,
+    TargetLine, TrackPoint,
+};
+use alloc::{boxed::Box, string::String, vec::Vec};
+use core::{error, fmt};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug)]
+pub enum Error {
+    Json(serde_json::Error),
+    /// Invalid target line (invalid coordinates, coincident or antipodal endpoints)
+    Route(AnalyzeError),
+    /// Corridor width is not a positive finite value
+    Corridor(f64),
+    /// Leniency is not a percentage in the [0, 100) range
+    Leniency(f64),
+    /// Exclusion zone (index) has less than three vertices, or invalid coordinates
+    ExclusionZone(usize),
+}
+
+impl error::Error for Error {}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Json(error) => error.fmt(f),
+            Error::Route(error) => write!(f, "invalid target line: {}", error),
+            Error::Corridor(corridor) => write!(f, "invalid corridor width {}", corridor),
+            Error::Leniency(leniency) => write!(f, "invalid leniency {}", leniency),
+            Error::ExclusionZone(index) => write!(f, "invalid exclusion zone #{}", index),
+        }
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(value: serde_json::Error) -> Self {
+        Error::Json(value)
+    }
+}
+
+/// A scoring preset of a mission
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ScoringPreset {
+    /// Burdell score, "Pro" level
+    Pro,
+    /// Burdell score, "Amateur" level
+    Amateur,
+    /// Burdell score, "Newbie" level
+    Newbie,
+    /// Burdell score, custom settings (e.g. `{"burdell": {"step": 10, "coefficient": 180}}`)
+    Burdell(burdell::BurdellSettings),
+    /// GeoWizard medal rank
+    Rank,
+    /// Rank of a custom scheme (e.g. `{"ranks": [{"rank": "Gold", "name": "GOLD", "max_deviation":
+    /// 50}, {"rank": "Silver", "name": "SILVER", "max_deviation": 100}]}`)
+    Ranks(geowizard::RankScheme),
+}
+
+impl ScoringPreset {
+    /// The Burdell settings of the preset, if a Burdell score
+    pub fn burdell_settings(&self) -> Option<burdell::BurdellSettings> {
+        match self {
+            ScoringPreset::Pro => Some(burdell::LVL_PRO),
+            ScoringPreset::Amateur => Some(burdell::LVL_AMATEUR),
+            ScoringPreset::Newbie => Some(burdell::LVL_NEWBIE),
+            ScoringPreset::Burdell(settings) => Some(*settings),
+            ScoringPreset::Rank | ScoringPreset::Ranks(_) => None,
+        }
+    }
+
+    /// The rank scheme of the preset, if a rank
+    pub fn rank_scheme(&self) -> Option<geowizard::RankScheme> {
+        match self {
+            ScoringPreset::Rank => Some(geowizard::RankScheme::geowizard()),
+            ScoringPreset::Ranks(scheme) => Some(scheme.clone()),
+            _ => None,
+        }
+    }
+
+    /// The scorer of the preset
+    pub fn scorer(&self) -> Box<dyn Scorer> {
+        match (self, self.burdell_settings()) {
+            (_, Some(settings)) => Box::new(settings),
+            (ScoringPreset::Ranks(scheme), None) => Box::new(scheme.clone()),
+            (_, None) => Box::new(geowizard::MedalRank),
+        }
+    }
+}
+
+/// An area where deviating from the line is allowed (e.g. private land, a lake): track points
+/// within it are ignored.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExclusionZone {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Vertices of the zone's outline (implicitly closed)
+    pub polygon: Vec<Coordinates>,
+}
+
+impl ExclusionZone {
+    /// Whether the zone contains a point; the outline edges are taken as straight segments in the
+    /// latitude/longitude plane, which only suits small zones away from the antimeridian
+    pub fn contains(&self, point: Coordinates) -> bool {
+        let (x, y) = (point.longitude, point.latitude);
+        let mut inside = false;
+        let mut j = self.polygon.len().wrapping_sub(1);
+        for (i, vi) in self.polygon.iter().enumerate() {
+            let vj = &self.polygon[j];
+            if (vi.latitude > y) != (vj.latitude > y)
+                && x < (vj.longitude - vi.longitude) * (y - vi.latitude)
+                    / (vj.latitude - vi.latitude)
+                    + vi.longitude
+            {
+                inside = !inside;
+            }
+            j = i;
+        }
+        inside
+    }
+}
+
+/// Descriptive information about a mission
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Metadata {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    /// Date of the mission (free form, e.g. `2024-05-12`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub date: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub region: Option<String>,
+}
+
+/// A straight line mission definition
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Mission {
+    pub name: String,
+    /// Start of the target line
+    pub start: Coordinates,
+    /// End of the target line
+    pub end: Coordinates,
+    /// Width of the corridor (in meters) the track must stay within, i.e. half of it on each side
+    /// of the line
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub corridor: Option<f64>,
+    /// Scores to compute (all of them if empty)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub scoring: Vec<ScoringPreset>,
+    /// Percentage of worst points ignored, see [`crate::apply_leniency`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub leniency: Option<f64>,
+    #[serde(default)]
+    pub settings: AnalyzeSettings,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exclusion_zones: Vec<ExclusionZone>,
+    #[serde(default)]
+    pub metadata: Metadata,
+}
+
+impl Mission {
+    /// Create a mission with no options
+    pub fn new(name: String, start: Coordinates, end: Coordinates) -> Self {
+        Self {
+            name,
+            start,
+            end,
+            corridor: None,
+            scoring: Vec::new(),
+            leniency: None,
+            settings: AnalyzeSettings::default(),
+            exclusion_zones: Vec::new(),
+            metadata: Metadata::default(),
+        }
+    }
+
+    /// The target line of the mission
+    pub fn target_line(&self) -> TargetLine {
+        TargetLine::new(self.start, self.end)
+    }
+
+    /// Check the mission is consistent: valid target line, corridor and leniency, and exclusion
+    /// zones of at least three valid vertices
+    pub fn validate(&self) -> Result<(), Error> {
+        crate::try_analyze_with(self.settings, self.start, self.end, [] as [Coordinates; 0])
+            .map_err(Error::Route)?;
+
+        if let Some(corridor) = self.corridor {
+            if !(corridor.is_finite() && corridor > 0.0) {
+                return Err(Error::Corridor(corridor));
+            }
+        }
+        if let Some(leniency) = self.leniency {
+            if !(0.0..100.0).contains(&leniency) {
+                return Err(Error::Leniency(leniency));
+            }
+        }
+        for (i, zone) in self.exclusion_zones.iter().enumerate() {
+            let valid = |c: &Coordinates| {
+                (-90.0..=90.0).contains(&c.latitude) && (-180.0..=180.0).contains(&c.longitude)
+            };
+            if zone.polygon.len() < 3 || !zone.polygon.iter().all(valid) {
+                return Err(Error::ExclusionZone(i));
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether a point lies in one of the exclusion zones
+    pub fn is_excluded(&self, point: Coordinates) -> bool {
+        self.exclusion_zones.iter().any(|zone| zone.contains(point))
+    }
+
+    /// Analyze a track against the mission: points within exclusion zones are dropped, the
+    /// mission's analysis settings are used and its leniency is applied.
+    pub fn analyze<I>(&self, track: I) -> Result<Slm, AnalyzeError>
+    where
+        I: IntoIterator,
+        I::Item: Into<TrackPoint>,
+    {
+        let track = track
+            .into_iter()
+            .map(Into::into)
+            .filter(|point: &TrackPoint| !self.is_excluded(point.coordinates));
+        let slm = crate::try_analyze_with(self.settings, self.start, self.end, track)?;
+        Ok(match self.leniency {
+            Some(leniency) => crate::apply_leniency(&slm, leniency),
+            None => slm,
+        })
+    }
+
+    /// The rank scheme of the mission: that of its first rank preset, GeoWizard's if none
+    pub fn rank_scheme(&self) -> geowizard::RankScheme {
+        self.scoring
+            .iter()
+            .find_map(ScoringPreset::rank_scheme)
+            .unwrap_or_default()
+    }
+
+    /// Whether an analyzed track stayed within the mission's corridor, if it has one
+    pub fn within_corridor(&self, slm: &Slm) -> Option<bool> {
+        self.corridor
+            .map(|corridor| slm.max_deviation <= corridor / 2.0)
+    }
+}
+
+/// Load (and validate) a mission definition
+pub fn load(buf: &[u8]) -> Result<Mission, Error> {
+    let mission = serde_json::from_slice::<Mission>(buf)?;
+    mission.validate()?;
+    Ok(mission)
+}
+
+/// Dump a mission definition
+pub fn dump(mission: &Mission) -> String {
+    serde_json::to_string_pretty(mission).expect("serialize to string")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Progress;
+    use alloc::vec;
+
+    const WALES: &str = r#"{
+  "name": "Wales",
+  "start": {"latitude": 52.606, "longitude": -4.09},
+  "end": {"latitude": 52.554, "longitude": -3.131},
+  "corridor": 100.0,
+  "scoring": ["pro", "amateur", {"burdell": {"step": 10, "coefficient": 180}}, "rank"],
+  "leniency": 1.0,
+  "exclusion_zones": [
+    {"name": "Reservoir", "polygon": [
+      {"latitude": 52.58, "longitude": -3.7},
+      {"latitude": 52.59, "longitude": -3.7},
+      {"latitude": 52.59, "longitude": -3.68}
+    ]}
+  ],
+  "metadata": {"author": "GeoWizard", "date": "2024-05-12", "region": "Wales"}
+}"#;
+
+    #[test]
+    fn load_full() {
+        let mission = load(WALES.as_bytes()).unwrap();
+
+        assert_eq!(mission.name, "Wales");
+        assert_eq!(
+            mission.end,
+            Coordinates {
+                latitude: 52.554,
+                longitude: -3.131
+            }
+        );
+        assert_eq!(mission.corridor, Some(100.0));
+        assert_eq!(
+            mission.scoring,
+            [
+                ScoringPreset::Pro,
+                ScoringPreset::Amateur,
+                ScoringPreset::Burdell(burdell::BurdellSettings::new(10.0, 180.0).unwrap()),
+                ScoringPreset::Rank
+            ]
+        );
+        assert_eq!(mission.leniency, Some(1.0));
+        assert_eq!(mission.settings, AnalyzeSettings::default());
+        assert_eq!(mission.exclusion_zones.len(), 1);
+        assert_eq!(mission.metadata.region.as_deref(), Some("Wales"));
+    }
+
+    #[test]
+    fn load_minimal() {
+        let mission = load(
+            br#"{"name": "Wales", "start": {"latitude": 52.606, "longitude": -4.09},
+                "end": {"latitude": 52.554, "longitude": -3.131}}"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            mission,
+            Mission::new("Wales".into(), mission.start, mission.end)
+        );
+    }
+
+    #[test]
+    fn dump_load() {
+        let mission = load(WALES.as_bytes()).unwrap();
+        assert_eq!(load(dump(&mission).as_bytes()).unwrap(), mission);
+    }
+
+    macro_rules! load_err_tests {
+        ($($name:ident: $json:literal => $err:pat,)*) => {
+        $(
+            #[test]
+            fn $name() {
+                assert!(matches!(load($json.as_bytes()), Err($err)));
+            }
+        )*
+        }
+    }
+
+    load_err_tests! {
+        load_err_ranks: r#"{"name": "x", "start": {"latitude": 1, "longitude": 2},
+            "end": {"latitude": 1, "longitude": 3}, "scoring": [{"ranks": []}]}"# => Error::Json(_),
+        load_err_missing_end: r#"{"name": "x", "start": {"latitude": 1, "longitude": 2}}"# => Error::Json(_),
+        load_err_unknown_field: r#"{"name": "x", "start": {"latitude": 1, "longitude": 2},
+            "end": {"latitude": 1, "longitude": 3}, "width": 1}"# => Error::Json(_),
+        load_err_degenerate: r#"{"name": "x", "start": {"latitude": 1, "longitude": 2},
+            "end": {"latitude": 1, "longitude": 2}}"# => Error::Route(AnalyzeError::DegenerateRoute),
+        load_err_coordinates: r#"{"name": "x", "start": {"latitude": 91, "longitude": 2},
+            "end": {"latitude": 1, "longitude": 2}}"# => Error::Route(AnalyzeError::InvalidCoordinates(_)),
+        load_err_corridor: r#"{"name": "x", "start": {"latitude": 1, "longitude": 2},
+            "end": {"latitude": 1, "longitude": 3}, "corridor": -5}"# => Error::Corridor(_),
+        load_err_leniency: r#"{"name": "x", "start": {"latitude": 1, "longitude": 2},
+            "end": {"latitude": 1, "longitude": 3}, "leniency": 100}"# => Error::Leniency(_),
+        load_err_burdell: r#"{"name": "x", "start": {"latitude": 1, "longitude": 2},
+            "end": {"latitude": 1, "longitude": 3}, "scoring": [{"burdell": {"step": 0, "coefficient": 1}}]}"# => Error::Json(_),
+        load_err_zone: r#"{"name": "x", "start": {"latitude": 1, "longitude": 2},
+            "end": {"latitude": 1, "longitude": 3}, "exclusion_zones": [{"polygon": [
+            {"latitude": 1, "longitude": 2}, {"latitude": 1, "longitude": 3}]}]}"# => Error::ExclusionZone(0),
+    }
+
+    #[test]
+    fn load_rank_scheme() {
+        let mission = load(
+            br#"{"name": "x", "start": {"latitude": 1, "longitude": 2},
+                "end": {"latitude": 1, "longitude": 3}, "scoring": ["pro", {"ranks": [
+                {"rank": "Gold", "name": "GOLD", "max_deviation": 50},
+                {"rank": "Bronze", "name": "COPPER", "max_deviation": 150}]}]}"#,
+        )
+        .unwrap();
+
+        let scheme = geowizard::RankScheme::new(vec![
+            geowizard::RankTier::new(geowizard::Rank::Gold, 50.0),
+            geowizard::RankTier::new(geowizard::Rank::Bronze, 150.0).with_name("COPPER"),
+        ])
+        .unwrap();
+        assert_eq!(
+            mission.scoring,
+            [ScoringPreset::Pro, ScoringPreset::Ranks(scheme.clone())]
+        );
+        assert_eq!(mission.rank_scheme(), scheme);
+        assert_eq!(
+            mission.scoring[1].scorer().name(),
+            "Rank (GOLD 50 m, COPPER 150 m)"
+        );
+        assert_eq!(load(dump(&mission).as_bytes()).unwrap(), mission);
+
+        let mission = load(WALES.as_bytes()).unwrap();
+        assert_eq!(mission.rank_scheme(), geowizard::RankScheme::geowizard());
+    }
+
+    #[test]
+    fn exclusion_zone_contains() {
+        let zone = ExclusionZone {
+            name: None,
+            polygon: [(0.0, 0.0), (0.0, 1.0), (1.0, 1.0), (1.0, 0.0)]
+                .map(|(latitude, longitude)| Coordinates {
+                    latitude,
+                    longitude,
+                })
+                .into(),
+        };
+        let contains = |latitude, longitude| {
+            zone.contains(Coordinates {
+                latitude,
+                longitude,
+            })
+        };
+        assert!(contains(0.5, 0.5));
+        assert!(contains(0.1, 0.9));
+        assert!(!contains(1.5, 0.5));
+        assert!(!contains(0.5, -0.1));
+    }
+
+    #[test]
+    fn analyze_exclusion_zone() {
+        let mut mission = Mission::new(
+            "x".into(),
+            Coordinates {
+                latitude: 45.0,
+                longitude: 7.0,
+            },
+            Coordinates {
+                latitude: 45.1,
+                longitude: 7.0,
+            },
+        );
+        let track =
+            [(45.02, 7.0), (45.05, 7.01), (45.08, 7.0)].map(|(latitude, longitude)| Coordinates {
+                latitude,
+                longitude,
+            });
+
+        let slm = mission.analyze(track).unwrap();
+        assert_eq!(slm.track.len(), 3);
+        assert!(slm.max_deviation > 700.0);
+
+        mission.corridor = Some(100.0);
+        assert_eq!(mission.within_corridor(&slm), Some(false));
+
+        mission.exclusion_zones.push(ExclusionZone {
+            name: None,
+            polygon: [
+                (45.04, 7.005),
+                (45.06, 7.005),
+                (45.06, 7.015),
+                (45.04, 7.015),
+            ]
+            .map(|(latitude, longitude)| Coordinates {
+                latitude,
+                longitude,
+            })
+            .into(),
+        });
+
+        let slm = mission.analyze(track).unwrap();
+        assert_eq!(slm.track.len(), 2);
+        assert!(slm
+            .track
+            .iter()
+            .all(|point| matches!(point.progress, Progress::EnRoute { .. })));
+        assert!(slm.max_deviation < 1.0);
+        assert_eq!(mission.within_corridor(&slm), Some(true));
+    }
+}
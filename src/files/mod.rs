@@ -12,11 +12,345 @@
 // You should have received a copy of the GNU General Public License along with slmlib. If not, see
 // <https://www.gnu.org/licenses/>.
 
+#[cfg(feature = "compress")]
+pub mod archive;
 #[cfg(feature = "csv")]
 pub mod csv;
+#[cfg(feature = "fit")]
+pub mod fit;
 #[cfg(any(test, feature = "fix"))]
 pub mod fix;
+#[cfg(any(test, feature = "geojson"))]
+pub mod geojson;
 #[cfg(feature = "gpx")]
 pub mod gpx;
+#[cfg(feature = "kml")]
+pub mod kml;
+#[cfg(feature = "kmz")]
+pub mod kmz;
+#[cfg(feature = "mission")]
+pub mod mission;
+#[cfg(feature = "nmea")]
+pub mod nmea;
 #[cfg(any(test, feature = "sml"))]
 pub mod sml;
+#[cfg(feature = "tcx")]
+pub mod tcx;
+
+mod detect;
+
+pub use detect::{detect, Format, ParseFormatError};
+
+extern crate alloc;
+
+use crate::{Coordinates, TrackPoint};
+use alloc::vec::Vec;
+use core::{error, fmt};
+
+/// Escape the markup characters of a text, for an XML document
+#[cfg(any(feature = "gpx", feature = "kml"))]
+fn escape_xml(text: &str) -> alloc::string::String {
+    let mut escaped = alloc::string::String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// A track, along with an optional route, as read from a file of any supported format
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnyTrack {
+    pub format: Format,
+    pub track: Vec<TrackPoint>,
+    pub start: Option<Coordinates>,
+    pub end: Option<Coordinates>,
+}
+
+/// An error occurring while loading a file of any supported format
+#[derive(Debug)]
+pub enum Error {
+    /// The format could not be detected
+    Unknown,
+    /// The format does not hold a track, or its support is not enabled (see crate features)
+    Unsupported(Format),
+    #[cfg(feature = "compress")]
+    Archive(archive::Error),
+    #[cfg(feature = "csv")]
+    Csv(csv::Error),
+    #[cfg(feature = "fit")]
+    Fit(fit::Error),
+    #[cfg(any(test, feature = "geojson"))]
+    GeoJson(geojson::Error),
+    #[cfg(feature = "gpx")]
+    Gpx(gpx::Error),
+    #[cfg(feature = "kml")]
+    Kml(kml::Error),
+    #[cfg(feature = "kmz")]
+    Kmz(kmz::Error),
+    #[cfg(feature = "nmea")]
+    Nmea(nmea::Error),
+    #[cfg(any(test, feature = "sml"))]
+    Sml(serde_json::Error),
+    #[cfg(feature = "tcx")]
+    Tcx(tcx::Error),
+}
+
+impl error::Error for Error {}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Unknown => f.write_str("unknown file format"),
+            Error::Unsupported(format) => write!(f, "unsupported file format: {}", format),
+            #[cfg(feature = "compress")]
+            Error::Archive(error) => write!(f, "archive: {}", error),
+            #[cfg(feature = "csv")]
+            Error::Csv(error) => write!(f, "csv: {}", error),
+            #[cfg(feature = "fit")]
+            Error::Fit(error) => write!(f, "fit: {}", error),
+            #[cfg(any(test, feature = "geojson"))]
+            Error::GeoJson(error) => write!(f, "geojson: {}", error),
+            #[cfg(feature = "gpx")]
+            Error::Gpx(error) => write!(f, "gpx: {}", error),
+            #[cfg(feature = "kml")]
+            Error::Kml(error) => write!(f, "kml: {}", error),
+            #[cfg(feature = "kmz")]
+            Error::Kmz(error) => write!(f, "kmz: {}", error),
+            #[cfg(feature = "nmea")]
+            Error::Nmea(error) => write!(f, "nmea: {}", error),
+            #[cfg(any(test, feature = "sml"))]
+            Error::Sml(error) => write!(f, "sml: {}", error),
+            #[cfg(feature = "tcx")]
+            Error::Tcx(error) => write!(f, "tcx: {}", error),
+        }
+    }
+}
+
+macro_rules! from_error {
+    ($($feature:meta, $error:ty => $variant:ident;)*) => {
+    $(
+        #[cfg($feature)]
+        impl From<$error> for Error {
+            fn from(value: $error) -> Self {
+                Error::$variant(value)
+            }
+        }
+    )*
+    };
+}
+
+from_error! {
+    feature = "compress", archive::Error => Archive;
+    feature = "csv", csv::Error => Csv;
+    feature = "fit", fit::Error => Fit;
+    any(test, feature = "geojson"), geojson::Error => GeoJson;
+    feature = "gpx", gpx::Error => Gpx;
+    feature = "kml", kml::Error => Kml;
+    feature = "kmz", kmz::Error => Kmz;
+    feature = "nmea", nmea::Error => Nmea;
+    any(test, feature = "sml"), serde_json::Error => Sml;
+    feature = "tcx", tcx::Error => Tcx;
+}
+
+/// Load a track from a file of the given format, with the default options of that format: CSV
+/// dialect is detected, only the first GPX track is read, etc.
+///
+/// With the `compress` feature, the file may also be gzipped or zipped (see
+/// [`archive::decompress`]).
+#[cfg_attr(
+    not(any(
+        test,
+        feature = "csv",
+        feature = "fit",
+        feature = "geojson",
+        feature = "gpx",
+        feature = "kml",
+        feature = "nmea",
+        feature = "sml",
+        feature = "tcx"
+    )),
+    allow(unused_variables, unreachable_code)
+)]
+pub fn load_as(format: Format, buf: &[u8]) -> Result<AnyTrack, Error> {
+    #[cfg(feature = "compress")]
+    let buf = &*match format {
+        // a KMZ document is a zip archive itself
+        Format::Kmz if !archive::is_gzip(buf) => alloc::borrow::Cow::Borrowed(buf),
+        _ => archive::decompress(buf)?,
+    };
+
+    let (track, start, end): (Vec<TrackPoint>, Option<Coordinates>, Option<Coordinates>) =
+        match format {
+            #[cfg(feature = "csv")]
+            Format::Csv => (csv::CsvOptions::new().load(buf)?, None, None),
+            #[cfg(feature = "fit")]
+            Format::Fit => (fit::load(buf)?, None, None),
+            #[cfg(any(test, feature = "geojson"))]
+            Format::GeoJson => {
+                let geojson = geojson::load(buf)?;
+                (geojson.track, geojson.start, geojson.end)
+            }
+            #[cfg(feature = "gpx")]
+            Format::Gpx => {
+                let gpx = gpx::load_gpx(buf)?;
+                let (start, end) = gpx.target_line().unzip();
+                let track = gpx
+                    .tracks
+                    .first()
+                    .map(|track| track.points().collect())
+                    .unwrap_or_default();
+                (track, start, end)
+            }
+            #[cfg(feature = "kml")]
+            Format::Kml => {
+                let kml = kml::load(buf)?;
+                (kml.track, kml.start, kml.end)
+            }
+            #[cfg(feature = "kmz")]
+            Format::Kmz => {
+                let kml = kmz::load(buf)?;
+                (kml.track, kml.start, kml.end)
+            }
+            #[cfg(feature = "nmea")]
+            Format::Nmea => {
+                let track = nmea::load(buf)?.into_iter().map(TrackPoint::from).collect();
+                (track, None, None)
+            }
+            #[cfg(any(test, feature = "sml"))]
+            Format::Sml => {
+                let attempt = sml::load(buf)?;
+                let (start, end) = match (&attempt.target_line_start, &attempt.target_line_end) {
+                    (Some(_), Some(_)) => {
+                        let (start, end) = attempt.route();
+                        (Some(start), Some(end))
+                    }
+                    _ => (None, None),
+                };
+                (attempt.track().map(TrackPoint::from).collect(), start, end)
+            }
+            #[cfg(feature = "tcx")]
+            Format::Tcx => (tcx::load(buf)?, None, None),
+            format => return Err(Error::Unsupported(format)),
+        };
+
+    Ok(AnyTrack {
+        format,
+        track,
+        start,
+        end,
+    })
+}
+
+/// Load a track from a file of any supported format, detected from its content (see [`detect`])
+///
+/// With the `compress` feature, the file may also be gzipped or zipped, the format then being
+/// detected from the decompressed content.
+///
+/// A file of a detected format that does not hold a track (i.e. [`Format::Fix`], scores published
+/// by scoremyline) fails with [`Error::Unsupported`], rather than [`Error::Unknown`].
+pub fn load_any(buf: &[u8]) -> Result<AnyTrack, Error> {
+    if let Some(format) = detect(buf) {
+        return load_as(format, buf);
+    }
+    #[cfg(feature = "compress")]
+    if archive::is_gzip(buf) || archive::is_zip(buf) {
+        let buf = archive::decompress(buf)?;
+        return load_as(detect(&buf).ok_or(Error::Unknown)?, &buf);
+    }
+    Err(Error::Unknown)
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use std::{fs, path};
+
+    macro_rules! detect_fixture_tests {
+        ($($name:ident: $file:literal => $format:expr,)*) => {
+        $(
+            #[test]
+            fn $name() {
+                let buf = fs::read(path::Path::new("fixtures").join($file)).expect("read fixture");
+                assert_eq!(detect(&buf), Some($format));
+            }
+        )*
+        }
+    }
+
+    detect_fixture_tests! {
+        detect_fixture_csv: "archie-iom.csv" => Format::Csv,
+        detect_fixture_fix: "archie-iom.json" => Format::Fix,
+        detect_fixture_gpx: "schaffhausen.gpx" => Format::Gpx,
+        detect_fixture_sml: "archie-iom.sml" => Format::Sml,
+    }
+
+    #[test]
+    fn load_any_sml() {
+        let buf = fs::read("fixtures/archie-iom.sml").expect("read fixture");
+        let attempt = sml::load(&buf).unwrap();
+        let any = load_any(&buf).unwrap();
+
+        assert_eq!(any.format, Format::Sml);
+        assert_eq!(any.track.len(), attempt.points.len());
+        assert_eq!((any.start.unwrap(), any.end.unwrap()), attempt.route());
+    }
+
+    #[test]
+    fn load_any_geojson() {
+        let any = load_any(
+            br#"{"type": "LineString", "coordinates": [[-1.91786, 52.6060], [-1.91787, 52.6061]]}"#,
+        )
+        .unwrap();
+
+        assert_eq!(any.format, Format::GeoJson);
+        assert_eq!(any.track.len(), 2);
+        assert_eq!(any.start, None);
+    }
+
+    #[cfg(feature = "compress")]
+    #[test]
+    fn load_any_gzip() {
+        let geojson =
+            br#"{"type": "LineString", "coordinates": [[-1.91786, 52.6060], [-1.91787, 52.6061]]}"#;
+        let mut buf = alloc::vec![0x1f, 0x8b, 8, 0, 0, 0, 0, 0, 0, 3];
+        buf.extend(miniz_oxide::deflate::compress_to_vec(geojson, 6));
+        buf.extend(archive::crc32(geojson).to_le_bytes());
+        buf.extend((geojson.len() as u32).to_le_bytes());
+
+        let any = load_any(&buf).unwrap();
+        assert_eq!(any.format, Format::GeoJson);
+        assert_eq!(any.track.len(), 2);
+        assert_eq!(load_as(Format::GeoJson, &buf).unwrap(), any);
+    }
+
+    #[test]
+    fn load_any_fix() {
+        // detected, but without a track
+        let buf = fs::read("fixtures/archie-iom.json").expect("read fixture");
+        assert!(matches!(
+            load_any(&buf),
+            Err(Error::Unsupported(Format::Fix))
+        ));
+    }
+
+    #[test]
+    fn load_any_unknown() {
+        assert!(matches!(load_any(b"hello world"), Err(Error::Unknown)));
+    }
+
+    #[test]
+    fn load_as_error() {
+        assert!(matches!(
+            load_as(Format::Sml, b"{\"Attempt\": 1}"),
+            Err(Error::Sml(_))
+        ));
+    }
+}
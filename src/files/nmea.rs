@@ -0,0 +1,533 @@
+// Copyright 2024 Barbagus
+//
+// This file is part of slmlib.
+//
+// slmlib is free software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// slmlib is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without even
+// the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU General
+// Public License for more details.
+// You should have received a copy of the GNU General Public License along with slmlib. If not, see
+// <https://www.gnu.org/licenses/>.
+
+//! A straight line mission track as NMEA 0183 sentences (e.g. a raw GPS logger dump, or a live
+//! serial feed). Specifically:
+//!  - Only `GGA` and `RMC` sentences from the `GP` (GPS) and `GN` (multi-constellation) talkers are
+//!    read, every other sentence is ignored.
+//!  - Checksums are validated when present.
+//!  - Sentences sharing the same UTC time make up a single fix.
+//!  - Fixes flagged as invalid (`GGA` fix quality below the minimum, or `RMC` void status) are
+//!    dropped.
+//!  - The date is taken from the last `RMC` sentence and rolled over at midnight; fixes before the
+//!    first date are not timed.
+//!
+//! Example:
+//! ```text
+//!$GPRMC,094107.00,A,5417.76028,N,00435.32664,W,8.26,42.1,120524,,,A*47
+//!$GPGGA,094107.00,5417.76028,N,00435.32664,W,1,09,0.92,12.5,M,51.2,M,,*7C
+//! ```
+extern crate alloc;
+
+use crate::{time::days_from_civil, Coordinates, Timestamp, TrackPoint};
+use alloc::{string::String, vec::Vec};
+use core::{error, fmt, str};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ErrorKind {
+    Checksum,
+    Syntax,
+    Utf8,
+    Value,
+}
+
+#[derive(Debug, Clone)]
+pub struct Error {
+    pub line: u64,
+    /// The offending sentence
+    pub sentence: String,
+    pub kind: ErrorKind,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: ", self.line)?;
+        match self.kind {
+            ErrorKind::Checksum => f.write_str("checksum mismatch")?,
+            ErrorKind::Syntax => f.write_str("ill-formed sentence")?,
+            ErrorKind::Utf8 => f.write_str("invalid utf-8 encoding")?,
+            ErrorKind::Value => f.write_str("ill-formed field value")?,
+        }
+        write!(f, " '{}'", self.sentence)
+    }
+}
+
+impl error::Error for Error {}
+
+/// Knots to meters per second
+const KNOT: f64 = 1852.0 / 3600.0;
+
+const MS_PER_DAY: i64 = 86_400_000;
+
+/// A GPS fix, as reconstructed from the sentences of a given UTC time
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Fix {
+    pub point: TrackPoint,
+    /// `GGA` fix quality (if any), e.g. 1 for GPS, 2 for DGPS, 4 for RTK
+    pub quality: Option<u8>,
+    /// Horizontal dilution of precision
+    pub hdop: Option<f64>,
+}
+
+impl From<Fix> for TrackPoint {
+    fn from(value: Fix) -> Self {
+        value.point
+    }
+}
+
+/// Fields of a `GGA` or `RMC` sentence
+#[derive(Debug)]
+struct Sentence {
+    /// Milliseconds since midnight (UTC)
+    time_of_day: i64,
+    /// Days since the Unix epoch (`RMC` only)
+    date: Option<i64>,
+    valid: bool,
+    coordinates: Option<Coordinates>,
+    quality: Option<u8>,
+    hdop: Option<f64>,
+    elevation: Option<f64>,
+    speed: Option<f64>,
+}
+
+/// Strip and validate the checksum (if any) of a sentence, returning its data fields
+fn checksum(sentence: &str) -> Result<&str, ErrorKind> {
+    let data = sentence.strip_prefix('$').ok_or(ErrorKind::Syntax)?;
+    match data.split_once('*') {
+        Some((data, checksum)) => {
+            let checksum = u8::from_str_radix(checksum, 16).map_err(|_| ErrorKind::Syntax)?;
+            if data.bytes().fold(0, |acc, b| acc ^ b) != checksum {
+                return Err(ErrorKind::Checksum);
+            }
+            Ok(data)
+        }
+        None => Ok(data),
+    }
+}
+
+/// Parse an optional (i.e. possibly empty) field value
+fn optional<T: str::FromStr>(field: &str) -> Result<Option<T>, ErrorKind> {
+    if field.is_empty() {
+        Ok(None)
+    } else {
+        field.parse::<T>().map(Some).map_err(|_| ErrorKind::Value)
+    }
+}
+
+/// Parse a `hhmmss[.sss]` time of day, as milliseconds
+fn time_of_day(field: &str) -> Result<i64, ErrorKind> {
+    let (hms, fraction) = field.split_at(field.find('.').unwrap_or(field.len()));
+    if hms.len() != 6 || !hms.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(ErrorKind::Value);
+    }
+    let hms = hms.parse::<i64>().map_err(|_| ErrorKind::Value)?;
+    let (h, m, s) = (hms / 10000, hms / 100 % 100, hms % 100);
+    if h > 23 || m > 59 || s > 60 {
+        return Err(ErrorKind::Value);
+    }
+    let millis = match fraction {
+        "" | "." => 0,
+        _ => libm::round(fraction.parse::<f64>().map_err(|_| ErrorKind::Value)? * 1000.0) as i64,
+    };
+    Ok(((h * 60 + m) * 60 + s) * 1000 + millis)
+}
+
+/// Parse a `ddmmyy` date, as days since the Unix epoch
+fn date(field: &str) -> Result<i64, ErrorKind> {
+    if field.len() != 6 || !field.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(ErrorKind::Value);
+    }
+    let dmy = field.parse::<i64>().map_err(|_| ErrorKind::Value)?;
+    let (day, month, year) = (dmy / 10000, dmy / 100 % 100, dmy % 100);
+    if !(1..=31).contains(&day) || !(1..=12).contains(&month) {
+        return Err(ErrorKind::Value);
+    }
+    let year = if year < 80 { 2000 + year } else { 1900 + year };
+    Ok(days_from_civil(year, month, day))
+}
+
+/// Parse a `(d)ddmm.mmmm` angle (at most `max` degrees) and its hemisphere
+fn angle(
+    field: &str,
+    hemisphere: &str,
+    (positive, negative): (&str, &str),
+    max: f64,
+) -> Result<f64, ErrorKind> {
+    let (integer, fraction) = field.split_once('.').unwrap_or((field, ""));
+    if integer.len() < 3
+        || !integer.bytes().all(|b| b.is_ascii_digit())
+        || !fraction.bytes().all(|b| b.is_ascii_digit())
+    {
+        return Err(ErrorKind::Value);
+    }
+    let (degrees, minutes) = field.split_at(integer.len() - 2);
+    let degrees = degrees.parse::<u16>().map_err(|_| ErrorKind::Value)? as f64;
+    let minutes = minutes.parse::<f64>().map_err(|_| ErrorKind::Value)?;
+    let angle = degrees + minutes / 60.0;
+    if minutes >= 60.0 || angle > max {
+        return Err(ErrorKind::Value);
+    }
+    if hemisphere == positive {
+        Ok(angle)
+    } else if hemisphere == negative {
+        Ok(-angle)
+    } else {
+        Err(ErrorKind::Value)
+    }
+}
+
+fn coordinates(fields: &[&str]) -> Result<Option<Coordinates>, ErrorKind> {
+    match fields {
+        ["", "", "", ""] => Ok(None),
+        [lat, ns, lon, ew] => Ok(Some(Coordinates {
+            latitude: angle(lat, ns, ("N", "S"), 90.0)?,
+            longitude: angle(lon, ew, ("E", "W"), 180.0)?,
+        })),
+        _ => Err(ErrorKind::Syntax),
+    }
+}
+
+/// Parse a sentence, `None` if it is not a supported one
+fn sentence(line: &str, min_quality: u8) -> Result<Option<Sentence>, ErrorKind> {
+    let data = checksum(line)?;
+    let fields = data.split(',').collect::<Vec<_>>();
+
+    match fields[0] {
+        "GPGGA" | "GNGGA" => {
+            if fields.len() < 10 {
+                return Err(ErrorKind::Syntax);
+            }
+            let quality = fields[6].parse::<u8>().map_err(|_| ErrorKind::Value)?;
+            let valid = quality > 0 && quality >= min_quality;
+            Ok(Some(Sentence {
+                time_of_day: time_of_day(fields[1])?,
+                date: None,
+                valid,
+                coordinates: if valid {
+                    coordinates(&fields[2..6])?
+                } else {
+                    None
+                },
+                quality: Some(quality),
+                hdop: optional(fields[8])?,
+                elevation: optional(fields[9])?,
+                speed: None,
+            }))
+        }
+        "GPRMC" | "GNRMC" => {
+            if fields.len() < 10 {
+                return Err(ErrorKind::Syntax);
+            }
+            let valid = match fields[2] {
+                "A" => true,
+                "V" => false,
+                _ => return Err(ErrorKind::Value),
+            };
+            Ok(Some(Sentence {
+                time_of_day: time_of_day(fields[1])?,
+                date: if fields[9].is_empty() {
+                    None
+                } else {
+                    Some(date(fields[9])?)
+                },
+                valid,
+                coordinates: if valid {
+                    coordinates(&fields[3..7])?
+                } else {
+                    None
+                },
+                quality: None,
+                hdop: None,
+                elevation: None,
+                speed: optional::<f64>(fields[7])?.map(|knots| knots * KNOT),
+            }))
+        }
+        _ => Ok(None),
+    }
+}
+
+/// A fix being reconstructed from the sentences of a given time of day
+#[derive(Debug)]
+struct Epoch {
+    time_of_day: i64,
+    valid: bool,
+    coordinates: Option<Coordinates>,
+    quality: Option<u8>,
+    hdop: Option<f64>,
+    elevation: Option<f64>,
+    speed: Option<f64>,
+}
+
+/// A stateful NMEA sentence parser, turning a stream of sentences into fixes.
+///
+/// As a fix may span several sentences, it is only complete (and returned) once a sentence of a
+/// later time is parsed, or the stream is [finished](Parser::finish).
+#[derive(Debug)]
+pub struct Parser {
+    min_quality: u8,
+    line: u64,
+    /// Current date (days since the Unix epoch) and time of day it was last seen at
+    date: Option<(i64, i64)>,
+    epoch: Option<Epoch>,
+}
+
+impl Default for Parser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Parser {
+    /// A parser keeping any valid fix (`GGA` fix quality of at least 1)
+    pub fn new() -> Self {
+        Self::with_min_quality(1)
+    }
+
+    /// A parser dropping fixes of a `GGA` fix quality below `min_quality` (e.g. 2 for DGPS only)
+    pub fn with_min_quality(min_quality: u8) -> Self {
+        Self {
+            min_quality,
+            line: 0,
+            date: None,
+            epoch: None,
+        }
+    }
+
+    /// Parse a line (sentence), returning the fix of the previous time of day, if complete and
+    /// valid
+    pub fn parse_line(&mut self, line: &str) -> Result<Option<Fix>, Error> {
+        self.line = self.line.saturating_add(1);
+
+        let line = line.trim();
+        if line.is_empty() {
+            return Ok(None);
+        }
+
+        let sentence = match sentence(line, self.min_quality) {
+            Ok(Some(sentence)) => sentence,
+            Ok(None) => return Ok(None),
+            Err(kind) => {
+                return Err(Error {
+                    line: self.line,
+                    sentence: line.into(),
+                    kind,
+                })
+            }
+        };
+
+        let fix = match &self.epoch {
+            Some(epoch) if epoch.time_of_day == sentence.time_of_day => None,
+            _ => self.finish_epoch(),
+        };
+
+        if let Some(date) = sentence.date {
+            self.date = Some((date, sentence.time_of_day));
+        }
+
+        let epoch = self.epoch.get_or_insert(Epoch {
+            time_of_day: sentence.time_of_day,
+            valid: true,
+            coordinates: None,
+            quality: None,
+            hdop: None,
+            elevation: None,
+            speed: None,
+        });
+        epoch.valid &= sentence.valid;
+        epoch.coordinates = epoch.coordinates.or(sentence.coordinates);
+        epoch.quality = epoch.quality.or(sentence.quality);
+        epoch.hdop = epoch.hdop.or(sentence.hdop);
+        epoch.elevation = epoch.elevation.or(sentence.elevation);
+        epoch.speed = epoch.speed.or(sentence.speed);
+
+        Ok(fix)
+    }
+
+    /// Complete the current fix, if valid
+    fn finish_epoch(&mut self) -> Option<Fix> {
+        let epoch = self.epoch.take()?;
+
+        let time = self.date.as_mut().map(|(date, time_of_day)| {
+            // midnight rollover since the date was last seen
+            if epoch.time_of_day < *time_of_day {
+                *date += 1;
+            }
+            *time_of_day = epoch.time_of_day;
+            Timestamp(*date * MS_PER_DAY + epoch.time_of_day)
+        });
+
+        if !epoch.valid {
+            return None;
+        }
+
+        Some(Fix {
+            point: TrackPoint {
+                coordinates: epoch.coordinates?,
+                time,
+                elevation: epoch.elevation,
+                speed: epoch.speed,
+            },
+            quality: epoch.quality,
+            hdop: epoch.hdop,
+        })
+    }
+
+    /// End the stream, returning the last fix, if complete and valid
+    pub fn finish(mut self) -> Option<Fix> {
+        self.finish_epoch()
+    }
+}
+
+/// Load the fixes of NMEA sentences, one per line
+pub fn load(buf: &[u8]) -> Result<Vec<Fix>, Error> {
+    let mut parser = Parser::new();
+    let mut fixes: Vec<Fix> = Vec::new();
+
+    for (i, line) in buf.split(|c| c == &b'\n').enumerate() {
+        let line = str::from_utf8(line).map_err(|_| Error {
+            line: u64::try_from(i).unwrap_or(u64::MAX).saturating_add(1),
+            sentence: String::from_utf8_lossy(line).into_owned(),
+            kind: ErrorKind::Utf8,
+        })?;
+        if let Some(fix) = parser.parse_line(line)? {
+            fixes.push(fix);
+        }
+    }
+    fixes.extend(parser.finish());
+
+    Ok(fixes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NMEA: &str = "$GPRMC,094107.00,A,5417.76028,N,00435.32664,W,8.26,42.1,120524,,,A*47
+$GPGGA,094107.00,5417.76028,N,00435.32664,W,1,09,0.92,12.5,M,51.2,M,,*7C
+$GPGSA,A,3,04,05,,09,12,,,24,,,,,2.5,1.3,2.1*39
+$GPRMC,094108.00,A,5417.76039,N,00435.32656,W,8.31,42.3,120524,,,A*4D
+$GPGGA,094108.00,5417.76039,N,00435.32656,W,1,09,0.92,12.7,M,51.2,M,,*70
+$GPRMC,094109.00,V,,,,,,,120524,,,N*78
+$GPGGA,094109.00,,,,,0,00,99.99,,,,,,*63
+$GNGGA,094110.00,5417.76054,N,00435.32591,W,2,12,0.61,13.1,M,51.2,M,,*66
+";
+
+    #[test]
+    fn load_fixes() {
+        let fixes = load(NMEA.as_bytes()).unwrap();
+        assert_eq!(fixes.len(), 3);
+
+        approx::assert_abs_diff_eq!(
+            fixes[0].point.coordinates.latitude,
+            54.296004667,
+            epsilon = 1e-9
+        );
+        approx::assert_abs_diff_eq!(
+            fixes[0].point.coordinates.longitude,
+            -4.588777333,
+            epsilon = 1e-9
+        );
+        assert_eq!(fixes[0].point.time, Some(Timestamp(1715506867000)));
+        assert_eq!(fixes[0].point.elevation, Some(12.5));
+        approx::assert_abs_diff_eq!(fixes[0].point.speed.unwrap(), 4.2493, epsilon = 1e-4);
+        assert_eq!(fixes[0].quality, Some(1));
+        assert_eq!(fixes[0].hdop, Some(0.92));
+
+        assert_eq!(fixes[1].point.time, Some(Timestamp(1715506868000)));
+
+        // GGA only, the void fix at 09:41:09 is dropped
+        assert_eq!(fixes[2].point.time, Some(Timestamp(1715506870000)));
+        assert_eq!(fixes[2].point.speed, None);
+        assert_eq!(fixes[2].quality, Some(2));
+        assert_eq!(fixes[2].hdop, Some(0.61));
+    }
+
+    #[test]
+    fn min_quality() {
+        let mut parser = Parser::with_min_quality(2);
+        let mut fixes = NMEA
+            .lines()
+            .filter_map(|line| parser.parse_line(line).unwrap())
+            .collect::<Vec<_>>();
+        fixes.extend(parser.finish());
+        assert_eq!(fixes.len(), 1);
+        assert_eq!(fixes[0].quality, Some(2));
+    }
+
+    #[test]
+    fn midnight_rollover() {
+        let fixes = load(
+            b"$GPRMC,235959.00,A,5417.76028,N,00435.32664,W,8.26,42.1,311224,,,A
+$GPGGA,000000.50,5417.76039,N,00435.32656,W,1,09,0.92,12.7,M,51.2,M,,",
+        )
+        .unwrap();
+        assert_eq!(
+            fixes.iter().map(|f| f.point.time).collect::<Vec<_>>(),
+            [
+                Some("2024-12-31T23:59:59Z".parse().unwrap()),
+                Some("2025-01-01T00:00:00.5Z".parse().unwrap())
+            ]
+        );
+    }
+
+    #[test]
+    fn untimed_before_date() {
+        let fixes = load(
+            b"$GPGGA,094107.00,5417.76028,N,00435.32664,W,1,09,0.92,12.5,M,51.2,M,,
+$GPRMC,094108.00,A,5417.76039,N,00435.32656,W,8.31,42.3,120524,,,A",
+        )
+        .unwrap();
+        assert_eq!(fixes[0].point.time, None);
+        assert_eq!(fixes[1].point.time, Some(Timestamp(1715506868000)));
+    }
+
+    macro_rules! load_err_tests {
+        ($($name:ident: $nmea:literal => ($line:expr, $kind:expr),)*) => {
+        $(
+            #[test]
+            fn $name() {
+                let err = load($nmea.as_bytes()).unwrap_err();
+                assert_eq!((err.line, err.kind), ($line, $kind));
+            }
+        )*
+        }
+    }
+
+    load_err_tests! {
+        load_err_checksum: "$GPGGA,094107.00,5417.76028,N,00435.32664,W,1,09,0.92,12.5,M,51.2,M,,*7C
+    $GPGGA,094108.00,5417.76039,N,00435.32656,W,1,09,0.92,12.7,M,51.2,M,,*71" => (2, ErrorKind::Checksum),
+        load_err_syntax: "GPGGA,094107.00" => (1, ErrorKind::Syntax),
+        load_err_truncated: "$GPGGA,094107.00,5417.76028,N" => (1, ErrorKind::Syntax),
+        load_err_hemisphere: "$GPGGA,094107.00,5417.76028,X,00435.32664,W,1,09,0.92,12.5,M,51.2,M,," => (1, ErrorKind::Value),
+        load_err_time: "$GPRMC,250000.00,A,5417.76028,N,00435.32664,W,8.26,42.1,120524,,,A" => (1, ErrorKind::Value),
+        load_err_date: "$GPRMC,094107.00,A,5417.76028,N,00435.32664,W,8.26,42.1,121324,,,A" => (1, ErrorKind::Value),
+        load_err_latitude: "$GPGGA,094107.00,9100.00000,N,00435.32664,W,1,09,0.92,12.5,M,51.2,M,," => (1, ErrorKind::Value),
+        load_err_longitude: "$GPGGA,094107.00,5417.76028,N,18000.00001,W,1,09,0.92,12.5,M,51.2,M,," => (1, ErrorKind::Value),
+        load_err_minutes: "$GPGGA,094107.00,5960.00000,N,00435.32664,W,1,09,0.92,12.5,M,51.2,M,," => (1, ErrorKind::Value),
+        load_err_minutes_overflow: "$GPGGA,094107.00,9959.0,N,00435.32664,W,1,09,0.92,12.5,M,51.2,M,," => (1, ErrorKind::Value),
+        load_err_non_ascii: "$GPGGA,094107.00,5é7.76028,N,00435.32664,W,1,09,0.92,12.5,M,51.2,M,," => (1, ErrorKind::Value),
+        load_err_sign: "$GPGGA,094107.00,5417.76028,N,-0435.32664,W,1,09,0.92,12.5,M,51.2,M,," => (1, ErrorKind::Value),
+        load_err_short: "$GPGGA,094107.00,17.76028,N,00435.32664,W,1,09,0.92,12.5,M,51.2,M,," => (1, ErrorKind::Value),
+    }
+
+    #[test]
+    fn error_sentence() {
+        let err = load(b"\n$GPGGA,094107.00,5417.76028,N").unwrap_err();
+        assert_eq!(err.sentence, "$GPGGA,094107.00,5417.76028,N");
+        assert_eq!(
+            alloc::format!("{}", err),
+            "2: ill-formed sentence '$GPGGA,094107.00,5417.76028,N'"
+        );
+    }
+}
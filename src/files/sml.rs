@@ -16,11 +16,11 @@
 
 extern crate alloc;
 
-use crate::Coordinates;
-use alloc::vec::Vec;
-use serde::Deserialize;
+use crate::{Coordinates, Deviation, Progress, Slm};
+use alloc::{string::String, vec::Vec};
+use serde::{ser::SerializeStruct, Deserialize, Serialize, Serializer};
 
-#[derive(Deserialize, Clone, Debug)]
+#[derive(Deserialize, Serialize, Clone, Debug)]
 pub struct SMLPoint {
     #[serde(rename = "Latitude")]
     pub latitude: f64,
@@ -46,7 +46,31 @@ pub struct SMLTargetPoint {
     pub longitude: f64,
 }
 
-#[derive(Deserialize, Clone, Debug)]
+impl Serialize for SMLTargetPoint {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        // same layout as a track point, with no order nor projection
+        let mut point = serializer.serialize_struct("SMLTargetPoint", 7)?;
+        point.serialize_field("Latitude", &self.latitude)?;
+        point.serialize_field("Longitude", &self.longitude)?;
+        point.serialize_field("Order", &0)?;
+        point.serialize_field("CtrlPtLat", &None::<f64>)?;
+        point.serialize_field("CtrlPtLng", &None::<f64>)?;
+        point.serialize_field("DistToLine", &None::<f64>)?;
+        point.serialize_field("CtrlPtDistToStart", &None::<f64>)?;
+        point.end()
+    }
+}
+
+impl From<Coordinates> for SMLTargetPoint {
+    fn from(value: Coordinates) -> Self {
+        Self {
+            latitude: value.latitude,
+            longitude: value.longitude,
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
 pub struct SMLAttempt {
     #[serde(rename = "Points")]
     pub points: Vec<SMLPoint>,
@@ -64,6 +88,58 @@ struct SMLDoc {
     attempt: SMLAttempt,
 }
 
+#[derive(Serialize)]
+struct SMLDocRef<'a> {
+    #[serde(rename = "Attempt")]
+    attempt: &'a SMLAttempt,
+}
+
+impl From<&Slm> for SMLAttempt {
+    fn from(slm: &Slm) -> Self {
+        let points = slm
+            .track
+            .iter()
+            .enumerate()
+            .map(|(i, point)| {
+                let (control_point, distance_to_line, control_point_distance_to_start) =
+                    match &point.progress {
+                        Progress::Standby => (slm.route_start, 0.0, 0.0),
+                        Progress::EnRoute {
+                            on_route,
+                            made_good,
+                            deviation,
+                        } => (
+                            *on_route,
+                            match deviation {
+                                Some(Deviation::Left(deviation)) => *deviation,
+                                Some(Deviation::Right(deviation)) => *deviation,
+                                None => 0.0,
+                            },
+                            *made_good,
+                        ),
+                        Progress::Arrived => (slm.route_end, 0.0, slm.route_length),
+                    };
+                SMLPoint {
+                    latitude: point.coordinates.latitude,
+                    longitude: point.coordinates.longitude,
+                    order: i + 1,
+                    control_point_latitude: control_point.latitude,
+                    control_point_longitude: control_point.longitude,
+                    distance_to_line,
+                    control_point_distance_to_start,
+                }
+            })
+            .collect();
+
+        Self {
+            points,
+            target_line_start: Some(slm.route_start.into()),
+            target_line_end: Some(slm.route_end.into()),
+            target_line_length: slm.route_length,
+        }
+    }
+}
+
 impl SMLAttempt {
     pub fn route(&self) -> (Coordinates, Coordinates) {
         if let Some(ref start) = self.target_line_start {
@@ -106,3 +182,113 @@ impl SMLAttempt {
 pub fn load(buf: &[u8]) -> Result<SMLAttempt, serde_json::Error> {
     Ok(serde_json::from_reader::<_, SMLDoc>(buf)?.attempt)
 }
+
+/// Dump an analyzed straight line mission, as scoremyline would (i.e. unsigned deviations, and
+/// the route start or end as the projection of the points off route).
+pub fn dump(slm: &Slm) -> String {
+    let attempt = SMLAttempt::from(slm);
+    serde_json::to_string_pretty(&SMLDocRef { attempt: &attempt }).expect("serialize to string")
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use approx::assert_abs_diff_eq;
+    use std::{fs, path};
+
+    fn round_trip_test(name: &str) {
+        let path = path::Path::new("fixtures").join(name).with_extension("sml");
+        let buf = fs::read(path).expect("read SML file");
+        let them = load(&buf).expect("parse SML file");
+        let (start, end) = them.route();
+
+        let us = load(dump(&crate::analyze(start, end, them.track())).as_bytes())
+            .expect("parse dumped SML");
+
+        assert_eq!(us.route(), (start, end));
+        assert_abs_diff_eq!(
+            us.target_line_length,
+            them.target_line_length,
+            epsilon = 1e-2
+        );
+        assert_eq!(us.points.len(), them.points.len());
+        for (us, them) in core::iter::zip(&us.points, &them.points) {
+            assert_eq!(us.latitude, them.latitude);
+            assert_eq!(us.longitude, them.longitude);
+            assert_abs_diff_eq!(
+                us.control_point_latitude,
+                them.control_point_latitude,
+                epsilon = 1e-6
+            );
+            assert_abs_diff_eq!(
+                us.control_point_longitude,
+                them.control_point_longitude,
+                epsilon = 1e-6
+            );
+            assert_abs_diff_eq!(us.distance_to_line, them.distance_to_line, epsilon = 1e-2);
+            assert_abs_diff_eq!(
+                us.control_point_distance_to_start,
+                them.control_point_distance_to_start,
+                epsilon = 1e-2
+            );
+        }
+    }
+
+    macro_rules! round_trip_tests {
+        ($($f:ident: $n:expr,)*) => {
+        $(
+            #[test]
+            fn $f() {
+                round_trip_test($n)
+            }
+        )*
+        }
+    }
+    round_trip_tests! {
+        round_trip_archie_iom: "archie-iom",
+        round_trip_archie_scotland: "archie-scotland",
+        round_trip_archie_wales_run: "archie-wales-run",
+        round_trip_archie_wales_walk: "archie-wales-walk",
+        round_trip_geowizard_iom: "geowizard-iom",
+        round_trip_geowizard_norway: "geowizard-norway",
+        round_trip_geowizard_scotland: "geowizard-scotland",
+        round_trip_geowizard_wales1a: "geowizard-wales1a",
+        round_trip_geowizard_wales1b: "geowizard-wales1b",
+        round_trip_geowizard_wales2: "geowizard-wales2",
+        round_trip_geowizard_wales3: "geowizard-wales3",
+        round_trip_geowizard_wales4: "geowizard-wales4",
+        round_trip_hiiumaa: "hiiumaa",
+        round_trip_muhu: "muhu",
+        round_trip_new_forest: "new-forest",
+    }
+
+    #[test]
+    fn dump_target_line() {
+        let slm = crate::analyze(
+            Coordinates {
+                latitude: 45.0,
+                longitude: 7.0,
+            },
+            Coordinates {
+                latitude: 45.01,
+                longitude: 7.0,
+            },
+            [] as [Coordinates; 0],
+        );
+        let doc: serde_json::Value = serde_json::from_str(&dump(&slm)).unwrap();
+        assert_eq!(
+            doc["Attempt"]["TLStart"],
+            serde_json::json!({
+                "Latitude": 45.0,
+                "Longitude": 7.0,
+                "Order": 0,
+                "CtrlPtLat": null,
+                "CtrlPtLng": null,
+                "DistToLine": null,
+                "CtrlPtDistToStart": null,
+            })
+        );
+    }
+}
@@ -0,0 +1,311 @@
+// Copyright 2024 Barbagus
+//
+// This file is part of slmlib.
+//
+// slmlib is free software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// slmlib is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without even
+// the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU General
+// Public License for more details.
+// You should have received a copy of the GNU General Public License along with slmlib. If not, see
+// <https://www.gnu.org/licenses/>.
+
+//! A straight line mission track as a
+//! [TCX](https://www8.garmin.com/xmlschemas/TrainingCenterDatabasev2.xsd) (Training Center XML)
+//! document. Specifically:
+//!  - Only the first activity is read, its laps making one continuous track.
+//!  - Trackpoints without a position (e.g. heart rate only) are ignored.
+//!  - Time, altitude and (extension) speed are read when present.
+extern crate alloc;
+
+use crate::{Coordinates, Timestamp, TrackPoint};
+use alloc::vec::Vec;
+use core::{error, fmt, str};
+use xmlparser::{ElementEnd, TextPos, Token, Tokenizer};
+
+#[derive(Debug, Clone)]
+pub enum Error {
+    InvalidAltitude(TextPos),
+    InvalidCoordinate(TextPos),
+    InvalidSpeed(TextPos),
+    InvalidTime(TextPos),
+    MissingCoordinate(TextPos),
+    Utf8(str::Utf8Error),
+    XmlForm(TextPos),
+    XmlStack(TextPos),
+    XmlStream(xmlparser::Error),
+}
+
+impl error::Error for Error {}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::InvalidAltitude(text_pos) => {
+                write!(f, "invalid altitude at {}", text_pos)
+            }
+            Error::InvalidCoordinate(text_pos) => {
+                write!(f, "invalid coordinate at {}", text_pos)
+            }
+            Error::InvalidSpeed(text_pos) => {
+                write!(f, "invalid speed at {}", text_pos)
+            }
+            Error::InvalidTime(text_pos) => {
+                write!(f, "invalid time at {}", text_pos)
+            }
+            Error::MissingCoordinate(text_pos) => {
+                write!(f, "missing coordinate at {}", text_pos)
+            }
+            Error::Utf8(utf8_error) => utf8_error.fmt(f),
+            Error::XmlForm(text_pos) => {
+                write!(f, "malformed xml at {}", text_pos)
+            }
+            Error::XmlStack(text_pos) => {
+                write!(f, "xml stack at {} (you found a bug !)", text_pos)
+            }
+            Error::XmlStream(error) => error.fmt(f),
+        }
+    }
+}
+
+impl From<str::Utf8Error> for Error {
+    fn from(value: str::Utf8Error) -> Self {
+        Error::Utf8(value)
+    }
+}
+impl From<xmlparser::Error> for Error {
+    fn from(value: xmlparser::Error) -> Self {
+        Self::XmlStream(value)
+    }
+}
+
+macro_rules! stack_error {
+    ($tokenizer: expr) => {
+        Error::XmlStack($tokenizer.stream().gen_text_pos())
+    };
+}
+
+macro_rules! form_error {
+    ($tokenizer: expr) => {
+        Err(Error::XmlForm($tokenizer.stream().gen_text_pos()))
+    };
+}
+
+macro_rules! parse_value {
+    ($tokenizer: expr, $text:expr, $error: path) => {
+        $text
+            .trim()
+            .parse::<f64>()
+            .map_err(|_| $error($tokenizer.stream().gen_text_pos()))?
+    };
+}
+
+/// Load the points of the first activity (all laps), along with their time, altitude and speed
+pub fn load(buf: &[u8]) -> Result<Vec<TrackPoint>, Error> {
+    let buf = str::from_utf8(buf)?;
+
+    let mut track: Vec<TrackPoint> = Vec::new();
+    let mut stack: Vec<&str> = Vec::with_capacity(10);
+
+    let mut lat: Option<f64> = None;
+    let mut lon: Option<f64> = None;
+    let mut time: Option<Timestamp> = None;
+    let mut alt: Option<f64> = None;
+    let mut speed: Option<f64> = None;
+
+    let mut tokenizer = Tokenizer::from(buf);
+    while let Some(token) = tokenizer.next() {
+        let token = token?;
+
+        match token {
+            Token::ElementStart { local, .. } => {
+                stack.push(local.as_str());
+            }
+            Token::Text { text } => match stack.as_slice() {
+                [.., "Trackpoint", "Position", "LatitudeDegrees"] => {
+                    lat.replace(parse_value!(tokenizer, text, Error::InvalidCoordinate));
+                }
+                [.., "Trackpoint", "Position", "LongitudeDegrees"] => {
+                    lon.replace(parse_value!(tokenizer, text, Error::InvalidCoordinate));
+                }
+                [.., "Trackpoint", "AltitudeMeters"] => {
+                    alt.replace(parse_value!(tokenizer, text, Error::InvalidAltitude));
+                }
+                [.., "Trackpoint", "Extensions", "TPX", "Speed"] => {
+                    speed.replace(parse_value!(tokenizer, text, Error::InvalidSpeed));
+                }
+                [.., "Trackpoint", "Time"] => {
+                    time.replace(
+                        text.trim()
+                            .parse::<Timestamp>()
+                            .map_err(|_| Error::InvalidTime(tokenizer.stream().gen_text_pos()))?,
+                    );
+                }
+                _ => continue,
+            },
+            Token::ElementEnd { end, .. } => {
+                let element = match end {
+                    ElementEnd::Open => continue,
+                    ElementEnd::Close(_, local) => {
+                        let element = stack.pop().ok_or_else(|| stack_error!(tokenizer))?;
+                        if local != element {
+                            return form_error!(tokenizer);
+                        }
+                        element
+                    }
+                    ElementEnd::Empty => stack.pop().ok_or_else(|| stack_error!(tokenizer))?,
+                };
+
+                match element {
+                    "Activity" => break,
+                    "Trackpoint" => {
+                        let (time, elevation, speed) = (time.take(), alt.take(), speed.take());
+                        let coordinates = match (lat.take(), lon.take()) {
+                            (Some(latitude), Some(longitude)) => Coordinates {
+                                latitude,
+                                longitude,
+                            },
+                            (None, None) => continue,
+                            _ => {
+                                return Err(Error::MissingCoordinate(
+                                    tokenizer.stream().gen_text_pos(),
+                                ))
+                            }
+                        };
+                        track.push(TrackPoint {
+                            coordinates,
+                            time,
+                            elevation,
+                            speed,
+                        });
+                    }
+                    _ => continue,
+                }
+            }
+            _ => continue,
+        }
+    }
+    Ok(track)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    macro_rules! tcx_load_ok_tests {
+        ($($name:ident: $tcx:literal,)*) => {
+        $(
+            #[test]
+            fn $name() {
+                let points = load($tcx.as_bytes()).unwrap();
+                let check = alloc::vec![
+                    Coordinates{latitude: 47.6655080, longitude: 8.5671500},
+                    Coordinates{latitude: 47.6655040, longitude: 8.5671580},
+                    Coordinates{latitude: 47.6655010, longitude: 8.5671610},
+                ];
+
+                assert_eq!(points.iter().map(|p| p.coordinates).collect::<Vec<_>>(), check);
+            }
+        )*
+        }
+    }
+    tcx_load_ok_tests! {
+        happy_case: r#"<?xml version="1.0" encoding="UTF-8"?>
+<TrainingCenterDatabase xmlns="http://www.garmin.com/xmlschemas/TrainingCenterDatabase/v2">
+ <Activities>
+  <Activity Sport="Other">
+   <Id>2024-05-12T09:41:07Z</Id>
+   <Lap StartTime="2024-05-12T09:41:07Z">
+    <Track>
+     <Trackpoint><Position><LatitudeDegrees>47.6655080</LatitudeDegrees><LongitudeDegrees>8.5671500</LongitudeDegrees></Position></Trackpoint>
+     <Trackpoint><Position><LatitudeDegrees>47.6655040</LatitudeDegrees><LongitudeDegrees>8.5671580</LongitudeDegrees></Position></Trackpoint>
+     <Trackpoint><Position><LatitudeDegrees>47.6655010</LatitudeDegrees><LongitudeDegrees>8.5671610</LongitudeDegrees></Position></Trackpoint>
+    </Track>
+   </Lap>
+  </Activity>
+ </Activities>
+</TrainingCenterDatabase>
+"#,
+        multiple_laps: r#"<?xml version="1.0" encoding="UTF-8"?>
+<TrainingCenterDatabase>
+ <Activities>
+  <Activity Sport="Other">
+   <Lap>
+    <Track>
+     <Trackpoint><Position><LatitudeDegrees>47.6655080</LatitudeDegrees><LongitudeDegrees>8.5671500</LongitudeDegrees></Position></Trackpoint>
+    </Track>
+   </Lap>
+   <Lap>
+    <Track>
+     <Trackpoint><Position><LatitudeDegrees>47.6655040</LatitudeDegrees><LongitudeDegrees>8.5671580</LongitudeDegrees></Position></Trackpoint>
+    </Track>
+    <Track>
+     <Trackpoint><Position><LatitudeDegrees>47.6655010</LatitudeDegrees><LongitudeDegrees>8.5671610</LongitudeDegrees></Position></Trackpoint>
+    </Track>
+   </Lap>
+  </Activity>
+  <Activity Sport="Other">
+   <Lap>
+    <Track>
+     <Trackpoint><Position><LatitudeDegrees>-47.6655080</LatitudeDegrees><LongitudeDegrees>-8.5671500</LongitudeDegrees></Position></Trackpoint>
+    </Track>
+   </Lap>
+  </Activity>
+ </Activities>
+</TrainingCenterDatabase>
+"#,
+        no_position: r#"<?xml version="1.0" encoding="UTF-8"?>
+<TrainingCenterDatabase>
+ <Activities>
+  <Activity Sport="Other">
+   <Lap>
+    <Track>
+     <Trackpoint><Time>2024-05-12T09:41:06Z</Time><HeartRateBpm><Value>92</Value></HeartRateBpm></Trackpoint>
+     <Trackpoint><Position><LatitudeDegrees>47.6655080</LatitudeDegrees><LongitudeDegrees>8.5671500</LongitudeDegrees></Position></Trackpoint>
+     <Trackpoint><Position><LatitudeDegrees>47.6655040</LatitudeDegrees><LongitudeDegrees>8.5671580</LongitudeDegrees></Position></Trackpoint>
+     <Trackpoint><Position><LatitudeDegrees>47.6655010</LatitudeDegrees><LongitudeDegrees>8.5671610</LongitudeDegrees></Position></Trackpoint>
+    </Track>
+   </Lap>
+  </Activity>
+ </Activities>
+</TrainingCenterDatabase>
+"#,
+    }
+
+    #[test]
+    fn load_time_altitude_speed() {
+        let points = load(
+            br#"<TrainingCenterDatabase xmlns:ns3="http://www.garmin.com/xmlschemas/ActivityExtension/v2"><Activities><Activity><Lap><Track>
+<Trackpoint>
+ <Time>2024-05-12T09:41:07Z</Time>
+ <Position><LatitudeDegrees>47.6655080</LatitudeDegrees><LongitudeDegrees>8.5671500</LongitudeDegrees></Position>
+ <AltitudeMeters>402.5</AltitudeMeters>
+ <Extensions><ns3:TPX><ns3:Speed>4.25</ns3:Speed></ns3:TPX></Extensions>
+</Trackpoint>
+<Trackpoint>
+ <Position><LatitudeDegrees>47.6655040</LatitudeDegrees><LongitudeDegrees>8.5671580</LongitudeDegrees></Position>
+</Trackpoint>
+</Track></Lap></Activity></Activities></TrainingCenterDatabase>"#,
+        )
+        .unwrap();
+
+        assert_eq!(points[0].time, Some(Timestamp(1715506867000)));
+        assert_eq!(points[0].elevation, Some(402.5));
+        assert_eq!(points[0].speed, Some(4.25));
+        assert_eq!(points[1].time, None);
+        assert_eq!(points[1].elevation, None);
+        assert_eq!(points[1].speed, None);
+    }
+
+    #[test]
+    fn load_missing_coordinate() {
+        let result = load(
+            br#"<TrainingCenterDatabase><Activities><Activity><Lap><Track>
+<Trackpoint><Position><LatitudeDegrees>47.6655080</LatitudeDegrees></Position></Trackpoint>
+</Track></Lap></Activity></Activities></TrainingCenterDatabase>"#,
+        );
+        assert!(matches!(result, Err(Error::MissingCoordinate(_))));
+    }
+}
@@ -0,0 +1,1061 @@
+// Copyright 2024 Barbagus
+//
+// This file is part of slmlib.
+//
+// slmlib is free software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// slmlib is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without even
+// the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU General
+// Public License for more details.
+// You should have received a copy of the GNU General Public License along with slmlib. If not, see
+// <https://www.gnu.org/licenses/>.
+
+//! Geodesics on the WGS84 ellipsoid, after C. F. F. Karney,
+//! [Algorithms for geodesics](https://doi.org/10.1007/s00190-012-0578-z), J. Geodesy 87 (2013).
+//!
+//! This is a port of the relevant parts of [GeographicLib](https://geographiclib.sourceforge.io/)
+//! (series expanded to the 6th order), restricted to the oblate WGS84 ellipsoid. Angles are
+//! expressed in degrees, distances in meters.
+use super::{A, B, F};
+use core::f64::consts::PI;
+use libm::{atan2, cbrt, copysign, cos, fabs as abs, hypot, remainder, remquo, sin, sqrt};
+
+const F1: f64 = 1.0 - F;
+const E2: f64 = F * (2.0 - F);
+const EP2: f64 = E2 / (F1 * F1);
+const N: f64 = F / (2.0 - F);
+
+/// Series order
+const ORDER: usize = 6;
+
+/// `sqrt(f64::MIN_POSITIVE)`
+const TINY: f64 = 1.4916681462400413e-154;
+const TOL0: f64 = f64::EPSILON;
+const TOL1: f64 = 200.0 * TOL0;
+/// `sqrt(f64::EPSILON)`
+const TOL2: f64 = 1.4901161193847656e-8;
+const TOLB: f64 = TOL0 * TOL2;
+const XTHRESH: f64 = 1000.0 * TOL2;
+
+/// Newton iterations
+const MAXIT1: usize = 20;
+/// Newton and bisection iterations
+const MAXIT2: usize = MAXIT1 + f64::MANTISSA_DIGITS as usize + 10;
+
+fn sq(x: f64) -> f64 {
+    x * x
+}
+
+/// Normalize a two-vector
+fn norm(x: f64, y: f64) -> (f64, f64) {
+    let r = hypot(x, y);
+    (x / r, y / r)
+}
+
+/// Error free transformation of a sum
+fn sum(u: f64, v: f64) -> (f64, f64) {
+    let s = u + v;
+    let up = s - v;
+    let vpp = s - up;
+    let up = up - u;
+    let vpp = vpp - v;
+    (s, -(up + vpp))
+}
+
+/// Evaluate a polynomial of degree `n` (highest degree coefficient first)
+fn polyval(n: usize, p: &[f64], x: f64) -> f64 {
+    p[1..=n].iter().fold(p[0], |y, c| y * x + c)
+}
+
+/// Round an angle so that small values underflow to 0
+fn ang_round(x: f64) -> f64 {
+    const Z: f64 = 1.0 / 16.0;
+    let y = abs(x);
+    // the compiler mustn't "simplify" Z - (Z - y) to y
+    let y = if y < Z { Z - (Z - y) } else { y };
+    copysign(y, x)
+}
+
+/// Reduce an angle to (-180, 180]
+fn ang_normalize(x: f64) -> f64 {
+    let y = remainder(x, 360.0);
+    if abs(y) == 180.0 {
+        copysign(180.0, x)
+    } else {
+        y
+    }
+}
+
+/// Compute `y - x`, reduced to [-180, 180], accurately
+fn ang_diff(x: f64, y: f64) -> (f64, f64) {
+    let (d, t) = sum(remainder(-x, 360.0), remainder(y, 360.0));
+    let (d, t) = sum(remainder(d, 360.0), t);
+    if d == 0.0 || abs(d) == 180.0 {
+        // reduce to [-180, 180]
+        (copysign(d, if t == 0.0 { y - x } else { -t }), t)
+    } else {
+        (d, t)
+    }
+}
+
+/// Sine and cosine of an angle in degrees
+fn sincosd(x: f64) -> (f64, f64) {
+    let (r, q) = remquo(x, 90.0);
+    let (s, c) = (sin(r.to_radians()), cos(r.to_radians()));
+    let (s, c) = match q & 3 {
+        0 => (s, c),
+        1 => (c, -s),
+        2 => (-s, -c),
+        _ => (-c, s),
+    };
+    (if s == 0.0 { copysign(s, x) } else { s }, c + 0.0)
+}
+
+/// `atan2(y, x)` in degrees
+fn atan2d(y: f64, x: f64) -> f64 {
+    let (y, x, q) = if abs(y) > abs(x) {
+        (x, y, 2)
+    } else {
+        (y, x, 0)
+    };
+    let (x, q) = if x < 0.0 { (-x, q + 1) } else { (x, q) };
+    let ang = atan2(y, x).to_degrees();
+    match q {
+        1 => copysign(180.0, y) - ang,
+        2 => 90.0 - ang,
+        3 => -90.0 + ang,
+        _ => ang,
+    }
+}
+
+/// Evaluate a Clenshaw sum of sines (`sinp`) or cosines
+fn sin_cos_series(sinp: bool, sinx: f64, cosx: f64, c: &[f64]) -> f64 {
+    let mut k = c.len();
+    let mut n = k - usize::from(sinp);
+    let ar = 2.0 * (cosx - sinx) * (cosx + sinx);
+    let mut y1 = 0.0;
+    let mut y0 = if n & 1 != 0 {
+        k -= 1;
+        c[k]
+    } else {
+        0.0
+    };
+    n /= 2;
+    while n > 0 {
+        n -= 1;
+        k -= 1;
+        y1 = ar * y0 - y1 + c[k];
+        k -= 1;
+        y0 = ar * y1 - y0 + c[k];
+    }
+    if sinp {
+        2.0 * sinx * cosx * y0
+    } else {
+        cosx * (y0 - y1)
+    }
+}
+
+/// Solve the astroid equation `k^4 + 2 k^3 - (x^2 + y^2 - 1) k^2 - 2 y^2 k - y^2 = 0` for the
+/// positive root `k`
+fn astroid(x: f64, y: f64) -> f64 {
+    let p = sq(x);
+    let q = sq(y);
+    let r = (p + q - 1.0) / 6.0;
+    if q == 0.0 && r <= 0.0 {
+        return 0.0;
+    }
+    let s = p * q / 4.0;
+    let r2 = sq(r);
+    let r3 = r * r2;
+    let disc = s * (s + 2.0 * r3);
+    let mut u = r;
+    if disc >= 0.0 {
+        let mut t3 = s + r3;
+        t3 += if t3 < 0.0 { -sqrt(disc) } else { sqrt(disc) };
+        let t = cbrt(t3);
+        u += t + if t != 0.0 { r2 / t } else { 0.0 };
+    } else {
+        let ang = atan2(sqrt(-disc), -(s + r3));
+        u += 2.0 * r * cos(ang / 3.0);
+    }
+    let v = sqrt(sq(u) + q);
+    let uv = if u < 0.0 { q / (v - u) } else { u + v };
+    let w = (uv - q) / (2.0 * v);
+    uv / (sqrt(uv + sq(w)) + w)
+}
+
+/// Fill `c[1..=ORDER]` with the terms of a series in `eps` whose coefficients are given as
+/// polynomials in `eps^2`
+fn series(coefficients: &[f64], eps: f64, c: &mut [f64; ORDER + 1]) {
+    let eps2 = sq(eps);
+    let mut d = eps;
+    let mut o = 0;
+    for (l, c) in c.iter_mut().enumerate().skip(1) {
+        let m = (ORDER - l) / 2;
+        *c = d * polyval(m, &coefficients[o..], eps2) / coefficients[o + m + 1];
+        o += m + 2;
+        d *= eps;
+    }
+}
+
+/// `A1 - 1`
+fn a1m1f(eps: f64) -> f64 {
+    const COEFF: [f64; 5] = [1.0, 4.0, 64.0, 0.0, 256.0];
+    let m = ORDER / 2;
+    let t = polyval(m, &COEFF, sq(eps)) / COEFF[m + 1];
+    (t + eps) / (1.0 - eps)
+}
+
+/// `C1[l]`
+fn c1f(eps: f64, c: &mut [f64; ORDER + 1]) {
+    const COEFF: [f64; 18] = [
+        -1.0, 6.0, -16.0, 32.0, -9.0, 64.0, -128.0, 2048.0, 9.0, -16.0, 768.0, 3.0, -5.0, 512.0,
+        -7.0, 1280.0, -7.0, 2048.0,
+    ];
+    series(&COEFF, eps, c);
+}
+
+/// `C1'[l]`
+fn c1pf(eps: f64, c: &mut [f64; ORDER + 1]) {
+    const COEFF: [f64; 18] = [
+        205.0, -432.0, 768.0, 1536.0, 4005.0, -4736.0, 3840.0, 12288.0, -225.0, 116.0, 384.0,
+        -7173.0, 2695.0, 7680.0, 3467.0, 7680.0, 38081.0, 61440.0,
+    ];
+    series(&COEFF, eps, c);
+}
+
+/// `A2 - 1`
+fn a2m1f(eps: f64) -> f64 {
+    const COEFF: [f64; 5] = [-11.0, -28.0, -192.0, 0.0, 256.0];
+    let m = ORDER / 2;
+    let t = polyval(m, &COEFF, sq(eps)) / COEFF[m + 1];
+    (t - eps) / (1.0 + eps)
+}
+
+/// `C2[l]`
+fn c2f(eps: f64, c: &mut [f64; ORDER + 1]) {
+    const COEFF: [f64; 18] = [
+        1.0, 2.0, 16.0, 32.0, 35.0, 64.0, 384.0, 2048.0, 15.0, 80.0, 768.0, 7.0, 35.0, 512.0, 63.0,
+        1280.0, 77.0, 2048.0,
+    ];
+    series(&COEFF, eps, c);
+}
+
+/// `A3`
+fn a3f(eps: f64) -> f64 {
+    const COEFF: [f64; 18] = [
+        -3.0, 128.0, -2.0, -3.0, 64.0, -1.0, -3.0, -1.0, 16.0, 3.0, -1.0, -2.0, 8.0, 1.0, -1.0,
+        2.0, 1.0, 1.0,
+    ];
+    let mut a3x = [0.0; ORDER];
+    let mut o = 0;
+    for (k, j) in (0..ORDER).rev().enumerate() {
+        let m = j.min(ORDER - j - 1);
+        a3x[k] = polyval(m, &COEFF[o..], N) / COEFF[o + m + 1];
+        o += m + 2;
+    }
+    polyval(ORDER - 1, &a3x, eps)
+}
+
+/// `C3[l]`
+fn c3f(eps: f64, c: &mut [f64; ORDER]) {
+    const COEFF: [f64; 45] = [
+        3.0, 128.0, 2.0, 5.0, 128.0, -1.0, 3.0, 3.0, 64.0, -1.0, 0.0, 1.0, 8.0, -1.0, 1.0, 4.0,
+        5.0, 256.0, 1.0, 3.0, 128.0, -3.0, -2.0, 3.0, 64.0, 1.0, -3.0, 2.0, 32.0, 7.0, 512.0,
+        -10.0, 9.0, 384.0, 5.0, -9.0, 5.0, 192.0, 7.0, 512.0, -14.0, 7.0, 512.0, 21.0, 2560.0,
+    ];
+    let mut c3x = [0.0; ORDER * (ORDER - 1) / 2];
+    let mut o = 0;
+    let mut k = 0;
+    for l in 1..ORDER {
+        for j in (l..ORDER).rev() {
+            let m = j.min(ORDER - j - 1);
+            c3x[k] = polyval(m, &COEFF[o..], N) / COEFF[o + m + 1];
+            k += 1;
+            o += m + 2;
+        }
+    }
+
+    let mut mult = 1.0;
+    let mut o = 0;
+    for (l, c) in c.iter_mut().enumerate().skip(1) {
+        let m = ORDER - l - 1;
+        mult *= eps;
+        *c = mult * polyval(m, &c3x[o..], eps);
+        o += m + 1;
+    }
+}
+
+/// Distance and reduced length (scaled by `B`) along with `m0`
+fn lengths(
+    eps: f64,
+    sig12: f64,
+    (ssig1, csig1, dn1): (f64, f64, f64),
+    (ssig2, csig2, dn2): (f64, f64, f64),
+) -> (f64, f64, f64) {
+    let mut c1a = [0.0; ORDER + 1];
+    let mut c2a = [0.0; ORDER + 1];
+
+    let a1 = a1m1f(eps);
+    c1f(eps, &mut c1a);
+    let a2 = a2m1f(eps);
+    c2f(eps, &mut c2a);
+    let m0x = a1 - a2;
+    let a1 = a1 + 1.0;
+    let a2 = a2 + 1.0;
+
+    let b1 = sin_cos_series(true, ssig2, csig2, &c1a) - sin_cos_series(true, ssig1, csig1, &c1a);
+    let s12b = a1 * (sig12 + b1);
+
+    let b2 = sin_cos_series(true, ssig2, csig2, &c2a) - sin_cos_series(true, ssig1, csig1, &c2a);
+    let j12 = m0x * sig12 + (a1 * b1 - a2 * b2);
+
+    let m12b = dn2 * (csig1 * ssig2) - dn1 * (ssig1 * csig2) - csig1 * csig2 * j12;
+
+    (s12b, m12b, m0x)
+}
+
+/// Starting point for Newton's method: `(sig12, salp1, calp1, salp2, calp2, dnm)` (a non-negative
+/// `sig12` means that the short line approximation is accurate enough)
+fn inverse_start(
+    (sbet1, cbet1): (f64, f64),
+    (sbet2, cbet2): (f64, f64),
+    lam12: f64,
+    slam12: f64,
+    clam12: f64,
+) -> (f64, f64, f64, f64, f64, f64) {
+    let etol2 = 0.1 * TOL2 / sqrt(F.max(0.001) * (1.0 - F / 2.0).min(1.0) / 2.0);
+
+    let mut sig12 = -1.0;
+    let mut salp2 = f64::NAN;
+    let mut calp2 = f64::NAN;
+    let mut dnm = f64::NAN;
+
+    let sbet12 = sbet2 * cbet1 - cbet2 * sbet1;
+    let cbet12 = cbet2 * cbet1 + sbet2 * sbet1;
+    let sbet12a = sbet2 * cbet1 + cbet2 * sbet1;
+
+    let shortline = cbet12 >= 0.0 && sbet12 < 0.5 && cbet2 * lam12 < 0.5;
+    let (mut somg12, mut comg12) = if shortline {
+        let sbetm2 = sq(sbet1 + sbet2);
+        let sbetm2 = sbetm2 / (sbetm2 + sq(cbet1 + cbet2));
+        dnm = sqrt(1.0 + EP2 * sbetm2);
+        let omg12 = lam12 / (F1 * dnm);
+        (sin(omg12), cos(omg12))
+    } else {
+        (slam12, clam12)
+    };
+
+    let mut salp1 = cbet2 * somg12;
+    let mut calp1 = if comg12 >= 0.0 {
+        sbet12 + cbet2 * sbet1 * sq(somg12) / (1.0 + comg12)
+    } else {
+        sbet12a - cbet2 * sbet1 * sq(somg12) / (1.0 - comg12)
+    };
+
+    let ssig12 = hypot(salp1, calp1);
+    let csig12 = sbet1 * sbet2 + cbet1 * cbet2 * comg12;
+
+    if shortline && ssig12 < etol2 {
+        // really short lines
+        (salp2, calp2) = norm(
+            cbet1 * somg12,
+            sbet12
+                - cbet1
+                    * sbet2
+                    * if comg12 >= 0.0 {
+                        sq(somg12) / (1.0 + comg12)
+                    } else {
+                        1.0 - comg12
+                    },
+        );
+        sig12 = atan2(ssig12, csig12);
+    } else if abs(N) > 0.1 || csig12 >= 0.0 || ssig12 >= 6.0 * abs(N) * PI * sq(cbet1) {
+        // nothing to do, zeroth order spherical approximation is OK
+    } else {
+        // scale lam12 and bet2 to x, y coordinate system where antipodal point is at origin and
+        // singular point is at y = 0, x = -1
+        let lam12x = atan2(-slam12, -clam12);
+        let k2 = sq(sbet1) * EP2;
+        let eps = k2 / (2.0 * (1.0 + sqrt(1.0 + k2)) + k2);
+        let lamscale = F * cbet1 * a3f(eps) * PI;
+        let betscale = lamscale * cbet1;
+        let x = lam12x / lamscale;
+        let y = sbet12a / betscale;
+
+        if y > -TOL1 && x > -1.0 - XTHRESH {
+            salp1 = (-x).min(1.0);
+            calp1 = -sqrt(1.0 - sq(salp1));
+        } else {
+            let k = astroid(x, y);
+            let omg12a = lamscale * (-x * k / (1.0 + k));
+            somg12 = sin(omg12a);
+            comg12 = -cos(omg12a);
+            salp1 = cbet2 * somg12;
+            calp1 = sbet12a - cbet2 * sbet1 * sq(somg12) / (1.0 - comg12);
+        }
+    }
+
+    if salp1 > 0.0 || salp1.is_nan() {
+        (salp1, calp1) = norm(salp1, calp1);
+    } else {
+        salp1 = 1.0;
+        calp1 = 0.0;
+    }
+
+    (sig12, salp1, calp1, salp2, calp2, dnm)
+}
+
+/// Intermediate results of [`lambda12`]
+struct Lambda12 {
+    lam12: f64,
+    salp2: f64,
+    calp2: f64,
+    sig12: f64,
+    ssig1: f64,
+    csig1: f64,
+    ssig2: f64,
+    csig2: f64,
+    eps: f64,
+    dlam12: f64,
+}
+
+/// Longitude difference as a function of the azimuth at point 1 (and its derivative)
+fn lambda12(
+    (sbet1, cbet1, dn1): (f64, f64, f64),
+    (sbet2, cbet2, dn2): (f64, f64, f64),
+    salp1: f64,
+    calp1: f64,
+    slam120: f64,
+    clam120: f64,
+    diffp: bool,
+) -> Lambda12 {
+    let calp1 = if sbet1 == 0.0 && calp1 == 0.0 {
+        // break degeneracy of equatorial line
+        -TINY
+    } else {
+        calp1
+    };
+
+    let salp0 = salp1 * cbet1;
+    let calp0 = hypot(calp1, salp1 * sbet1);
+
+    let somg1 = salp0 * sbet1;
+    let comg1 = calp1 * cbet1;
+    let (ssig1, csig1) = norm(sbet1, comg1);
+
+    let salp2 = if cbet2 != cbet1 { salp0 / cbet2 } else { salp1 };
+    let calp2 = if cbet2 != cbet1 || abs(sbet2) != -sbet1 {
+        sqrt(
+            sq(calp1 * cbet1)
+                + if cbet1 < -sbet1 {
+                    (cbet2 - cbet1) * (cbet1 + cbet2)
+                } else {
+                    (sbet1 - sbet2) * (sbet1 + sbet2)
+                },
+        ) / cbet2
+    } else {
+        abs(calp1)
+    };
+
+    let somg2 = salp0 * sbet2;
+    let comg2 = calp2 * cbet2;
+    let (ssig2, csig2) = norm(sbet2, comg2);
+
+    let sig12 = atan2(
+        (csig1 * ssig2 - ssig1 * csig2).max(0.0),
+        csig1 * csig2 + ssig1 * ssig2,
+    );
+    let somg12 = (comg1 * somg2 - somg1 * comg2).max(0.0);
+    let comg12 = comg1 * comg2 + somg1 * somg2;
+    let eta = atan2(
+        somg12 * clam120 - comg12 * slam120,
+        comg12 * clam120 + somg12 * slam120,
+    );
+
+    let k2 = sq(calp0) * EP2;
+    let eps = k2 / (2.0 * (1.0 + sqrt(1.0 + k2)) + k2);
+    let mut c3a = [0.0; ORDER];
+    c3f(eps, &mut c3a);
+    let b312 = sin_cos_series(true, ssig2, csig2, &c3a) - sin_cos_series(true, ssig1, csig1, &c3a);
+    let domg12 = -F * a3f(eps) * salp0 * (sig12 + b312);
+    let lam12 = eta + domg12;
+
+    let dlam12 = if !diffp {
+        f64::NAN
+    } else if calp2 == 0.0 {
+        -2.0 * F1 * dn1 / sbet1
+    } else {
+        let (_, m12b, _) = lengths(eps, sig12, (ssig1, csig1, dn1), (ssig2, csig2, dn2));
+        m12b * F1 / (calp2 * cbet2)
+    };
+
+    Lambda12 {
+        lam12,
+        salp2,
+        calp2,
+        sig12,
+        ssig1,
+        csig1,
+        ssig2,
+        csig2,
+        eps,
+        dlam12,
+    }
+}
+
+/// Reduced latitude (sine, cosine) of a latitude in degrees
+fn reduced_latitude(latitude: f64) -> (f64, f64) {
+    let (sbet, cbet) = sincosd(latitude);
+    let (sbet, cbet) = norm(sbet * F1, cbet);
+    (sbet, cbet.max(TINY))
+}
+
+///
+/// Solve the inverse geodesic problem.
+///
+/// Return a tuple comprising of:
+///   - the distance between the points
+///   - the azimuth at point 1
+///   - the (forward) azimuth at point 2
+///
+pub(crate) fn inverse(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> (f64, f64, f64) {
+    // compute longitude difference accurately, make it positive
+    let (lon12, lon12s) = ang_diff(lon1, lon2);
+    let mut lonsign: f64 = if lon12 >= 0.0 { 1.0 } else { -1.0 };
+    let lon12 = lonsign * ang_round(lon12);
+    let lon12s = ang_round((180.0 - lon12) - lonsign * lon12s);
+    let lam12 = lon12.to_radians();
+    let (slam12, clam12) = if lon12 > 90.0 {
+        let (s, c) = sincosd(lon12s);
+        (s, -c)
+    } else {
+        sincosd(lon12)
+    };
+
+    // make |lat1| >= |lat2| and lat1 <= 0
+    let mut lat1 = ang_round(lat1);
+    let mut lat2 = ang_round(lat2);
+    let swapp: f64 = if abs(lat1) < abs(lat2) { -1.0 } else { 1.0 };
+    if swapp < 0.0 {
+        lonsign *= -1.0;
+        (lat1, lat2) = (lat2, lat1);
+    }
+    let latsign: f64 = if lat1 < 0.0 { 1.0 } else { -1.0 };
+    lat1 *= latsign;
+    lat2 *= latsign;
+
+    let (sbet1, cbet1) = reduced_latitude(lat1);
+    let (mut sbet2, mut cbet2) = reduced_latitude(lat2);
+
+    if cbet1 < -sbet1 {
+        if cbet2 == cbet1 {
+            sbet2 = copysign(sbet1, sbet2);
+        }
+    } else if abs(sbet2) == -sbet1 {
+        cbet2 = cbet1;
+    }
+
+    let dn1 = sqrt(1.0 + EP2 * sq(sbet1));
+    let dn2 = sqrt(1.0 + EP2 * sq(sbet2));
+
+    let mut meridian = lat1 == -90.0 || slam12 == 0.0;
+
+    let mut salp1 = 0.0;
+    let mut calp1 = 0.0;
+    let mut salp2 = 0.0;
+    let mut calp2 = 0.0;
+    let mut s12x = 0.0;
+
+    if meridian {
+        // endpoints on a single full meridian
+        (salp1, calp1) = (slam12, clam12);
+        (salp2, calp2) = (0.0, 1.0);
+
+        let (ssig1, csig1) = (sbet1, calp1 * cbet1);
+        let (ssig2, csig2) = (sbet2, calp2 * cbet2);
+
+        let sig12 = atan2(
+            (csig1 * ssig2 - ssig1 * csig2).max(0.0),
+            csig1 * csig2 + ssig1 * ssig2,
+        );
+        let (s12b, m12b, _) = lengths(N, sig12, (ssig1, csig1, dn1), (ssig2, csig2, dn2));
+
+        if sig12 < 1.0 || m12b >= 0.0 {
+            s12x = if sig12 < 3.0 * TINY { 0.0 } else { s12b * B };
+        } else {
+            // not a shortest path
+            meridian = false;
+        }
+    }
+
+    if !meridian && sbet1 == 0.0 && lon12s >= F * 180.0 {
+        // geodesic runs along the equator
+        (salp1, calp1) = (1.0, 0.0);
+        (salp2, calp2) = (1.0, 0.0);
+        s12x = A * lam12;
+    } else if !meridian {
+        let (sig12, salp10, calp10, salp20, calp20, dnm) =
+            inverse_start((sbet1, cbet1), (sbet2, cbet2), lam12, slam12, clam12);
+        (salp1, calp1) = (salp10, calp10);
+
+        if sig12 >= 0.0 {
+            // short lines
+            (salp2, calp2) = (salp20, calp20);
+            s12x = sig12 * B * dnm;
+        } else {
+            // Newton's method, with bisection as a fallback
+            let mut tripn = false;
+            let mut tripb = false;
+            let (mut salp1a, mut calp1a) = (TINY, 1.0);
+            let (mut salp1b, mut calp1b) = (TINY, -1.0);
+
+            let mut result = None;
+            for numit in 0..MAXIT2 {
+                let l = lambda12(
+                    (sbet1, cbet1, dn1),
+                    (sbet2, cbet2, dn2),
+                    salp1,
+                    calp1,
+                    slam12,
+                    clam12,
+                    numit < MAXIT1,
+                );
+                let v = l.lam12;
+                let dv = l.dlam12;
+                result = Some(l);
+
+                if tripb || abs(v) < if tripn { 8.0 } else { 1.0 } * TOL0 || v.is_nan() {
+                    break;
+                }
+
+                // update bracketing values
+                if v > 0.0 && (numit > MAXIT1 || calp1 / salp1 > calp1b / salp1b) {
+                    (salp1b, calp1b) = (salp1, calp1);
+                } else if v < 0.0 && (numit > MAXIT1 || calp1 / salp1 < calp1a / salp1a) {
+                    (salp1a, calp1a) = (salp1, calp1);
+                }
+
+                if numit < MAXIT1 && dv > 0.0 {
+                    let dalp1 = -v / dv;
+                    let (sdalp1, cdalp1) = (sin(dalp1), cos(dalp1));
+                    let nsalp1 = salp1 * cdalp1 + calp1 * sdalp1;
+                    if nsalp1 > 0.0 && abs(dalp1) < PI {
+                        (salp1, calp1) = norm(nsalp1, calp1 * cdalp1 - salp1 * sdalp1);
+                        tripn = abs(v) <= 16.0 * TOL0;
+                        continue;
+                    }
+                }
+
+                // bisection
+                (salp1, calp1) = norm((salp1a + salp1b) / 2.0, (calp1a + calp1b) / 2.0);
+                tripn = false;
+                tripb = abs(salp1a - salp1) + (calp1a - calp1) < TOLB
+                    || abs(salp1 - salp1b) + (calp1 - calp1b) < TOLB;
+            }
+
+            if let Some(l) = result {
+                salp2 = l.salp2;
+                calp2 = l.calp2;
+                let (s12b, _, _) = lengths(
+                    l.eps,
+                    l.sig12,
+                    (l.ssig1, l.csig1, dn1),
+                    (l.ssig2, l.csig2, dn2),
+                );
+                s12x = s12b * B;
+            }
+        }
+    }
+
+    let s12 = 0.0 + s12x;
+
+    if swapp < 0.0 {
+        (salp1, salp2) = (salp2, salp1);
+        (calp1, calp2) = (calp2, calp1);
+    }
+    salp1 *= swapp * lonsign;
+    calp1 *= swapp * latsign;
+    salp2 *= swapp * lonsign;
+    calp2 *= swapp * latsign;
+
+    (s12, atan2d(salp1, calp1), atan2d(salp2, calp2))
+}
+
+///
+/// A geodesic line, from a point and an azimuth.
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Line {
+    lon1: f64,
+    a1m1: f64,
+    b11: f64,
+    a3c: f64,
+    b31: f64,
+    c1a: [f64; ORDER + 1],
+    c1pa: [f64; ORDER + 1],
+    c3a: [f64; ORDER],
+    salp0: f64,
+    calp0: f64,
+    ssig1: f64,
+    csig1: f64,
+    somg1: f64,
+    comg1: f64,
+    stau1: f64,
+    ctau1: f64,
+}
+
+impl Line {
+    pub(crate) fn new(lat1: f64, lon1: f64, azi1: f64) -> Self {
+        let azi1 = ang_normalize(azi1);
+        let (salp1, calp1) = sincosd(ang_round(azi1));
+        let (sbet1, cbet1) = reduced_latitude(ang_round(lat1));
+
+        let salp0 = salp1 * cbet1;
+        let calp0 = hypot(calp1, salp1 * sbet1);
+
+        let somg1 = salp0 * sbet1;
+        let csig1 = if sbet1 != 0.0 || calp1 != 0.0 {
+            cbet1 * calp1
+        } else {
+            1.0
+        };
+        let comg1 = csig1;
+        let (ssig1, csig1) = norm(sbet1, csig1);
+
+        let k2 = sq(calp0) * EP2;
+        let eps = k2 / (2.0 * (1.0 + sqrt(1.0 + k2)) + k2);
+
+        let a1m1 = a1m1f(eps);
+        let mut c1a = [0.0; ORDER + 1];
+        c1f(eps, &mut c1a);
+        let b11 = sin_cos_series(true, ssig1, csig1, &c1a);
+        let (s, c) = (sin(b11), cos(b11));
+        let stau1 = ssig1 * c + csig1 * s;
+        let ctau1 = csig1 * c - ssig1 * s;
+
+        let mut c1pa = [0.0; ORDER + 1];
+        c1pf(eps, &mut c1pa);
+
+        let mut c3a = [0.0; ORDER];
+        c3f(eps, &mut c3a);
+        let a3c = -F * salp0 * a3f(eps);
+        let b31 = sin_cos_series(true, ssig1, csig1, &c3a);
+
+        Self {
+            lon1,
+            a1m1,
+            b11,
+            a3c,
+            b31,
+            c1a,
+            c1pa,
+            c3a,
+            salp0,
+            calp0,
+            ssig1,
+            csig1,
+            somg1,
+            comg1,
+            stau1,
+            ctau1,
+        }
+    }
+
+    ///
+    /// The point at a given distance along the line.
+    ///
+    /// Return a tuple comprising of:
+    ///   - the latitude of the point
+    ///   - the longitude of the point
+    ///   - the (forward) azimuth at the point
+    ///
+    pub(crate) fn position(&self, s12: f64) -> (f64, f64, f64) {
+        let tau12 = s12 / (B * (1.0 + self.a1m1));
+        let (s, c) = (sin(tau12), cos(tau12));
+
+        let b12 = -sin_cos_series(
+            true,
+            self.stau1 * c + self.ctau1 * s,
+            self.ctau1 * c - self.stau1 * s,
+            &self.c1pa,
+        );
+        let sig12 = tau12 - (b12 - self.b11);
+        let (ssig12, csig12) = (sin(sig12), cos(sig12));
+
+        let ssig2 = self.ssig1 * csig12 + self.csig1 * ssig12;
+        let mut csig2 = self.csig1 * csig12 - self.ssig1 * ssig12;
+
+        let sbet2 = self.calp0 * ssig2;
+        let mut cbet2 = hypot(self.salp0, self.calp0 * csig2);
+        if cbet2 == 0.0 {
+            // break the degeneracy of a geodesic through a pole
+            cbet2 = TINY;
+            csig2 = TINY;
+        }
+        let salp2 = self.salp0;
+        let calp2 = self.calp0 * csig2;
+
+        let somg2 = self.salp0 * ssig2;
+        let comg2 = csig2;
+        let omg12 = atan2(
+            somg2 * self.comg1 - comg2 * self.somg1,
+            comg2 * self.comg1 + somg2 * self.somg1,
+        );
+        let lam12 =
+            omg12 + self.a3c * (sig12 + (sin_cos_series(true, ssig2, csig2, &self.c3a) - self.b31));
+        let lon2 = ang_normalize(ang_normalize(self.lon1) + ang_normalize(lam12.to_degrees()));
+
+        (atan2d(sbet2, F1 * cbet2), lon2, atan2d(salp2, calp2))
+    }
+}
+
+///
+/// Solve the direct geodesic problem.
+///
+/// Return a tuple comprising of:
+///   - the latitude of point 2
+///   - the longitude of point 2
+///   - the (forward) azimuth at point 2
+///
+pub(crate) fn direct(lat1: f64, lon1: f64, azi1: f64, s12: f64) -> (f64, f64, f64) {
+    Line::new(lat1, lon1, azi1).position(s12)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_abs_diff_eq;
+
+    /// Test cases published with GeographicLib (`geodtest.c`): `lat1`, `lon1`, `azi1`, `lat2`,
+    /// `lon2`, `azi2`, `s12`
+    #[allow(clippy::excessive_precision)]
+    const TESTCASES: [[f64; 7]; 20] = [
+        [
+            35.60777,
+            -139.44815,
+            111.098748429560326,
+            -11.17491,
+            -69.95921,
+            129.289270889708762,
+            8935244.5604818305,
+        ],
+        [
+            55.52454,
+            106.05087,
+            22.020059880982801,
+            77.03196,
+            197.18234,
+            109.112041110671519,
+            4105086.1713924406,
+        ],
+        [
+            -21.97856,
+            142.59065,
+            -32.44456876433189,
+            41.84138,
+            98.56635,
+            -41.84359951440466,
+            8394328.894657671,
+        ],
+        [
+            -66.99028,
+            112.2363,
+            173.73491240878403,
+            -12.70631,
+            285.90344,
+            2.512956620913668,
+            11150344.2312080241,
+        ],
+        [
+            -17.42761,
+            173.34268,
+            -159.033557661192928,
+            -15.84784,
+            5.93557,
+            -20.787484651536988,
+            16076603.1631180673,
+        ],
+        [
+            32.84994,
+            48.28919,
+            150.492927788121982,
+            -56.28556,
+            202.29132,
+            48.113449399816759,
+            16727068.9438164461,
+        ],
+        [
+            6.96833,
+            52.74123,
+            92.581585386317712,
+            -7.39675,
+            206.17291,
+            90.721692165923907,
+            17102477.2496958388,
+        ],
+        [
+            -50.56724,
+            -16.30485,
+            -105.439679907590164,
+            -33.56571,
+            -94.97412,
+            -47.348547835650331,
+            6455670.5118668696,
+        ],
+        [
+            -58.93002,
+            -8.90775,
+            140.965397902500679,
+            -8.91104,
+            133.13503,
+            19.255429433416599,
+            11756066.0219864627,
+        ],
+        [
+            -68.82867,
+            -74.28391,
+            93.774347763114881,
+            -50.63005,
+            -8.36685,
+            34.65564085411343,
+            3956936.926063544,
+        ],
+        [
+            -10.62672,
+            -32.0898,
+            -86.426713286747751,
+            5.883,
+            -134.31681,
+            -80.473780971034875,
+            11470869.3864563009,
+        ],
+        [
+            -21.76221,
+            166.90563,
+            29.319421206936428,
+            48.72884,
+            213.97627,
+            43.508671946410168,
+            9098627.3986554915,
+        ],
+        [
+            -19.79938,
+            -174.47484,
+            71.167275780171533,
+            -11.99349,
+            -154.35109,
+            65.589099775199228,
+            2319004.8601169389,
+        ],
+        [
+            -11.95887,
+            -116.94513,
+            92.712619830452549,
+            4.57352,
+            7.16501,
+            78.64960934409585,
+            13834722.5801401374,
+        ],
+        [
+            -87.85331,
+            85.66836,
+            -65.120313040242748,
+            66.48646,
+            16.09921,
+            -4.888658719272296,
+            17286615.3147144645,
+        ],
+        [
+            1.74708,
+            128.32011,
+            -101.584843631173858,
+            -11.16617,
+            11.87109,
+            -86.325793296437476,
+            12942901.1241347408,
+        ],
+        [
+            -25.72959,
+            -144.90758,
+            -153.647468693117198,
+            -57.70581,
+            -269.17879,
+            -48.343983158876487,
+            9413446.7452453107,
+        ],
+        [
+            -41.22777,
+            122.32875,
+            14.285113402275739,
+            -7.57291,
+            130.37946,
+            10.805303085187369,
+            3812686.035106021,
+        ],
+        [
+            11.01307,
+            138.25278,
+            79.43682622782374,
+            6.62726,
+            247.05981,
+            103.708090215522657,
+            11911190.819018408,
+        ],
+        [
+            -29.47124,
+            95.14681,
+            -163.779130441688382,
+            -27.46601,
+            -69.15955,
+            -15.909335945554969,
+            13487015.8381145492,
+        ],
+    ];
+
+    #[test]
+    fn inverse_testcases() {
+        for [lat1, lon1, azi1, lat2, lon2, azi2, s12] in TESTCASES {
+            let (s, a1, a2) = inverse(lat1, lon1, lat2, lon2);
+            assert_abs_diff_eq!(s, s12, epsilon = 1e-8);
+            assert_abs_diff_eq!(a1, azi1, epsilon = 1e-13);
+            assert_abs_diff_eq!(a2, azi2, epsilon = 1e-13);
+        }
+    }
+
+    #[test]
+    fn direct_testcases() {
+        for [lat1, lon1, azi1, lat2, lon2, azi2, s12] in TESTCASES {
+            let (lat, lon, azi) = direct(lat1, lon1, azi1, s12);
+            assert_abs_diff_eq!(lat, lat2, epsilon = 1e-13);
+            assert_abs_diff_eq!(ang_normalize(lon - lon2), 0.0, epsilon = 1e-13);
+            assert_abs_diff_eq!(azi, azi2, epsilon = 1e-13);
+        }
+    }
+
+    macro_rules! inverse_tests {
+        ($($name:ident: ($lat1:expr, $lon1:expr) ($lat2:expr, $lon2:expr) $s12:expr)*) => {
+        $(
+            #[test]
+            fn $name() {
+                let (s12, _, _) = inverse($lat1, $lon1, $lat2, $lon2);
+                assert_abs_diff_eq!(s12, $s12, epsilon = 0.5e-3);
+            }
+        )*
+        }
+    }
+    // after the GeodSolve test cases published with GeographicLib
+    inverse_tests! {
+        inverse_jfk_cdg: (40.6, -73.8) (49.01666667, 2.55) 5853226.256
+        inverse_short_line: (36.493349428792, 0.0) (36.49334942879201, 0.0000008) 0.072
+        inverse_antipodal_1: (88.202499451857, 0.0) (-88.202499451857, 179.98102203299285) 20003898.214
+        inverse_antipodal_2: (89.333123580033, 0.0) (-89.333123580033, 179.99295812360148) 20003926.881
+        inverse_antipodal_3: (56.320923501171, 0.0) (-56.320923501171, 179.6647476717729) 19993558.287
+        inverse_antipodal_4: (52.784459512564, 0.0) (-52.78445951256399, 179.6344074649438) 19991596.095
+        inverse_antipodal_5: (48.522876735459, 0.0) (-48.522876735458986, 179.59972045622308) 19989144.774
+        inverse_coincident: (12.3, 4.56) (12.3, 4.56) 0.0
+        inverse_antipodal: (4.0, 2.0) (-4.0, -178.0) 20003931.459
+    }
+}
@@ -13,6 +13,8 @@
 // <https://www.gnu.org/licenses/>.
 
 //! Geographic utilities library
+mod karney;
+
 use libm::{asin, atan, atan2, fabs as abs, sincos as sin_cos, sqrt, tan};
 
 const A: f64 = 6378137.0;
@@ -164,6 +166,13 @@ impl Geodesic {
             normal: end.cross(start).to_unit(),
         }
     }
+
+    /// Whether the geodesic is ill-defined (coincident or antipodal `start` and `end`)
+    pub(crate) fn is_degenerate(&self) -> bool {
+        // sine of the angle between `start` and `end` seen from the center
+        let sine = self.end.cross(self.start).len() / (self.start.len() * self.end.len());
+        sine.is_nan() || sine <= 1e-12
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -217,6 +226,145 @@ impl Point {
     }
 }
 
+///
+/// A geodesic segment (shortest path between two points), as solved on the ellipsoid.
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct EllipsoidalGeodesic {
+    line: karney::Line,
+    length: f64,
+}
+
+impl EllipsoidalGeodesic {
+    pub(crate) fn new(start: Point, end: Point) -> Self {
+        let (start_lat, start_lon) = start.coordinates();
+        let (end_lat, end_lon) = end.coordinates();
+        let (length, azimuth, _) = karney::inverse(start_lat, start_lon, end_lat, end_lon);
+        Self {
+            line: karney::Line::new(start_lat, start_lon, azimuth),
+            length,
+        }
+    }
+
+    /// The geodesic's length
+    pub(crate) fn length(&self) -> f64 {
+        self.length
+    }
+
+    /// The point at a given distance from `start` (may be negative or beyond `end`)
+    pub(crate) fn point_at(&self, distance: f64) -> Point {
+        self.position_at(distance).0
+    }
+
+    /// The point at a given distance from `start` (may be negative or beyond `end`), along with
+    /// the geodesic's azimuth at that point (in degrees, clockwise from north)
+    pub(crate) fn position_at(&self, distance: f64) -> (Point, f64) {
+        let (lat, lon, azimuth) = self.line.position(distance);
+        (Point::new(lat, lon), azimuth)
+    }
+}
+
+/// Mean earth radius used to estimate the interception steps
+const R: f64 = (2.0 * A + B) / 3.0;
+/// Accuracy (in meters) of the interception
+const INTERCEPTION_ACCURACY: f64 = 1e-6;
+/// Max. number of interception steps
+const INTERCEPTION_MAX_ITERATION: i32 = 50;
+
+impl Point {
+    /// Project the point orthogonally to a geodesic, solving the interception problem on the
+    /// ellipsoid (S. Baselga, J. C. Martínez-Llario, "Intersection and point-to-line solutions for
+    /// geodesics on the ellipsoid", Stud. Geophys. Geod. 62 (2018)).
+    ///
+    /// Return a tuple comprising of:
+    ///   - the projected point on the geodesic
+    ///   - the order of the projected point with regards to `start` and `end` of the geodesic
+    ///   - the side of the original point (from `start`, looking towards `end` of the geodesic)
+    ///   - the (signed) distance from `start` to the projected point
+    ///   - the distance from the projected point to the original point
+    ///
+    /// Return `None` if the computation does not converge.
+    pub(crate) fn intercept(
+        self,
+        geodesic: EllipsoidalGeodesic,
+    ) -> Option<(Self, Order, Side, f64, f64)> {
+        let (latitude, longitude) = self.coordinates();
+
+        let mut along = 0_f64;
+        for _ in 0..INTERCEPTION_MAX_ITERATION {
+            let (lat, lon, azimuth) = geodesic.line.position(along);
+            let (distance, bearing, _) = karney::inverse(lat, lon, latitude, longitude);
+
+            let (sin_angle, cos_angle) = sin_cos((bearing - azimuth).to_radians());
+            let (sin_sigma, cos_sigma) = sin_cos(distance / R);
+            let step = R * atan2(sin_sigma * cos_angle, cos_sigma);
+
+            along += step;
+            if abs(step) > INTERCEPTION_ACCURACY {
+                continue;
+            }
+
+            let (lat, lon, _) = geodesic.line.position(along);
+            let (across, _, _) = karney::inverse(lat, lon, latitude, longitude);
+
+            return Some((
+                Point::new(lat, lon),
+                if along <= 0.0 {
+                    Order::Before
+                } else if along >= geodesic.length {
+                    Order::After
+                } else {
+                    Order::Between
+                },
+                if across == 0.0 {
+                    Side::Center
+                } else if sin_angle > 0.0 {
+                    Side::Right
+                } else if sin_angle < 0.0 {
+                    Side::Left
+                } else {
+                    Side::Center
+                },
+                along,
+                across,
+            ));
+        }
+        None
+    }
+}
+
+///
+/// Compute the geodetic distance and azimuths between two points, using
+/// [Karney's algorithms](https://doi.org/10.1007/s00190-012-0578-z) (converges for all pairs of
+/// points, including nearly antipodal ones).
+///
+/// Return a tuple comprising of:
+///   - the distance between the points
+///   - the forward azimuth at `p1` (in degrees, clockwise from north)
+///   - the forward azimuth at `p2` (in degrees, clockwise from north), the backward azimuth being
+///     the opposite direction
+///
+pub(crate) fn karney_inverse(p1: Point, p2: Point) -> (f64, f64, f64) {
+    let (lat1, lon1) = p1.coordinates();
+    let (lat2, lon2) = p2.coordinates();
+    karney::inverse(lat1, lon1, lat2, lon2)
+}
+
+///
+/// Compute the destination from a point, an azimuth (in degrees, clockwise from north) and a
+/// distance (the geodesic *direct problem*), using
+/// [Karney's algorithms](https://doi.org/10.1007/s00190-012-0578-z).
+///
+/// Return a tuple comprising of:
+///   - the destination
+///   - the forward azimuth at the destination (in degrees, clockwise from north)
+///
+pub(crate) fn karney_direct(point: Point, azimuth: f64, distance: f64) -> (Point, f64) {
+    let (lat1, lon1) = point.coordinates();
+    let (lat2, lon2, azi2) = karney::direct(lat1, lon1, azimuth, distance);
+    (Point::new(lat2, lon2), azi2)
+}
+
 ///
 /// Compute the geodetic distance between two points, using
 /// [Vincenty's formulae](https://en.wikipedia.org/wiki/Vincenty's_formulae).
@@ -358,7 +506,7 @@ mod tests {
     }
     vincenty_inverse_tests! {
         vincenty_inverse_short: (48.154563, 17.072561) (48.154564, 17.072562) 0.13378944117648012
-        vincenty_inverse_medium: (48.154563, 17.072561) (48.158800, 17.064064) 788.41482952369672
+        vincenty_inverse_medium: (48.154563, 17.072561) (48.158800, 17.064064) 788.4148295236967
         vincenty_inverse_long: (48.148636, 17.107558) (48.208810, 16.372477) 55073.68246366003
         vincenty_inverse_equatorial: (0.0, 0.0) (0.0, 100.0) 11131949.079
         vincenty_inverse_coincident: (12.3, 4.56) (12.3, 4.56) 0.0
@@ -368,6 +516,38 @@ mod tests {
         assert_eq!(vincenty_inverse!((4.0, 2.0), (-4.0, -178.0)), None)
     }
 
+    macro_rules! karney_inverse_tests {
+        ($($name:ident: ($lat1:expr, $lon1:expr) ($lat2:expr, $lon2:expr) $exp:expr)*) => {
+        $(
+            #[test]
+            fn $name() {
+                let (distance, _, _) =
+                    karney_inverse(Point::new($lat1, $lon1), Point::new($lat2, $lon2));
+                assert_abs_diff_eq!(distance, $exp, epsilon = 1.0e-2);
+            }
+        )*
+        }
+    }
+    karney_inverse_tests! {
+        karney_inverse_short: (48.154563, 17.072561) (48.154564, 17.072562) 0.13378944117648012
+        karney_inverse_medium: (48.154563, 17.072561) (48.158800, 17.064064) 788.4148295236967
+        karney_inverse_long: (48.148636, 17.107558) (48.208810, 16.372477) 55073.68246366003
+        karney_inverse_equatorial: (0.0, 0.0) (0.0, 100.0) 11131949.079
+        karney_inverse_coincident: (12.3, 4.56) (12.3, 4.56) 0.0
+        karney_inverse_antipodal: (4.0, 2.0) (-4.0, -178.0) 20003931.459
+    }
+    #[test]
+    fn karney_inverse_azimuths() {
+        // along the equator, eastward
+        let (_, azi1, azi2) = karney_inverse(Point::new(0.0, 0.0), Point::new(0.0, 100.0));
+        assert_abs_diff_eq!(azi1, 90.0, epsilon = 1e-12);
+        assert_abs_diff_eq!(azi2, 90.0, epsilon = 1e-12);
+        // along a meridian, southward
+        let (_, azi1, azi2) = karney_inverse(Point::new(46.0, 7.0), Point::new(45.0, 7.0));
+        assert_abs_diff_eq!(azi1, 180.0, epsilon = 1e-12);
+        assert_abs_diff_eq!(azi2, 180.0, epsilon = 1e-12);
+    }
+
     macro_rules! projection {
         (($lat1:expr, $lon1:expr), ($lat2:expr, $lon2:expr), ($lat3:expr, $lon3:expr)) => {{
             let geodesic = Geodesic::new(Point::new($lat1, $lon1), Point::new($lat2, $lon2));
@@ -412,4 +592,81 @@ mod tests {
         parallel_projection_between: (45.0, 7.0) (45.0, 8.0) (45.0, 7.5) Order::Between
         parallel_projection_after: (45.0, 7.0) (45.0, 8.0) (45.0, 8.5) Order::After
     }
+
+    macro_rules! interception {
+        (($lat1:expr, $lon1:expr), ($lat2:expr, $lon2:expr), ($lat3:expr, $lon3:expr)) => {{
+            let geodesic =
+                EllipsoidalGeodesic::new(Point::new($lat1, $lon1), Point::new($lat2, $lon2));
+            let point = Point::new($lat3, $lon3);
+
+            point.intercept(geodesic).unwrap()
+        }};
+    }
+    macro_rules! interception_side_tests {
+        ($($name:ident: ($lat1:expr, $lon1:expr) ($lat2:expr, $lon2:expr) ($lat3:expr, $lon3:expr) $side:expr)*) => {
+        $(
+            #[test]
+            fn $name() {
+                let (_, _, side, _, _) = interception!(($lat1, $lon1), ($lat2, $lon2), ($lat3, $lon3));
+                assert_eq!(side, $side);
+            }
+        )*
+        }
+    }
+    interception_side_tests! {
+        meridian_interception_right: (45.0, 7.0) (46.0, 7.0) (45.5, 6.5) Side::Left
+        meridian_interception_left: (45.0, 7.0) (46.0, 7.0) (45.5, 7.5) Side::Right
+        parallel_interception_right: (45.0, 7.0) (45.0, 8.0) (45.5, 7.5) Side::Left
+        parallel_interception_left: (45.0, 7.0) (45.0, 8.0) (44.5, 7.5) Side::Right
+    }
+    macro_rules! interception_sequence_tests {
+        ($($name:ident: ($lat1:expr, $lon1:expr) ($lat2:expr, $lon2:expr) ($lat3:expr, $lon3:expr) $seq:expr)*) => {
+        $(
+            #[test]
+            fn $name() {
+                let (_, sequence, _, _, _) = interception!(($lat1, $lon1), ($lat2, $lon2), ($lat3, $lon3));
+                assert_eq!(sequence, $seq);
+            }
+        )*
+        }
+    }
+    interception_sequence_tests! {
+        meridian_interception_before: (45.0, 7.0) (46.0, 7.0) (44.5, 7.0) Order::Before
+        meridian_interception_between: (45.0, 7.0) (46.0, 7.0) (45.5, 7.0) Order::Between
+        meridian_interception_after: (45.0, 7.0) (46.0, 7.0) (46.5, 7.0) Order::After
+        parallel_interception_before: (45.0, 7.0) (45.0, 8.0) (45.0, 6.5) Order::Before
+        parallel_interception_between: (45.0, 7.0) (45.0, 8.0) (45.0, 7.5) Order::Between
+        parallel_interception_after: (45.0, 7.0) (45.0, 8.0) (45.0, 8.5) Order::After
+    }
+
+    #[test]
+    fn equatorial_interception() {
+        // meridians are perpendicular to the equator
+        let (projection, _, _, along, across) = interception!((0.0, 0.0), (0.0, 10.0), (0.5, 4.0));
+        assert!(projection.approx_eq(Point::new(0.0, 4.0), 1e-12));
+        assert_abs_diff_eq!(along, A * 4_f64.to_radians(), epsilon = 1e-6);
+        assert_abs_diff_eq!(
+            across,
+            karney::inverse(0.0, 4.0, 0.5, 4.0).0,
+            epsilon = 1e-6
+        );
+    }
+
+    #[test]
+    fn meridian_interception() {
+        // parallels are not geodesics: the foot of the perpendicular is not on the point's parallel
+        let (projection, _, _, along, across) =
+            interception!((45.0, 7.0), (48.0, 7.0), (46.0, 8.0));
+        let (lat, lon) = projection.coordinates();
+        let (distance, azimuth, _) = karney::inverse(lat, lon, 46.0, 8.0);
+        assert_abs_diff_eq!(lon, 7.0, epsilon = 1e-12);
+        assert!(lat > 46.0);
+        assert_abs_diff_eq!(azimuth, 90.0, epsilon = 1e-8);
+        assert_abs_diff_eq!(across, distance, epsilon = 1e-6);
+        assert_abs_diff_eq!(
+            along,
+            karney::inverse(45.0, 7.0, lat, lon).0,
+            epsilon = 1e-6
+        );
+    }
 }
@@ -0,0 +1,520 @@
+// Copyright 2024 Barbagus
+//
+// This file is part of slmlib.
+//
+// slmlib is free software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// slmlib is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without even
+// the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU General
+// Public License for more details.
+// You should have received a copy of the GNU General Public License along with slmlib. If not, see
+// <https://www.gnu.org/licenses/>.
+//! Geodesy on the WGS84 ellipsoid
+extern crate alloc;
+
+use crate::{geo, Coordinates, Projection};
+use alloc::vec::Vec;
+use core::{error, fmt};
+
+/// Normalize an azimuth (in `(-180, 180]`) to a bearing in `[0, 360)`
+fn bearing(azimuth: f64) -> f64 {
+    if azimuth < 0.0 {
+        let bearing = azimuth + 360.0;
+        // tiny negative azimuths round to 360
+        if bearing < 360.0 {
+            bearing
+        } else {
+            0.0
+        }
+    } else {
+        // no negative zero
+        azimuth + 0.0
+    }
+}
+
+impl Coordinates {
+    /// The distance (in meters) to other coordinates, along the geodesic
+    pub fn distance(self, other: Coordinates) -> f64 {
+        geo::karney_inverse(self.into(), other.into()).0
+    }
+
+    /// The initial bearing (in degrees, clockwise from north, in `[0, 360)`) of the geodesic to
+    /// other coordinates
+    pub fn initial_bearing(self, other: Coordinates) -> f64 {
+        bearing(geo::karney_inverse(self.into(), other.into()).1)
+    }
+
+    /// The final bearing (in degrees, clockwise from north, in `[0, 360)`) of the geodesic to
+    /// other coordinates, at these other coordinates
+    pub fn final_bearing(self, other: Coordinates) -> f64 {
+        bearing(geo::karney_inverse(self.into(), other.into()).2)
+    }
+
+    /// The point halfway along the geodesic to other coordinates
+    pub fn midpoint(self, other: Coordinates) -> Coordinates {
+        let geodesic = Geodesic::new(self, other);
+        geodesic.point_at(geodesic.length() / 2.0)
+    }
+
+    /// Compute the destination reached from these coordinates, following the geodesic with the
+    /// given initial azimuth (in degrees, clockwise from north) for the given distance (in meters,
+    /// negative to go backwards); the *direct problem* of geodesy.
+    ///
+    /// Return a tuple comprising of:
+    ///   - the destination
+    ///   - the final azimuth at the destination (in degrees, clockwise from north)
+    pub fn destination(self, azimuth: f64, distance: f64) -> (Coordinates, f64) {
+        let (destination, azimuth) = geo::karney_direct(self.into(), azimuth, distance);
+        (destination.into(), azimuth)
+    }
+}
+
+/// A geodesic (shortest path on the ellipsoid) from a start to an end, such as the target line of a
+/// straight line mission.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Geodesic {
+    start: Coordinates,
+    end: Coordinates,
+    inner: geo::EllipsoidalGeodesic,
+}
+
+impl Geodesic {
+    /// Create the geodesic from `start` to `end`
+    pub fn new(start: Coordinates, end: Coordinates) -> Self {
+        Self {
+            start,
+            end,
+            inner: geo::EllipsoidalGeodesic::new(start.into(), end.into()),
+        }
+    }
+
+    /// The geodesic's start
+    pub fn start(&self) -> Coordinates {
+        self.start
+    }
+
+    /// The geodesic's end
+    pub fn end(&self) -> Coordinates {
+        self.end
+    }
+
+    /// The geodesic's length (in meters)
+    pub fn length(&self) -> f64 {
+        self.inner.length()
+    }
+
+    /// The point on the geodesic at a given distance from its start (*made good*, in meters); it
+    /// may be negative or greater than the geodesic's length to extend the geodesic beyond its
+    /// start or end.
+    pub fn point_at(&self, made_good: f64) -> Coordinates {
+        self.inner.point_at(made_good).into()
+    }
+}
+
+/// Position of a point's projection with regards to a target line
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Position {
+    /// Before the line's start
+    Before,
+    /// Between the line's start and end
+    Between,
+    /// After the line's end
+    After,
+}
+
+/// Projection of a point onto a target line
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LineProjection {
+    /// The foot of the perpendicular from the point to the line (possibly extended)
+    pub foot: Coordinates,
+    /// The (signed) distance from the line's start to the foot (in meters)
+    pub along_track: f64,
+    /// The (signed) distance from the foot to the point (in meters), positive on the right of the
+    /// line, negative on its left
+    pub cross_track: f64,
+    /// The position of the foot with regards to the line's start and end
+    pub position: Position,
+}
+
+/// Max. number of intervals along a target line, see [`TargetLine::vertices`]
+const MAX_INTERVALS: f64 = 1e7;
+
+/// An error occurring when spacing points along a target line: the spacing is not a positive finite
+/// value, or is too small for the line's length.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpacingError(pub f64);
+
+impl error::Error for SpacingError {}
+
+impl fmt::Display for SpacingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid spacing {}", self.0)
+    }
+}
+
+/// The target line of a straight line mission
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TargetLine {
+    geodesic: Geodesic,
+    section: geo::Geodesic,
+}
+
+impl TargetLine {
+    /// Create the target line from `start` to `end`
+    pub fn new(start: Coordinates, end: Coordinates) -> Self {
+        Self {
+            geodesic: Geodesic::new(start, end),
+            section: geo::Geodesic::new(start.into(), end.into()),
+        }
+    }
+
+    /// The line's geodesic
+    pub fn geodesic(&self) -> &Geodesic {
+        &self.geodesic
+    }
+
+    /// The line's start
+    pub fn start(&self) -> Coordinates {
+        self.geodesic.start
+    }
+
+    /// The line's end
+    pub fn end(&self) -> Coordinates {
+        self.geodesic.end
+    }
+
+    /// The line's length (in meters)
+    pub fn length(&self) -> f64 {
+        self.geodesic.length()
+    }
+
+    /// The line's initial bearing (in degrees, clockwise from north, in `[0, 360)`)
+    pub fn initial_bearing(&self) -> f64 {
+        self.start().initial_bearing(self.end())
+    }
+
+    /// The line's final bearing (in degrees, clockwise from north, in `[0, 360)`)
+    pub fn final_bearing(&self) -> f64 {
+        self.start().final_bearing(self.end())
+    }
+
+    /// The line's midpoint
+    pub fn midpoint(&self) -> Coordinates {
+        self.geodesic.point_at(self.length() / 2.0)
+    }
+
+    /// The point on the line at a given distance from its start, see [`Geodesic::point_at`]
+    pub fn point_at(&self, made_good: f64) -> Coordinates {
+        self.geodesic.point_at(made_good)
+    }
+
+    /// Number of evenly spaced intervals, at most `spacing` meters long, along the line
+    fn intervals(&self, spacing: f64) -> Result<usize, SpacingError> {
+        let intervals = libm::fmax(libm::ceil(self.length() / spacing), 1.0);
+        if !(spacing.is_finite() && spacing > 0.0 && intervals <= MAX_INTERVALS) {
+            return Err(SpacingError(spacing));
+        }
+        Ok(intervals as usize)
+    }
+
+    /// Evenly spaced points along the line (start and end included), at most `spacing` meters
+    /// apart; e.g. to draw the line on a map
+    ///
+    /// Fail if `spacing` is not a positive finite value, or is too small for the line's length.
+    pub fn vertices(&self, spacing: f64) -> Result<Vec<Coordinates>, SpacingError> {
+        let count = self.intervals(spacing)?;
+        Ok((0..=count)
+            .map(|i| match i {
+                0 => self.start(),
+                i if i == count => self.end(),
+                i => self.point_at(self.length() * i as f64 / count as f64),
+            })
+            .collect())
+    }
+
+    /// A corridor around the line, at a given distance on each side: a closed ring (first and last
+    /// points are the same, counterclockwise), with points evenly spaced along the line at most
+    /// `spacing` meters apart; e.g. to draw medal corridors on a map
+    ///
+    /// Fail if `spacing` is not a positive finite value, or is too small for the line's length.
+    pub fn corridor(&self, distance: f64, spacing: f64) -> Result<Vec<Coordinates>, SpacingError> {
+        let count = self.intervals(spacing)?;
+
+        let mut left: Vec<Coordinates> = Vec::with_capacity(count + 1);
+        let mut right: Vec<Coordinates> = Vec::with_capacity(count + 1);
+        for i in 0..=count {
+            let (point, azimuth) = self
+                .geodesic
+                .inner
+                .position_at(self.length() * i as f64 / count as f64);
+            let (left_point, _) = geo::karney_direct(point, azimuth - 90.0, distance);
+            let (right_point, _) = geo::karney_direct(point, azimuth + 90.0, distance);
+            left.push(left_point.into());
+            right.push(right_point.into());
+        }
+
+        let mut ring = right;
+        ring.extend(left.into_iter().rev());
+        ring.push(ring[0]);
+        Ok(ring)
+    }
+
+    /// Project a point onto the line, with the given method (see [`Projection`])
+    ///
+    /// Return `None` if the line is degenerate (coincident or antipodal start and end) or if the
+    /// computation does not converge.
+    pub fn project(&self, point: Coordinates, method: Projection) -> Option<LineProjection> {
+        if self.section.is_degenerate() {
+            return None;
+        }
+
+        let g_point: geo::Point = point.into();
+        let (g_foot, order, side, along_track, cross_track) = match method {
+            Projection::ScoreMyLine => {
+                let (g_foot, order, side) = g_point.project_onto(self.section);
+                let (along_track, _, _) = geo::karney_inverse(self.start().into(), g_foot);
+                let (cross_track, _, _) = geo::karney_inverse(g_foot, g_point);
+                (g_foot, order, side, along_track, cross_track)
+            }
+            Projection::Precise => g_point.intercept(self.geodesic.inner)?,
+        };
+
+        Some(LineProjection {
+            foot: g_foot.into(),
+            along_track: match (method, order) {
+                (Projection::ScoreMyLine, geo::Order::Before) => -along_track,
+                _ => along_track,
+            },
+            cross_track: match side {
+                geo::Side::Left => -cross_track,
+                geo::Side::Right => cross_track,
+                geo::Side::Center => 0.0,
+            },
+            position: match order {
+                geo::Order::Before => Position::Before,
+                geo::Order::Between => Position::Between,
+                geo::Order::After => Position::After,
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_abs_diff_eq;
+
+    macro_rules! destination_tests {
+        ($($name:ident: ($lat1:expr, $lon1:expr) $azi1:expr, $s12:expr => ($lat2:expr, $lon2:expr) $azi2:expr)*) => {
+        $(
+            #[test]
+            fn $name() {
+                let start = Coordinates { latitude: $lat1, longitude: $lon1 };
+                let (destination, azimuth) = start.destination($azi1, $s12);
+                assert_abs_diff_eq!(destination.latitude, $lat2, epsilon = 1e-9);
+                assert_abs_diff_eq!(destination.longitude, $lon2, epsilon = 1e-9);
+                assert_abs_diff_eq!(azimuth, $azi2, epsilon = 1e-9);
+            }
+        )*
+        }
+    }
+    destination_tests! {
+        // expected values computed with GeographicLib
+        destination_jfk: (40.6, -73.8) 51.0, 5853226.256
+            => (50.84684696314153, 3.653345585031701) 110.93562839843645
+        destination_sydney: (-33.9, 151.2) -120.0, 12e6
+            => (-12.911571985315048, 29.099092705550916) -47.570089453354356
+        destination_equator: (0.0, 0.0) 90.0, 1e6 => (0.0, 8.983152841195214) 90.0
+        destination_backwards: (0.0, 8.983152841195214) 90.0, -1e6 => (0.0, 0.0) 90.0
+    }
+
+    macro_rules! inverse_tests {
+        ($($name:ident: ($lat1:expr, $lon1:expr) ($lat2:expr, $lon2:expr) => $s12:expr, $azi1:expr, $azi2:expr)*) => {
+        $(
+            #[test]
+            fn $name() {
+                let start = Coordinates { latitude: $lat1, longitude: $lon1 };
+                let end = Coordinates { latitude: $lat2, longitude: $lon2 };
+                assert_abs_diff_eq!(start.distance(end), $s12, epsilon = 1e-6);
+                assert_abs_diff_eq!(start.initial_bearing(end), $azi1, epsilon = 1e-9);
+                assert_abs_diff_eq!(start.final_bearing(end), $azi2, epsilon = 1e-9);
+            }
+        )*
+        }
+    }
+    inverse_tests! {
+        // expected values computed with GeographicLib
+        inverse_jfk: (40.6, -73.8) (50.84684696314153, 3.653345585031701)
+            => 5853226.256, 51.0, 110.93562839843645
+        inverse_sydney: (-33.9, 151.2) (-12.911571985315048, 29.099092705550916)
+            => 12e6, 240.0, 312.42991054664564
+        inverse_southward: (46.0, 7.0) (45.0, 7.0) => 111141.54847420914, 180.0, 180.0
+        inverse_northward: (45.0, 7.0) (46.0, 7.0) => 111141.54847420914, 0.0, 0.0
+    }
+
+    #[test]
+    fn midpoint() {
+        let start = Coordinates {
+            latitude: 45.0,
+            longitude: 7.0,
+        };
+        let end = Coordinates {
+            latitude: 47.0,
+            longitude: 7.0,
+        };
+        let midpoint = start.midpoint(end);
+        assert_abs_diff_eq!(midpoint.longitude, 7.0, epsilon = 1e-12);
+        assert_abs_diff_eq!(
+            start.distance(midpoint),
+            midpoint.distance(end),
+            epsilon = 1e-6
+        );
+        assert_eq!(TargetLine::new(start, end).midpoint(), midpoint);
+    }
+
+    macro_rules! target_line_projection_tests {
+        ($($name:ident: $method:expr, ($lat:expr, $lon:expr) => $position:expr, $sign:expr)*) => {
+        $(
+            #[test]
+            fn $name() {
+                let line = TargetLine::new(
+                    Coordinates { latitude: 45.0, longitude: 7.0 },
+                    Coordinates { latitude: 46.0, longitude: 7.0 },
+                );
+                let point = Coordinates { latitude: $lat, longitude: $lon };
+                let projection = line.project(point, $method).unwrap();
+
+                assert_eq!(projection.position, $position);
+                assert_eq!(projection.cross_track.signum(), $sign);
+                assert_abs_diff_eq!(
+                    projection.cross_track.abs(),
+                    projection.foot.distance(point),
+                    epsilon = 1e-6
+                );
+                assert_abs_diff_eq!(
+                    projection.along_track.abs(),
+                    line.start().distance(projection.foot),
+                    epsilon = 1e-6
+                );
+                assert_eq!(projection.along_track < 0.0, $position == Position::Before);
+            }
+        )*
+        }
+    }
+    target_line_projection_tests! {
+        target_line_scoremyline_before: Projection::ScoreMyLine, (44.5, 6.9) => Position::Before, -1.0
+        target_line_scoremyline_between: Projection::ScoreMyLine, (45.5, 7.1) => Position::Between, 1.0
+        target_line_scoremyline_after: Projection::ScoreMyLine, (46.5, 6.9) => Position::After, -1.0
+        target_line_precise_before: Projection::Precise, (44.5, 7.1) => Position::Before, 1.0
+        target_line_precise_between: Projection::Precise, (45.5, 6.9) => Position::Between, -1.0
+        target_line_precise_after: Projection::Precise, (46.5, 7.1) => Position::After, 1.0
+    }
+
+    #[test]
+    fn target_line_corridor() {
+        let line = TargetLine::new(
+            Coordinates {
+                latitude: 45.0,
+                longitude: 7.0,
+            },
+            Coordinates {
+                latitude: 45.1,
+                longitude: 7.2,
+            },
+        );
+
+        // a bit less than 20 km
+        let vertices = line.vertices(1000.0).unwrap();
+        assert_eq!(vertices.len(), 21);
+        assert_eq!(vertices.first(), Some(&line.start()));
+        assert_eq!(vertices.last(), Some(&line.end()));
+
+        let corridor = line.corridor(50.0, 1000.0).unwrap();
+        assert_eq!(corridor.len(), 2 * 21 + 1);
+        assert_eq!(corridor.first(), corridor.last());
+        for point in corridor {
+            let projection = line.project(point, Projection::Precise).unwrap();
+            assert_abs_diff_eq!(projection.cross_track.abs(), 50.0, epsilon = 1e-6);
+        }
+    }
+
+    macro_rules! target_line_spacing_tests {
+        ($($name:ident: $spacing:expr,)*) => {
+        $(
+            #[test]
+            fn $name() {
+                let line = TargetLine::new(
+                    Coordinates {
+                        latitude: 45.0,
+                        longitude: 7.0,
+                    },
+                    Coordinates {
+                        latitude: 45.1,
+                        longitude: 7.2,
+                    },
+                );
+                assert_eq!(line.vertices($spacing), Err(SpacingError($spacing)));
+                assert_eq!(line.corridor(50.0, $spacing), Err(SpacingError($spacing)));
+            }
+        )*
+        }
+    }
+    target_line_spacing_tests! {
+        target_line_spacing_zero: 0.0,
+        target_line_spacing_negative: -1000.0,
+        target_line_spacing_infinite: f64::INFINITY,
+        target_line_spacing_tiny: 1e-300,
+    }
+
+    #[test]
+    fn target_line_spacing_nan() {
+        let point = Coordinates {
+            latitude: 45.0,
+            longitude: 7.0,
+        };
+        let line = TargetLine::new(point, point);
+        assert!(line.vertices(f64::NAN).is_err());
+        assert!(line.corridor(50.0, f64::NAN).is_err());
+    }
+
+    #[test]
+    fn target_line_degenerate() {
+        let point = Coordinates {
+            latitude: 45.0,
+            longitude: 7.0,
+        };
+        let line = TargetLine::new(point, point);
+        assert_eq!(line.project(point, Projection::ScoreMyLine), None);
+        assert_eq!(line.project(point, Projection::Precise), None);
+    }
+
+    #[test]
+    fn geodesic_point_at() {
+        let start = Coordinates {
+            latitude: 45.0,
+            longitude: 7.0,
+        };
+        let end = Coordinates {
+            latitude: 46.0,
+            longitude: 8.0,
+        };
+        let geodesic = Geodesic::new(start, end);
+
+        let point = geodesic.point_at(0.0);
+        assert_abs_diff_eq!(point.latitude, start.latitude, epsilon = 1e-12);
+        assert_abs_diff_eq!(point.longitude, start.longitude, epsilon = 1e-12);
+
+        let point = geodesic.point_at(geodesic.length());
+        assert_abs_diff_eq!(point.latitude, end.latitude, epsilon = 1e-12);
+        assert_abs_diff_eq!(point.longitude, end.longitude, epsilon = 1e-12);
+
+        // kilometer post #10
+        let point = geodesic.point_at(1e4);
+        let (_, azimuth, _) = geo::karney_inverse(start.into(), end.into());
+        let (expected, _) = start.destination(azimuth, 1e4);
+        assert_abs_diff_eq!(point.latitude, expected.latitude, epsilon = 1e-12);
+        assert_abs_diff_eq!(point.longitude, expected.longitude, epsilon = 1e-12);
+    }
+}
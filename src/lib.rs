@@ -17,10 +17,19 @@ extern crate alloc;
 pub mod burdell;
 pub mod files;
 mod geo;
+mod geodesy;
 pub mod geowizard;
+pub mod scoring;
 mod slm;
+mod time;
+mod tracker;
 
+pub use geodesy::*;
 pub use slm::*;
+pub use time::*;
+pub use tracker::*;
+
+use alloc::vec::Vec;
 
 macro_rules! vincenty_inverse {
     ($p1: expr, $p2: expr) => {
@@ -28,6 +37,14 @@ macro_rules! vincenty_inverse {
     };
 }
 
+/// Compute the distance between two points with the given method
+fn inverse(method: Inverse, p1: geo::Point, p2: geo::Point) -> Option<f64> {
+    match method {
+        Inverse::Karney => Some(geo::karney_inverse(p1, p2).0),
+        Inverse::Vincenty => vincenty_inverse!(p1, p2),
+    }
+}
+
 impl From<Coordinates> for geo::Point {
     fn from(value: Coordinates) -> Self {
         geo::Point::new(value.latitude, value.longitude)
@@ -45,53 +62,268 @@ impl From<geo::Point> for Coordinates {
 }
 
 /// Analyze a straight line mission
+///
+/// # Panics
+/// Panics if the analysis fails, see [`try_analyze`] for a fallible version.
 pub fn analyze<I>(start: Coordinates, end: Coordinates, track: I) -> Slm
 where
-    I: IntoIterator<Item = Coordinates>,
+    I: IntoIterator,
+    I::Item: Into<TrackPoint>,
+{
+    analyze_with(AnalyzeSettings::default(), start, end, track)
+}
+
+/// Analyze a straight line mission with the given settings
+///
+/// # Panics
+/// Panics if the analysis fails, see [`try_analyze_with`] for a fallible version.
+pub fn analyze_with<I>(
+    settings: AnalyzeSettings,
+    start: Coordinates,
+    end: Coordinates,
+    track: I,
+) -> Slm
+where
+    I: IntoIterator,
+    I::Item: Into<TrackPoint>,
 {
-    let g_start: geo::Point = start.into();
-    let g_end: geo::Point = end.into();
+    match try_analyze_with(settings, start, end, track) {
+        Ok(slm) => slm,
+        Err(error) => panic!("{}", error),
+    }
+}
 
-    let g_route = geo::Geodesic::new(g_start, g_end);
-    let route_length = vincenty_inverse!(g_start, g_end).unwrap();
+/// Check coordinates are finite and within range
+fn validate(coordinates: Coordinates) -> Result<geo::Point, AnalyzeError> {
+    let Coordinates {
+        latitude,
+        longitude,
+    } = coordinates;
+
+    if latitude.is_finite()
+        && longitude.is_finite()
+        && (-90.0..=90.0).contains(&latitude)
+        && (-180.0..=180.0).contains(&longitude)
+    {
+        Ok(coordinates.into())
+    } else {
+        Err(AnalyzeError::InvalidCoordinates(coordinates))
+    }
+}
+
+/// Analyze a straight line mission, failing (instead of panicking) on invalid input
+pub fn try_analyze<I>(start: Coordinates, end: Coordinates, track: I) -> Result<Slm, AnalyzeError>
+where
+    I: IntoIterator,
+    I::Item: Into<TrackPoint>,
+{
+    try_analyze_with(AnalyzeSettings::default(), start, end, track)
+}
+
+/// Analyze a straight line mission with the given settings, failing (instead of panicking) on
+/// invalid input
+pub fn try_analyze_with<I>(
+    settings: AnalyzeSettings,
+    start: Coordinates,
+    end: Coordinates,
+    track: I,
+) -> Result<Slm, AnalyzeError>
+where
+    I: IntoIterator,
+    I::Item: Into<TrackPoint>,
+{
+    let analyzer = Analyzer::new(settings, start, end)?;
 
     let mut max_deviation = 0_f64;
 
     let track = track
         .into_iter()
-        .map(|coordinates| {
-            let g_point: geo::Point = coordinates.clone().into();
-            let (g_projection, order, side) = g_point.project_onto(g_route);
-
-            Point {
+        .map(|point| {
+            let TrackPoint {
+                coordinates,
+                time,
+                elevation,
+                ..
+            } = point.into();
+            let progress = analyzer.progress(coordinates)?;
+            if let Some(deviation) = progress.deviation() {
+                if deviation > max_deviation {
+                    max_deviation = deviation;
+                }
+            }
+            Ok(Point {
                 coordinates,
-                progress: match order {
+                time,
+                elevation,
+                progress,
+            })
+        })
+        .collect::<Result<_, AnalyzeError>>()?;
+
+    Ok(Slm {
+        route_start: start,
+        route_end: end,
+        route_length: analyzer.route_length,
+        max_deviation,
+        track,
+    })
+}
+
+fn deviation(side: geo::Side, deviation: f64) -> Option<Deviation> {
+    match side {
+        geo::Side::Left => Some(Deviation::Left(deviation)),
+        geo::Side::Right => Some(Deviation::Right(deviation)),
+        geo::Side::Center => None,
+    }
+}
+
+/// The route, as needed by the projection method
+#[derive(Debug, Clone, Copy)]
+enum Route {
+    ScoreMyLine {
+        method: Inverse,
+        start: geo::Point,
+        section: geo::Geodesic,
+    },
+    Precise {
+        geodesic: geo::EllipsoidalGeodesic,
+    },
+}
+
+/// Computes the progress of track points along a route
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Analyzer {
+    start: Coordinates,
+    route: Route,
+    pub(crate) route_length: f64,
+}
+
+impl Analyzer {
+    pub(crate) fn new(
+        settings: AnalyzeSettings,
+        start: Coordinates,
+        end: Coordinates,
+    ) -> Result<Self, AnalyzeError> {
+        let g_start = validate(start)?;
+        let g_end = validate(end)?;
+
+        // the precise method uses the same degeneracy criterion (no unique plane section)
+        let section = geo::Geodesic::new(g_start, g_end);
+        let (route, route_length) = match settings.projection {
+            Projection::ScoreMyLine => (
+                Route::ScoreMyLine {
+                    method: settings.inverse,
+                    start: g_start,
+                    section,
+                },
+                inverse(settings.inverse, g_start, g_end),
+            ),
+            Projection::Precise => {
+                let geodesic = geo::EllipsoidalGeodesic::new(g_start, g_end);
+                (Route::Precise { geodesic }, Some(geodesic.length()))
+            }
+        };
+        let route_length = match route_length {
+            Some(route_length) if route_length > 0.0 && !section.is_degenerate() => route_length,
+            _ => return Err(AnalyzeError::DegenerateRoute),
+        };
+
+        Ok(Self {
+            start,
+            route,
+            route_length,
+        })
+    }
+
+    /// Compute the progress of a track point
+    pub(crate) fn progress(&self, coordinates: Coordinates) -> Result<Progress, AnalyzeError> {
+        let g_point = validate(coordinates)?;
+
+        match self.route {
+            Route::ScoreMyLine {
+                method,
+                start,
+                section,
+            } => {
+                let (g_projection, order, side) = g_point.project_onto(section);
+
+                Ok(match order {
                     geo::Order::Before => Progress::Standby,
-                    geo::Order::Between => {
-                        let deviation = vincenty_inverse!(g_projection, g_point).unwrap();
-                        if deviation > max_deviation {
-                            max_deviation = deviation;
-                        }
-                        Progress::EnRoute {
-                            on_route: g_projection.into(),
-                            made_good: vincenty_inverse!(g_start, g_projection).unwrap(),
-                            deviation: match side {
-                                geo::Side::Left => Some(Deviation::Left(deviation)),
-                                geo::Side::Right => Some(Deviation::Right(deviation)),
-                                geo::Side::Center => None,
-                            },
-                        }
-                    }
+                    geo::Order::Between => Progress::EnRoute {
+                        on_route: g_projection.into(),
+                        made_good: inverse(method, start, g_projection)
+                            .ok_or(AnalyzeError::Distance(self.start, g_projection.into()))?,
+                        deviation: deviation(
+                            side,
+                            inverse(method, g_projection, g_point)
+                                .ok_or(AnalyzeError::Distance(g_projection.into(), coordinates))?,
+                        ),
+                    },
                     geo::Order::After => Progress::Arrived,
-                },
+                })
             }
+            Route::Precise { geodesic } => {
+                let (g_projection, order, side, made_good, cross_track) = g_point
+                    .intercept(geodesic)
+                    .ok_or(AnalyzeError::Distance(self.start, coordinates))?;
+
+                Ok(match order {
+                    geo::Order::Before => Progress::Standby,
+                    geo::Order::Between => Progress::EnRoute {
+                        on_route: g_projection.into(),
+                        made_good,
+                        deviation: deviation(side, cross_track),
+                    },
+                    geo::Order::After => Progress::Arrived,
+                })
+            }
+        }
+    }
+}
+
+/// Apply a *leniency* setting to an analyzed straight line mission: the given percentage of worst
+/// points is ignored (removed from the track) and the max deviation is updated accordingly.
+///
+/// Mimicking [scoremyline](https://scoremyline.com/), the number of ignored points is computed over
+/// the whole track (including points before the route start and after the route end) whereas only
+/// points en route are eligible to be ignored.
+pub fn apply_leniency(slm: &Slm, ignore: f64) -> Slm {
+    let count = libm::round(slm.track.len() as f64 * ignore / 100.0) as usize;
+
+    let mut worst: Vec<(usize, f64)> = slm
+        .track
+        .iter()
+        .enumerate()
+        .filter_map(|(i, point)| match &point.progress {
+            Progress::Standby => None,
+            Progress::EnRoute { deviation, .. } => match deviation {
+                Some(Deviation::Left(deviation)) => Some((i, *deviation)),
+                Some(Deviation::Right(deviation)) => Some((i, *deviation)),
+                None => Some((i, 0.0)),
+            },
+            Progress::Arrived => None,
         })
         .collect();
 
+    worst.sort_by(|(_, d1), (_, d2)| d2.total_cmp(d1));
+
+    let max_deviation = worst.get(count).map(|(_, d)| *d).unwrap_or(0.0);
+
+    let mut ignored: Vec<usize> = worst.into_iter().take(count).map(|(i, _)| i).collect();
+    ignored.sort_unstable();
+
+    let track = slm
+        .track
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| ignored.binary_search(i).is_err())
+        .map(|(_, point)| point.clone())
+        .collect();
+
     Slm {
-        route_start: start,
-        route_end: end,
-        route_length,
+        route_start: slm.route_start,
+        route_end: slm.route_end,
+        route_length: slm.route_length,
         max_deviation,
         track,
     }
@@ -116,13 +348,27 @@ mod tests {
         };
         let (start, end) = them.route();
 
-        let us = analyze(start, end, them.track());
+        for inverse in [Inverse::Karney, Inverse::Vincenty] {
+            let settings = AnalyzeSettings {
+                inverse,
+                ..Default::default()
+            };
+            let us = analyze_with(settings, start, end, them.track());
+            mission_compare(start, end, us, &them);
+        }
+    }
 
+    fn mission_compare(
+        start: Coordinates,
+        end: Coordinates,
+        us: Slm,
+        them: &files::sml::SMLAttempt,
+    ) {
         assert_abs_diff_eq!(us.route_length, them.target_line_length, epsilon = 1e-2);
 
         let mut them_max_deviation = 0_f64;
 
-        for (us_point, them_point) in iter::zip(us.track.into_iter(), them.points.into_iter()) {
+        for (us_point, them_point) in iter::zip(us.track, &them.points) {
             let Point { progress, .. } = us_point;
 
             let (us_projection, us_made_good, us_deviation) = match progress {
@@ -169,6 +415,94 @@ mod tests {
         assert_abs_diff_eq!(us.max_deviation, them_max_deviation, epsilon = 1e-2);
     }
 
+    fn leniency_test(name: &str) {
+        let base = path::Path::new("fixtures");
+
+        let sml = {
+            let path = base.join(name).with_extension("sml");
+            let buf = fs::read(path).expect("read SML file");
+            files::sml::load(&buf).expect("parse SML file")
+        };
+
+        let fix = {
+            let path = base.join(name).with_extension("json");
+            let buf = fs::read(path).expect("read FIX file");
+            files::fix::load(&buf).expect("parse FIX file")
+        };
+
+        let (start, end) = sml.route();
+        let mission = analyze(start, end, sml.track());
+
+        for score in fix.scores {
+            if let Some(ignore) = score.ignore {
+                let lenient = apply_leniency(&mission, ignore.into());
+                // published values are rounded to the decimeter
+                assert_abs_diff_eq!(lenient.max_deviation, score.max_deviation, epsilon = 5e-2);
+            }
+        }
+    }
+
+    fn precise_test(name: &str) {
+        let base = path::Path::new("fixtures");
+
+        let sml = {
+            let path = base.join(name).with_extension("sml");
+            let buf = fs::read(path).expect("read SML file");
+            files::sml::load(&buf).expect("parse SML file")
+        };
+        let (start, end) = sml.route();
+
+        let approximate = analyze(start, end, sml.track());
+        let precise = analyze_with(
+            AnalyzeSettings {
+                projection: Projection::Precise,
+                ..Default::default()
+            },
+            start,
+            end,
+            sml.track(),
+        );
+
+        assert_abs_diff_eq!(
+            precise.route_length,
+            approximate.route_length,
+            epsilon = 1e-3
+        );
+        // the plane section approximation is off by a few decimeters at most on these distances
+        assert_abs_diff_eq!(
+            precise.max_deviation,
+            approximate.max_deviation,
+            epsilon = 5e-1
+        );
+    }
+
+    macro_rules! try_analyze_err_tests {
+        ($($f:ident: ($lat1:expr, $lon1:expr) ($lat2:expr, $lon2:expr) $track:expr => $err:pat,)*) => {
+        $(
+            #[test]
+            fn $f() {
+                let start = Coordinates{latitude: $lat1, longitude: $lon1};
+                let end = Coordinates{latitude: $lat2, longitude: $lon2};
+                let track: &[Coordinates] = &$track;
+                assert!(matches!(try_analyze(start, end, track.iter().copied()), Err($err)));
+            }
+        )*
+        }
+    }
+    try_analyze_err_tests! {
+        try_analyze_coincident: (45.0, 7.0) (45.0, 7.0) [] => AnalyzeError::DegenerateRoute,
+        try_analyze_antipodal: (4.0, 2.0) (-4.0, -178.0) [] => AnalyzeError::DegenerateRoute,
+        try_analyze_latitude: (95.0, 7.0) (46.0, 7.0) [] => AnalyzeError::InvalidCoordinates(_),
+        try_analyze_longitude: (45.0, 7.0) (46.0, -187.0) [] => AnalyzeError::InvalidCoordinates(_),
+        try_analyze_nan: (45.0, 7.0) (46.0, 7.0) [
+            Coordinates{latitude: 45.5, longitude: 7.0},
+            Coordinates{latitude: f64::NAN, longitude: 7.0},
+        ] => AnalyzeError::InvalidCoordinates(_),
+        try_analyze_infinite: (45.0, 7.0) (46.0, 7.0) [
+            Coordinates{latitude: 45.5, longitude: f64::INFINITY},
+        ] => AnalyzeError::InvalidCoordinates(_),
+    }
+
     macro_rules! mission_tests {
         ($($f:ident: $n:expr,)*) => {
         $(
@@ -198,4 +532,60 @@ mod tests {
         mission_saaremaa: "saaremaa",
         mission_schaffhausen: "schaffhausen",
     }
+
+    macro_rules! leniency_tests {
+        ($($f:ident: $n:expr,)*) => {
+        $(
+            #[test]
+            fn $f() {
+                leniency_test($n)
+            }
+        )*
+        }
+    }
+    leniency_tests! {
+        leniency_archie_iom: "archie-iom",
+        leniency_archie_scotland: "archie-scotland",
+        leniency_archie_wales_run: "archie-wales-run",
+        leniency_archie_wales_walk: "archie-wales-walk",
+        leniency_geowizard_iom: "geowizard-iom",
+        leniency_geowizard_norway: "geowizard-norway",
+        leniency_geowizard_scotland: "geowizard-scotland",
+        leniency_geowizard_wales1a: "geowizard-wales1a",
+        leniency_geowizard_wales1b: "geowizard-wales1b",
+        leniency_geowizard_wales2: "geowizard-wales2",
+        leniency_geowizard_wales3: "geowizard-wales3",
+        leniency_geowizard_wales4: "geowizard-wales4",
+        leniency_hiiumaa: "hiiumaa",
+        leniency_muhu: "muhu",
+        leniency_new_forest: "new-forest",
+    }
+
+    macro_rules! precise_tests {
+        ($($f:ident: $n:expr,)*) => {
+        $(
+            #[test]
+            fn $f() {
+                precise_test($n)
+            }
+        )*
+        }
+    }
+    precise_tests! {
+        precise_archie_iom: "archie-iom",
+        precise_archie_scotland: "archie-scotland",
+        precise_archie_wales_run: "archie-wales-run",
+        precise_archie_wales_walk: "archie-wales-walk",
+        precise_geowizard_iom: "geowizard-iom",
+        precise_geowizard_norway: "geowizard-norway",
+        precise_geowizard_scotland: "geowizard-scotland",
+        precise_geowizard_wales1a: "geowizard-wales1a",
+        precise_geowizard_wales1b: "geowizard-wales1b",
+        precise_geowizard_wales2: "geowizard-wales2",
+        precise_geowizard_wales3: "geowizard-wales3",
+        precise_geowizard_wales4: "geowizard-wales4",
+        precise_hiiumaa: "hiiumaa",
+        precise_muhu: "muhu",
+        precise_new_forest: "new-forest",
+    }
 }
@@ -0,0 +1,220 @@
+// Copyright 2024 Barbagus
+//
+// This file is part of slmlib.
+//
+// slmlib is free software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// slmlib is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without even
+// the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU General
+// Public License for more details.
+// You should have received a copy of the GNU General Public License along with slmlib. If not, see
+// <https://www.gnu.org/licenses/>.
+
+//! Scoring schemes of straight line missions, behind a common [`Scorer`] trait so that any list of
+//! them can be run over an analyzed mission.
+extern crate alloc;
+
+use crate::{burdell, geowizard::Rank, Slm};
+use alloc::{string::String, vec::Vec};
+use core::{error, fmt};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// An error occurring while scoring a straight line mission.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ScoringError {
+    Burdell(burdell::ScoreError),
+}
+
+impl error::Error for ScoringError {}
+
+impl fmt::Display for ScoringError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScoringError::Burdell(error) => write!(f, "burdell score: {}", error),
+        }
+    }
+}
+
+impl From<burdell::ScoreError> for ScoringError {
+    fn from(value: burdell::ScoreError) -> Self {
+        ScoringError::Burdell(value)
+    }
+}
+
+/// The value of a score
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Value {
+    /// A numeric value, see [`Score::unit`]
+    Number(f64),
+    /// A rank (none if not ranked), and its name in the scoring scheme
+    Rank {
+        rank: Option<Rank>,
+        name: Option<String>,
+    },
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Number(value) => write!(f, "{:.1}", value),
+            Value::Rank {
+                name: Some(name), ..
+            } => f.write_str(name),
+            Value::Rank { name: None, .. } => f.write_str("-"),
+        }
+    }
+}
+
+/// The value of a score detail
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Detail {
+    Number(f64),
+    /// A named setting, e.g. a strategy
+    Text(String),
+}
+
+impl From<f64> for Detail {
+    fn from(value: f64) -> Self {
+        Detail::Number(value)
+    }
+}
+
+impl From<&str> for Detail {
+    fn from(value: &str) -> Self {
+        Detail::Text(value.into())
+    }
+}
+
+impl fmt::Display for Detail {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Detail::Number(value) => value.fmt(f),
+            Detail::Text(text) => f.write_str(text),
+        }
+    }
+}
+
+/// The result of a [`Scorer`]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Score {
+    /// Name of the scorer, e.g. `Burdell score (PRO)`
+    pub name: String,
+    pub value: Value,
+    /// Unit of the value, if any, e.g. `%`
+    pub unit: Option<String>,
+    /// Named values detailing the score, e.g. the scorer's parameters
+    pub details: Vec<(String, Detail)>,
+}
+
+impl fmt::Display for Score {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.unit {
+            Some(unit) => write!(f, "{} {}", self.value, unit),
+            None => self.value.fmt(f),
+        }
+    }
+}
+
+/// A scoring scheme
+pub trait Scorer {
+    /// Name of the scoring scheme (and its parameters)
+    fn name(&self) -> String;
+
+    /// Score an analyzed straight line mission
+    fn score(&self, slm: &Slm) -> Result<Score, ScoringError>;
+}
+
+/// Run a list of scorers over an analyzed straight line mission
+pub fn score_all(scorers: &[&dyn Scorer], slm: &Slm) -> Result<Vec<Score>, ScoringError> {
+    scorers.iter().map(|scorer| scorer.score(slm)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{burdell, geowizard, Coordinates};
+    use alloc::string::ToString;
+
+    #[test]
+    fn score_all_uniform() {
+        let slm = crate::analyze(
+            Coordinates {
+                latitude: 45.0,
+                longitude: 7.0,
+            },
+            Coordinates {
+                latitude: 45.1,
+                longitude: 7.0,
+            },
+            [(45.02, 7.0), (45.05, 7.0004), (45.08, 7.0)].map(|(latitude, longitude)| {
+                Coordinates {
+                    latitude,
+                    longitude,
+                }
+            }),
+        );
+
+        let custom = burdell::BurdellSettings::new(10.0, 180.0).unwrap();
+        let scores = score_all(&[&geowizard::MedalRank, &burdell::LVL_PRO, &custom], &slm).unwrap();
+
+        assert_eq!(scores[0].name, "Medal rank");
+        assert_eq!(
+            scores[0].value,
+            Value::Rank {
+                rank: Some(geowizard::Rank::Gold),
+                name: Some("GOLD".into())
+            }
+        );
+        assert_eq!(scores[0].to_string(), "GOLD");
+
+        assert_eq!(scores[1].name, "Burdell score (PRO)");
+        assert_eq!(
+            scores[1].value,
+            Value::Number(burdell::compute_score(burdell::LVL_PRO, &slm))
+        );
+        assert_eq!(scores[1].unit.as_deref(), Some("%"));
+
+        assert_eq!(scores[2].name, "Burdell score (10 m, 180)");
+        assert_eq!(
+            scores[2].details,
+            [
+                ("step".into(), 10.0.into()),
+                ("coefficient".into(), 180.0.into()),
+                ("segmentation".into(), "floor-indexed".into()),
+                ("gap_fill".into(), "linear-midpoint".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn score_all_error() {
+        let mut slm = crate::analyze(
+            Coordinates {
+                latitude: 45.0,
+                longitude: 7.0,
+            },
+            Coordinates {
+                latitude: 45.1,
+                longitude: 7.0,
+            },
+            [Coordinates {
+                latitude: 45.05,
+                longitude: 7.0,
+            }],
+        );
+        slm.route_length = f64::NAN;
+
+        let error = score_all(&[&geowizard::MedalRank, &burdell::LVL_PRO], &slm).unwrap_err();
+        assert!(matches!(
+            error,
+            ScoringError::Burdell(burdell::ScoreError::RouteLength(_))
+        ));
+        assert!(error.to_string().starts_with("burdell score: "));
+    }
+}
@@ -13,7 +13,9 @@
 // <https://www.gnu.org/licenses/>.
 extern crate alloc;
 
+use crate::Timestamp;
 use alloc::vec::Vec;
+use core::{error, fmt};
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
@@ -24,6 +26,30 @@ pub struct Coordinates {
     pub longitude: f64,
 }
 
+/// A track point, as recorded by a GPS device
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct TrackPoint {
+    pub coordinates: Coordinates,
+    /// Time of the recording
+    pub time: Option<Timestamp>,
+    /// Elevation (in meters)
+    pub elevation: Option<f64>,
+    /// Speed (in meters per second), as reported by the device
+    pub speed: Option<f64>,
+}
+
+impl From<Coordinates> for TrackPoint {
+    fn from(coordinates: Coordinates) -> Self {
+        TrackPoint {
+            coordinates,
+            time: None,
+            elevation: None,
+            speed: None,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Deviation {
@@ -43,10 +69,29 @@ pub enum Progress {
     Arrived,
 }
 
+impl Progress {
+    /// The (unsigned) deviation from the route, if en route
+    pub fn deviation(&self) -> Option<f64> {
+        match self {
+            Progress::Standby => None,
+            Progress::EnRoute { deviation, .. } => match deviation {
+                Some(Deviation::Left(deviation)) => Some(*deviation),
+                Some(Deviation::Right(deviation)) => Some(*deviation),
+                None => Some(0.0),
+            },
+            Progress::Arrived => None,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Point {
     pub coordinates: Coordinates,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub time: Option<Timestamp>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub elevation: Option<f64>,
     pub progress: Progress,
 }
 
@@ -59,3 +104,71 @@ pub struct Slm {
     pub track: Vec<Point>,
     pub max_deviation: f64,
 }
+
+/// Method used to project track points onto the route.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Projection {
+    /// Approximate the route as the section of the ellipsoid by the plane containing its start,
+    /// its end and the ellipsoid's center (as scoremyline.com does).
+    #[default]
+    ScoreMyLine,
+    /// Solve the geodesic interception problem on the ellipsoid, giving the true foot of the
+    /// perpendicular and cross-track distance.
+    Precise,
+}
+
+/// Method used to compute distances between points (the geodesic *inverse problem*).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Inverse {
+    /// Karney's algorithms, converging for all pairs of points.
+    #[default]
+    Karney,
+    /// Vincenty's formulae, as used by scoremyline.com (fails on nearly antipodal points).
+    Vincenty,
+}
+
+/// Settings of a straight line mission analysis.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct AnalyzeSettings {
+    pub projection: Projection,
+    /// Distances computation method, only relevant to the [`Projection::ScoreMyLine`] projection
+    /// (the precise projection always uses Karney's algorithms).
+    pub inverse: Inverse,
+}
+
+/// An error occurring while analyzing a straight line mission.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AnalyzeError {
+    /// Route start and end are coincident or antipodal.
+    DegenerateRoute,
+    /// Distance between two points could not be computed (nearly antipodal points or
+    /// non-convergent computation, see [`Inverse::Vincenty`] and [`Projection::Precise`]).
+    Distance(Coordinates, Coordinates),
+    /// Non-finite or out of range coordinates.
+    InvalidCoordinates(Coordinates),
+}
+
+impl error::Error for AnalyzeError {}
+
+impl fmt::Display for AnalyzeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AnalyzeError::DegenerateRoute => {
+                f.write_str("degenerate route (coincident or antipodal start and end)")
+            }
+            AnalyzeError::Distance(from, to) => write!(
+                f,
+                "unable to compute distance from {},{} to {},{}",
+                from.latitude, from.longitude, to.latitude, to.longitude
+            ),
+            AnalyzeError::InvalidCoordinates(coordinates) => write!(
+                f,
+                "invalid coordinates {},{}",
+                coordinates.latitude, coordinates.longitude
+            ),
+        }
+    }
+}
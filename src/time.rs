@@ -0,0 +1,386 @@
+// Copyright 2024 Barbagus
+//
+// This file is part of slmlib.
+//
+// slmlib is free software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// slmlib is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without even
+// the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU General
+// Public License for more details.
+// You should have received a copy of the GNU General Public License along with slmlib. If not, see
+// <https://www.gnu.org/licenses/>.
+//! Timestamps and timing statistics
+extern crate alloc;
+
+use crate::{Point, Progress, Slm};
+use alloc::vec::Vec;
+use core::{error, fmt, str};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+///
+/// A UTC timestamp, as a number of milliseconds since the Unix epoch (1970-01-01T00:00:00Z).
+///
+/// Parsed from and displayed as [RFC 3339](https://www.rfc-editor.org/rfc/rfc3339) date-times
+/// (e.g. `2024-05-12T09:41:07.250Z`).
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Timestamp(pub i64);
+
+impl Timestamp {
+    /// The number of seconds elapsed since an earlier timestamp
+    pub fn seconds_since(self, earlier: Timestamp) -> f64 {
+        (self.0 - earlier.0) as f64 / 1000.0
+    }
+}
+
+/// Number of days since the Unix epoch of a proleptic Gregorian date (H. Hinnant's algorithm)
+pub(crate) fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year - era * 400;
+    let doy = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+/// Proleptic Gregorian date of a number of days since the Unix epoch (H. Hinnant's algorithm)
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let doe = days - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+fn is_leap_year(year: i64) -> bool {
+    year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// An error occurring while parsing a timestamp.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseTimestampError;
+
+impl error::Error for ParseTimestampError {}
+
+impl fmt::Display for ParseTimestampError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("invalid RFC 3339 date-time")
+    }
+}
+
+/// Parse a fixed number of ASCII digits
+fn digits(value: &[u8]) -> Result<i64, ParseTimestampError> {
+    value.iter().try_fold(0, |acc, c| match c {
+        b'0'..=b'9' => Ok(acc * 10 + (c - b'0') as i64),
+        _ => Err(ParseTimestampError),
+    })
+}
+
+impl str::FromStr for Timestamp {
+    type Err = ParseTimestampError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim().as_bytes();
+        if s.len() < 20 || s[4] != b'-' || s[7] != b'-' || s[13] != b':' || s[16] != b':' {
+            return Err(ParseTimestampError);
+        }
+        if !matches!(s[10], b'T' | b't' | b' ') {
+            return Err(ParseTimestampError);
+        }
+
+        let year = digits(&s[0..4])?;
+        let month = digits(&s[5..7])?;
+        let day = digits(&s[8..10])?;
+        let hour = digits(&s[11..13])?;
+        let minute = digits(&s[14..16])?;
+        // allow for leap seconds
+        let second = digits(&s[17..19])?;
+
+        if !(1..=12).contains(&month)
+            || !(1..=days_in_month(year, month)).contains(&day)
+            || hour > 23
+            || minute > 59
+            || second > 60
+        {
+            return Err(ParseTimestampError);
+        }
+
+        let mut rest = &s[19..];
+
+        // fractional seconds, down to the millisecond
+        let mut millis = 0;
+        if let Some((b'.', fraction)) = rest.split_first() {
+            let count = fraction.iter().take_while(|c| c.is_ascii_digit()).count();
+            if count == 0 {
+                return Err(ParseTimestampError);
+            }
+            for (i, c) in fraction[..count].iter().take(3).enumerate() {
+                millis += (c - b'0') as i64 * [100, 10, 1][i];
+            }
+            rest = &fraction[count..];
+        }
+
+        let offset = match rest {
+            [b'Z' | b'z'] => 0,
+            [sign @ (b'+' | b'-'), h1, h2, b':', m1, m2] => {
+                let hours = digits(&[*h1, *h2])?;
+                let minutes = digits(&[*m1, *m2])?;
+                if hours > 23 || minutes > 59 {
+                    return Err(ParseTimestampError);
+                }
+                let offset = hours * 60 + minutes;
+                if *sign == b'-' {
+                    -offset
+                } else {
+                    offset
+                }
+            }
+            _ => return Err(ParseTimestampError),
+        };
+
+        let seconds =
+            days_from_civil(year, month, day) * 86400 + hour * 3600 + minute * 60 + second
+                - offset * 60;
+
+        Ok(Timestamp(seconds * 1000 + millis))
+    }
+}
+
+impl fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let millis = self.0.rem_euclid(1000);
+        let seconds = self.0.div_euclid(1000);
+        let (year, month, day) = civil_from_days(seconds.div_euclid(86400));
+        let seconds = seconds.rem_euclid(86400);
+
+        write!(
+            f,
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+            year,
+            month,
+            day,
+            seconds / 3600,
+            seconds / 60 % 60,
+            seconds % 60,
+        )?;
+        if millis != 0 {
+            write!(f, ".{:03}", millis)?;
+        }
+        f.write_str("Z")
+    }
+}
+
+/// Speed (in meters per second) under which one is considered not moving.
+const MOVING_SPEED: f64 = 0.5;
+
+/// Distance (in meters) of the splits.
+const SPLIT_DISTANCE: f64 = 1000.0;
+
+///
+/// Timing statistics of a straight line mission, computed over the timed track points en route.
+///
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Timing {
+    /// Time (in seconds) from the first to the last timed point en route.
+    pub duration: f64,
+    /// Time (in seconds) spent moving faster than 0.5 m/s.
+    pub moving_time: f64,
+    /// Average speed (in meters per second) along the route ("made good" distance over duration).
+    pub average_speed: f64,
+    /// Time (in seconds) of each complete kilometer along the route (first time the "made good"
+    /// distance reaches each kilometer, linearly interpolated between points).
+    pub splits: Vec<f64>,
+}
+
+impl Slm {
+    /// Compute the timing statistics of the mission.
+    ///
+    /// Return `None` if there are less than two timed track points en route, or if they do not
+    /// span a positive duration.
+    pub fn timing(&self) -> Option<Timing> {
+        let mut timed = self.track.iter().filter_map(|point| match point {
+            Point {
+                time: Some(time),
+                progress: Progress::EnRoute { made_good, .. },
+                coordinates,
+                ..
+            } => Some((*time, *made_good, *coordinates)),
+            _ => None,
+        });
+
+        let first = timed.next()?;
+        let (first_time, first_made_good, _) = first;
+
+        let mut last = first;
+        let mut moving_time = 0_f64;
+        let mut splits = Vec::new();
+        let mut split_start = first_time.0 as f64;
+        let mut split_end = SPLIT_DISTANCE * (libm::floor(first_made_good / SPLIT_DISTANCE) + 1.0);
+
+        for point in timed {
+            let (time1, made_good1, coordinates1) = last;
+            let (time2, made_good2, coordinates2) = point;
+
+            let elapsed = time2.seconds_since(time1);
+            if elapsed > 0.0 && coordinates1.distance(coordinates2) >= MOVING_SPEED * elapsed {
+                moving_time += elapsed;
+            }
+
+            while made_good2 >= split_end && made_good1 < split_end {
+                let ratio = (split_end - made_good1) / (made_good2 - made_good1);
+                let time = time1.0 as f64 + ratio * (time2.0 - time1.0) as f64;
+                splits.push((time - split_start) / 1000.0);
+                split_start = time;
+                split_end += SPLIT_DISTANCE;
+            }
+
+            last = point;
+        }
+
+        let (last_time, last_made_good, _) = last;
+        let duration = last_time.seconds_since(first_time);
+        if duration <= 0.0 {
+            return None;
+        }
+
+        Some(Timing {
+            duration,
+            moving_time,
+            average_speed: (last_made_good - first_made_good) / duration,
+            splits,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{analyze, Coordinates, TrackPoint};
+    use alloc::{string::ToString, vec};
+    use approx::assert_abs_diff_eq;
+
+    macro_rules! timestamp_tests {
+        ($($name:ident: $s:literal => $millis:expr, $display:literal)*) => {
+        $(
+            #[test]
+            fn $name() {
+                let timestamp = $s.parse::<Timestamp>().unwrap();
+                assert_eq!(timestamp, Timestamp($millis));
+                assert_eq!(timestamp.to_string(), $display);
+            }
+        )*
+        }
+    }
+    timestamp_tests! {
+        timestamp_epoch: "1970-01-01T00:00:00Z" => 0, "1970-01-01T00:00:00Z"
+        timestamp_before_epoch: "1969-12-31T23:59:59.5Z" => -500, "1969-12-31T23:59:59.500Z"
+        timestamp_millis: "2024-05-12T09:41:07.250Z" => 1715506867250, "2024-05-12T09:41:07.250Z"
+        timestamp_micros: "2024-05-12T09:41:07.250999Z" => 1715506867250, "2024-05-12T09:41:07.250Z"
+        timestamp_offset: "2024-05-12T11:41:07+02:00" => 1715506867000, "2024-05-12T09:41:07Z"
+        timestamp_negative_offset: "2024-05-12T04:11:07-05:30" => 1715506867000, "2024-05-12T09:41:07Z"
+        timestamp_leap_day: "2024-02-29T12:00:00Z" => 1709208000000, "2024-02-29T12:00:00Z"
+    }
+
+    macro_rules! timestamp_err_tests {
+        ($($name:ident: $s:literal)*) => {
+        $(
+            #[test]
+            fn $name() {
+                assert_eq!($s.parse::<Timestamp>(), Err(ParseTimestampError));
+            }
+        )*
+        }
+    }
+    timestamp_err_tests! {
+        timestamp_no_zone: "2024-05-12T09:41:07"
+        timestamp_no_leap_day: "2023-02-29T12:00:00Z"
+        timestamp_bad_month: "2024-13-12T09:41:07Z"
+        timestamp_bad_hour: "2024-05-12T24:41:07Z"
+        timestamp_bad_fraction: "2024-05-12T09:41:07.Z"
+        timestamp_garbage: "yesterday, around noon"
+    }
+
+    fn timed(latitude: f64, seconds: i64) -> TrackPoint {
+        TrackPoint {
+            coordinates: Coordinates {
+                latitude,
+                longitude: 7.0,
+            },
+            time: Some(Timestamp(1715506867000 + seconds * 1000)),
+            elevation: None,
+            speed: None,
+        }
+    }
+
+    #[test]
+    fn timing() {
+        let start = Coordinates {
+            latitude: 45.0,
+            longitude: 7.0,
+        };
+        let end = Coordinates {
+            latitude: 45.03,
+            longitude: 7.0,
+        };
+        // ~1111 m per 0.01 degree of latitude, the route start is en route, its end is arrived
+        let slm = analyze(
+            start,
+            end,
+            vec![
+                timed(44.999, 0),
+                timed(45.0, 0),
+                timed(45.01, 600),
+                // a 10 minutes break
+                timed(45.01, 1200),
+                timed(45.02, 1800),
+                timed(45.03, 2400),
+                timed(45.031, 2500),
+            ],
+        );
+
+        let timing = slm.timing().unwrap();
+        assert_abs_diff_eq!(timing.duration, 1800.0);
+        assert_abs_diff_eq!(timing.moving_time, 1200.0);
+        assert_abs_diff_eq!(
+            timing.average_speed,
+            2.0 * slm.route_length / 3.0 / 1800.0,
+            epsilon = 1e-2
+        );
+        assert_eq!(timing.splits.len(), 2);
+        assert!(timing.splits[0] > 500.0 && timing.splits[0] < 600.0);
+        assert!(timing.splits[1] > 1100.0 && timing.splits[1] < 1200.0);
+    }
+
+    #[test]
+    fn timing_untimed() {
+        let start = Coordinates {
+            latitude: 45.0,
+            longitude: 7.0,
+        };
+        let end = Coordinates {
+            latitude: 45.03,
+            longitude: 7.0,
+        };
+        let slm = analyze(start, end, [start, end]);
+        assert_eq!(slm.timing(), None);
+    }
+}
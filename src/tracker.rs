@@ -0,0 +1,298 @@
+// Copyright 2024 Barbagus
+//
+// This file is part of slmlib.
+//
+// slmlib is free software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// slmlib is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without even
+// the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU General
+// Public License for more details.
+// You should have received a copy of the GNU General Public License along with slmlib. If not, see
+// <https://www.gnu.org/licenses/>.
+//! Streaming analysis of a straight line mission
+use crate::{
+    burdell::{self, BurdellSettings, ScoreError},
+    geowizard::{Rank, RankScheme},
+    AnalyzeError, AnalyzeSettings, Analyzer, Coordinates, Progress, TrackPoint,
+};
+use core::{error, fmt};
+
+/// The state of a straight line mission after a track point
+#[derive(Debug, Clone)]
+pub struct TrackerUpdate {
+    /// The progress of the track point
+    pub progress: Progress,
+    /// The max deviation so far
+    pub max_deviation: f64,
+    /// The rank so far, in the tracker's rank scheme
+    pub rank: Option<Rank>,
+    /// The Burdell score estimate so far (considering the remaining of the route is perfectly
+    /// followed)
+    pub score: f64,
+}
+
+/// An error occurring while starting to track a straight line mission.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TrackerError {
+    Analyze(AnalyzeError),
+    /// The Burdell score settings do not fit the route (e.g. far too many segments).
+    Score(ScoreError),
+}
+
+impl error::Error for TrackerError {}
+
+impl fmt::Display for TrackerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TrackerError::Analyze(error) => write!(f, "analysis: {}", error),
+            TrackerError::Score(error) => write!(f, "burdell score: {}", error),
+        }
+    }
+}
+
+impl From<AnalyzeError> for TrackerError {
+    fn from(value: AnalyzeError) -> Self {
+        TrackerError::Analyze(value)
+    }
+}
+
+impl From<ScoreError> for TrackerError {
+    fn from(value: ScoreError) -> Self {
+        TrackerError::Score(value)
+    }
+}
+
+/// A straight line mission analysis, fed one track point at a time (e.g. live during an attempt).
+///
+/// Unlike [`analyze`](crate::analyze), track points are not retained: memory is bounded by the
+/// route length (for the Burdell score estimate) regardless of the track length.
+#[derive(Debug)]
+pub struct SlmTracker {
+    analyzer: Analyzer,
+    max_deviation: f64,
+    score: burdell::RunningScore,
+    rank_scheme: RankScheme,
+}
+
+impl SlmTracker {
+    /// Start tracking a straight line mission, with default analysis settings, the "Pro" Burdell
+    /// score settings and GeoWizard's rank scheme
+    pub fn new(start: Coordinates, end: Coordinates) -> Result<Self, TrackerError> {
+        Self::with_settings(AnalyzeSettings::default(), burdell::LVL_PRO, start, end)
+    }
+
+    /// Start tracking a straight line mission, with the given settings
+    pub fn with_settings(
+        settings: AnalyzeSettings,
+        config: BurdellSettings,
+        start: Coordinates,
+        end: Coordinates,
+    ) -> Result<Self, TrackerError> {
+        let analyzer = Analyzer::new(settings, start, end)?;
+        let score = burdell::RunningScore::new(config, analyzer.route_length)?;
+        Ok(Self {
+            analyzer,
+            max_deviation: 0.0,
+            score,
+            rank_scheme: RankScheme::geowizard(),
+        })
+    }
+
+    /// The same tracker, ranking with another scheme
+    pub fn with_rank_scheme(self, rank_scheme: RankScheme) -> Self {
+        Self {
+            rank_scheme,
+            ..self
+        }
+    }
+
+    /// The route length
+    pub fn route_length(&self) -> f64 {
+        self.analyzer.route_length
+    }
+
+    /// Feed the next track point
+    pub fn update<P>(&mut self, point: P) -> Result<TrackerUpdate, AnalyzeError>
+    where
+        P: Into<TrackPoint>,
+    {
+        let progress = self.analyzer.progress(point.into().coordinates)?;
+
+        if let Progress::EnRoute { made_good, .. } = progress {
+            let deviation = progress.deviation().unwrap_or(0.0);
+            if deviation > self.max_deviation {
+                self.max_deviation = deviation;
+            }
+            self.score.update(made_good, deviation);
+        }
+
+        Ok(TrackerUpdate {
+            progress,
+            max_deviation: self.max_deviation,
+            rank: self.rank_scheme.rank(self.max_deviation),
+            score: self.score.score(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use crate::{analyze, files, geowizard};
+    use approx::assert_abs_diff_eq;
+    use std::{fs, path, vec};
+
+    fn tracker_test(name: &str) {
+        let base = path::Path::new("fixtures");
+
+        let sml = {
+            let path = base.join(name).with_extension("sml");
+            let buf = fs::read(path).expect("read SML file");
+            files::sml::load(&buf).expect("parse SML file")
+        };
+        let (start, end) = sml.route();
+
+        let mission = analyze(start, end, sml.track());
+
+        let mut tracker = SlmTracker::new(start, end).expect("start tracker");
+        let mut update = None;
+        for (coordinates, point) in sml.track().zip(&mission.track) {
+            let u = tracker.update(coordinates).expect("update tracker");
+            assert_eq!(u.progress.deviation(), point.progress.deviation());
+            update = Some(u);
+        }
+        let update = update.expect("non empty track");
+
+        assert_eq!(update.max_deviation, mission.max_deviation);
+        assert_eq!(update.rank, geowizard::compute_rank(&mission));
+        assert_abs_diff_eq!(
+            update.score,
+            burdell::compute_score(burdell::LVL_PRO, &mission),
+            epsilon = 1e-6
+        );
+    }
+
+    #[test]
+    fn tracker_running_score() {
+        let start = Coordinates {
+            latitude: 45.0,
+            longitude: 7.0,
+        };
+        let end = Coordinates {
+            latitude: 45.1,
+            longitude: 7.0,
+        };
+        let mut tracker =
+            SlmTracker::with_settings(AnalyzeSettings::default(), burdell::LVL_NEWBIE, start, end)
+                .unwrap();
+
+        let on_route = tracker
+            .update(Coordinates {
+                latitude: 45.05,
+                longitude: 7.0,
+            })
+            .unwrap();
+        assert_eq!(on_route.score, 100.0);
+        assert_eq!(on_route.rank, Some(Rank::Platinum));
+
+        let off_route = tracker
+            .update(Coordinates {
+                latitude: 45.06,
+                longitude: 7.0005,
+            })
+            .unwrap();
+        assert!(off_route.score < 100.0);
+        assert_eq!(off_route.rank, Some(Rank::Gold));
+
+        let back_on_route = tracker
+            .update(Coordinates {
+                latitude: 45.07,
+                longitude: 7.0,
+            })
+            .unwrap();
+        assert!(back_on_route.score > off_route.score);
+        assert_eq!(back_on_route.max_deviation, off_route.max_deviation);
+    }
+
+    #[test]
+    fn tracker_rank_scheme() {
+        let start = Coordinates {
+            latitude: 45.0,
+            longitude: 7.0,
+        };
+        let end = Coordinates {
+            latitude: 45.1,
+            longitude: 7.0,
+        };
+        let cycling = RankScheme::new(vec![
+            geowizard::RankTier::new(Rank::Gold, 50.0),
+            geowizard::RankTier::new(Rank::Silver, 100.0),
+        ])
+        .unwrap();
+        let point = Coordinates {
+            latitude: 45.05,
+            longitude: 7.0002,
+        };
+
+        let mut tracker = SlmTracker::new(start, end).unwrap();
+        assert_eq!(tracker.update(point).unwrap().rank, Some(Rank::Platinum));
+
+        let mut tracker = SlmTracker::new(start, end)
+            .unwrap()
+            .with_rank_scheme(cycling);
+        assert_eq!(tracker.update(point).unwrap().rank, Some(Rank::Gold));
+    }
+
+    #[test]
+    fn tracker_score_error() {
+        let start = Coordinates {
+            latitude: 45.0,
+            longitude: 7.0,
+        };
+        let end = Coordinates {
+            latitude: 45.1,
+            longitude: 7.0,
+        };
+        let config = BurdellSettings::new(1e-300, 200.0).unwrap();
+        assert!(matches!(
+            SlmTracker::with_settings(AnalyzeSettings::default(), config, start, end),
+            Err(TrackerError::Score(ScoreError::RouteLength(_)))
+        ));
+        assert!(matches!(
+            SlmTracker::new(start, start),
+            Err(TrackerError::Analyze(AnalyzeError::DegenerateRoute))
+        ));
+    }
+
+    macro_rules! tracker_tests {
+        ($($f:ident: $n:expr,)*) => {
+        $(
+            #[test]
+            fn $f() {
+                tracker_test($n)
+            }
+        )*
+        }
+    }
+    tracker_tests! {
+        tracker_archie_iom: "archie-iom",
+        tracker_archie_scotland: "archie-scotland",
+        tracker_archie_wales_run: "archie-wales-run",
+        tracker_archie_wales_walk: "archie-wales-walk",
+        tracker_geowizard_iom: "geowizard-iom",
+        tracker_geowizard_norway: "geowizard-norway",
+        tracker_geowizard_scotland: "geowizard-scotland",
+        tracker_geowizard_wales1a: "geowizard-wales1a",
+        tracker_geowizard_wales1b: "geowizard-wales1b",
+        tracker_geowizard_wales2: "geowizard-wales2",
+        tracker_geowizard_wales3: "geowizard-wales3",
+        tracker_geowizard_wales4: "geowizard-wales4",
+        tracker_hiiumaa: "hiiumaa",
+        tracker_muhu: "muhu",
+        tracker_new_forest: "new-forest",
+    }
+}